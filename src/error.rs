@@ -0,0 +1,147 @@
+use crate::vulkan::capabilities::CapabilityReport;
+use ash::vk;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug)]
+pub enum PotatoError {
+    Vulkan(vk::Result),
+    Loading(ash::LoadingError),
+    MissingCapabilities(CapabilityReport),
+    NoSuitableDevice,
+    NoSuitableQueueFamily,
+    NoSuitableMemoryType,
+    NoSuitableDepthFormat,
+    UnsupportedWindow,
+    BufferOverflow {
+        size: u64,
+        capacity: u64,
+    },
+    MemoryNotMapped,
+    EmptyMesh,
+    InvalidSetting {
+        name: String,
+        value: String,
+    },
+    InvalidTexture {
+        width: u32,
+        height: u32,
+        size: usize,
+    },
+    ModelLoad {
+        path: String,
+        message: String,
+    },
+    ShaderIo {
+        path: String,
+        source: std::io::Error,
+    },
+    FileIo {
+        path: String,
+        source: std::io::Error,
+    },
+    Image(image::ImageError),
+    Window(winit::error::OsError),
+    Thread(std::io::Error),
+}
+
+impl Display for PotatoError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PotatoError::Vulkan(result) => write!(f, "Vulkan call failed: {}", result),
+            PotatoError::Loading(error) => write!(f, "Failed to load Vulkan: {}", error),
+            PotatoError::MissingCapabilities(report) => write!(f, "Missing {}", report),
+            PotatoError::NoSuitableDevice => write!(f, "Failed to find a compatable device"),
+            PotatoError::NoSuitableQueueFamily => {
+                write!(f, "Failed to find a queue family with the required support")
+            }
+            PotatoError::NoSuitableMemoryType => {
+                write!(
+                    f,
+                    "Failed to find a memory type with the required properties"
+                )
+            }
+            PotatoError::NoSuitableDepthFormat => {
+                write!(f, "Failed to find a depth format usable as an attachment")
+            }
+            PotatoError::UnsupportedWindow => {
+                write!(
+                    f,
+                    "Window does not expose a handle for the enabled surface platform"
+                )
+            }
+            PotatoError::BufferOverflow { size, capacity } => write!(
+                f,
+                "Data of {} bytes does not fit in buffer of {} bytes",
+                size, capacity
+            ),
+            PotatoError::MemoryNotMapped => {
+                write!(f, "Allocation is not host visible and coherent")
+            }
+            PotatoError::EmptyMesh => write!(f, "Mesh has no vertices or indices"),
+            PotatoError::InvalidSetting { name, value } => {
+                write!(f, "Invalid {} setting {:?}", name, value)
+            }
+            PotatoError::InvalidTexture {
+                width,
+                height,
+                size,
+            } => write!(
+                f,
+                "Texture of {}x{} texels does not match {} bytes of RGBA pixels",
+                width, height, size
+            ),
+            PotatoError::ModelLoad { path, message } => {
+                write!(f, "Failed to load model {:?}: {}", path, message)
+            }
+            PotatoError::ShaderIo { path, source } => {
+                write!(f, "Failed to read shader {:?}: {}", path, source)
+            }
+            PotatoError::FileIo { path, source } => {
+                write!(f, "Failed to write file {:?}: {}", path, source)
+            }
+            PotatoError::Image(error) => write!(f, "Image error: {}", error),
+            PotatoError::Window(error) => write!(f, "Failed to create window: {}", error),
+            PotatoError::Thread(error) => write!(f, "Failed to start thread: {}", error),
+        }
+    }
+}
+
+impl Error for PotatoError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PotatoError::Vulkan(result) => Some(result),
+            PotatoError::Loading(error) => Some(error),
+            PotatoError::ShaderIo { source, .. } => Some(source),
+            PotatoError::FileIo { source, .. } => Some(source),
+            PotatoError::Image(error) => Some(error),
+            PotatoError::Window(error) => Some(error),
+            PotatoError::Thread(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<vk::Result> for PotatoError {
+    fn from(result: vk::Result) -> Self {
+        PotatoError::Vulkan(result)
+    }
+}
+
+impl From<ash::LoadingError> for PotatoError {
+    fn from(error: ash::LoadingError) -> Self {
+        PotatoError::Loading(error)
+    }
+}
+
+impl From<image::ImageError> for PotatoError {
+    fn from(error: image::ImageError) -> Self {
+        PotatoError::Image(error)
+    }
+}
+
+impl From<winit::error::OsError> for PotatoError {
+    fn from(error: winit::error::OsError) -> Self {
+        PotatoError::Window(error)
+    }
+}
@@ -1,9 +1,13 @@
+use super::scene::TextureImage;
+use crate::error::PotatoError;
 use std::fs::{File, OpenOptions};
-use std::io::{Result, Read, Write};
+use std::io::{Read, Result, Write};
 
 pub fn read_file_to_bytes(path: &str) -> Result<Vec<u8>> {
-    let file = File::open(path).unwrap_or_else(|_| panic!("Failed to read spv file {:?}", path));
-    Ok(file.bytes().filter_map(|b| b.ok()).collect())
+    let mut file = File::open(path)?;
+    let mut contents = Vec::new();
+    file.read_to_end(&mut contents)?;
+    Ok(contents)
 }
 
 pub fn read_file_to_str(path: &str) -> Result<String> {
@@ -19,7 +23,33 @@ pub fn write_file(path: &str, contents: &str) -> Result<bool> {
     Ok(true)
 }
 
-pub fn append_file(path: &str, contents: &str) -> Result<()>{
-    let mut file = OpenOptions::new().append(true).open(path).expect("Failed to open file");
-    file.write_all(contents.as_bytes())
-}
\ No newline at end of file
+pub fn append_file(path: &str, contents: &str) -> std::result::Result<(), PotatoError> {
+    OpenOptions::new()
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(contents.as_bytes()))
+        .map_err(|source| PotatoError::FileIo {
+            path: path.to_string(),
+            source,
+        })
+}
+
+//Alpha is dropped so the same pixels can be written as either PNG or PPM
+pub fn write_image(path: &str, width: u32, height: u32, rgba: &[u8]) -> image::ImageResult<()> {
+    let rgb: Vec<u8> = rgba
+        .chunks_exact(4)
+        .flat_map(|x| x[..3].to_owned())
+        .collect();
+    image::save_buffer(path, &rgb, width, height, image::ColorType::Rgb8)
+}
+
+//Decodes PNG or JPEG, whatever the source layout the pixels come out as RGBA8
+pub fn read_image(path: &str) -> image::ImageResult<TextureImage> {
+    let rgba = image::open(path)?.into_rgba8();
+    Ok(TextureImage {
+        name: path.to_string(),
+        width: rgba.width(),
+        height: rgba.height(),
+        pixels: rgba.into_raw(),
+    })
+}
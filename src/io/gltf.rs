@@ -0,0 +1,265 @@
+use super::scene::{
+    Camera, Node, PbrMaterial, Primitive, Projection, Scene, SceneMesh, Texture, TextureImage,
+};
+use crate::error::PotatoError;
+use crate::vulkan::mesh::Mesh;
+use crate::vulkan::vertex::Vertex;
+use ash::vk::{Filter, SamplerAddressMode, SamplerMipmapMode};
+use gltf::buffer::Data;
+use gltf::image::Format;
+use gltf::mesh::Mode;
+use gltf::texture::{MagFilter, MinFilter, WrappingMode};
+use log::{debug, warn};
+use std::path::Path;
+
+//Loads .gltf with external or embedded buffers as well as binary .glb files
+pub fn load_gltf<P: AsRef<Path>>(path: P) -> Result<Scene, PotatoError> {
+    let path = path.as_ref();
+    let model_load_error = |message: String| PotatoError::ModelLoad {
+        path: path.display().to_string(),
+        message,
+    };
+
+    let (document, buffers, images) =
+        gltf::import(path).map_err(|e| model_load_error(e.to_string()))?;
+
+    let mut scene = Scene {
+        materials: document.materials().map(convert_material).collect(),
+        textures: document.textures().map(convert_texture).collect(),
+        cameras: document.cameras().map(convert_camera).collect(),
+        ..Default::default()
+    };
+
+    for (image, data) in document.images().zip(images) {
+        scene.images.push(TextureImage {
+            name: image.name().unwrap_or_default().to_string(),
+            width: data.width,
+            height: data.height,
+            pixels: expand_to_rgba8(data.format, &data.pixels).ok_or_else(|| {
+                model_load_error(format!("Unsupported image format {:?}", data.format))
+            })?,
+        });
+    }
+
+    for mesh in document.meshes() {
+        let mut scene_mesh = SceneMesh {
+            name: mesh.name().unwrap_or_default().to_string(),
+            primitives: vec![],
+        };
+
+        for primitive in mesh.primitives() {
+            let material = primitive.material().index();
+            let base_color = material.map_or([1.0, 1.0, 1.0, 1.0], |x| {
+                scene.materials[x].base_color_factor
+            });
+            match read_primitive(&primitive, &buffers, base_color) {
+                Some(primitive_mesh) => scene_mesh.primitives.push(Primitive {
+                    mesh: primitive_mesh,
+                    material,
+                }),
+                None => warn!(
+                    "Skipping {:?} primitive {} of mesh {:?}",
+                    primitive.mode(),
+                    primitive.index(),
+                    scene_mesh.name
+                ),
+            }
+        }
+
+        debug!(
+            "Loaded mesh {:?} with {} primitives",
+            scene_mesh.name,
+            scene_mesh.primitives.len()
+        );
+        scene.meshes.push(scene_mesh);
+    }
+
+    for node in document.nodes() {
+        scene.nodes.push(Node {
+            name: node.name().unwrap_or_default().to_string(),
+            transform: node.transform().matrix().into(),
+            children: node.children().map(|x| x.index()).collect(),
+            mesh: node.mesh().map(|x| x.index()),
+            camera: node.camera().map(|x| x.index()),
+        });
+    }
+
+    //Without a scene every node that is nobody's child is a root
+    scene.roots = match document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+    {
+        Some(gltf_scene) => gltf_scene.nodes().map(|x| x.index()).collect(),
+        None => {
+            let mut is_child = vec![false; scene.nodes.len()];
+            for node in &scene.nodes {
+                for child in &node.children {
+                    is_child[*child] = true;
+                }
+            }
+            (0..scene.nodes.len()).filter(|x| !is_child[*x]).collect()
+        }
+    };
+
+    Ok(scene)
+}
+
+//Returns None for point and line primitives or primitives without positions
+fn read_primitive(
+    primitive: &gltf::Primitive,
+    buffers: &[Data],
+    base_color: [f32; 4],
+) -> Option<Mesh> {
+    let reader = primitive.reader(|x| Some(&buffers[x.index()]));
+    let positions: Vec<[f32; 3]> = reader.read_positions()?.collect();
+
+    let mut normals = reader.read_normals().into_iter().flatten();
+    let mut tex_coords = reader
+        .read_tex_coords(0)
+        .map(|x| x.into_f32())
+        .into_iter()
+        .flatten();
+    let mut colors = reader
+        .read_colors(0)
+        .map(|x| x.into_rgb_f32())
+        .into_iter()
+        .flatten();
+
+    let vertices: Vec<Vertex> = positions
+        .iter()
+        .map(|x| Vertex {
+            pos: *x,
+            color: colors
+                .next()
+                .unwrap_or([base_color[0], base_color[1], base_color[2]]),
+            normal: normals.next().unwrap_or([0.0, 0.0, 0.0]),
+            //glTF already puts uv (0, 0) at the top left like Vulkan
+            tex_coord: tex_coords.next().unwrap_or([0.0, 0.0]),
+        })
+        .collect();
+
+    let indices: Vec<u32> = match reader.read_indices() {
+        Some(x) => x.into_u32().collect(),
+        None => (0..vertices.len() as u32).collect(),
+    };
+
+    let indices = match primitive.mode() {
+        Mode::Triangles => indices,
+        Mode::TriangleStrip => (2..indices.len())
+            .flat_map(|i| {
+                //Every other triangle is flipped to keep the winding consistent
+                if i % 2 == 0 {
+                    [indices[i - 2], indices[i - 1], indices[i]]
+                } else {
+                    [indices[i - 1], indices[i - 2], indices[i]]
+                }
+            })
+            .collect(),
+        Mode::TriangleFan => (2..indices.len())
+            .flat_map(|i| [indices[i - 1], indices[i], indices[0]])
+            .collect(),
+        _ => return None,
+    };
+
+    Some(Mesh { vertices, indices })
+}
+
+fn convert_material(material: gltf::Material) -> PbrMaterial {
+    let pbr = material.pbr_metallic_roughness();
+    PbrMaterial {
+        name: material.name().unwrap_or_default().to_string(),
+        base_color_factor: pbr.base_color_factor(),
+        base_color_texture: pbr.base_color_texture().map(|x| x.texture().index()),
+        metallic_factor: pbr.metallic_factor(),
+        roughness_factor: pbr.roughness_factor(),
+        metallic_roughness_texture: pbr
+            .metallic_roughness_texture()
+            .map(|x| x.texture().index()),
+        normal_texture: material.normal_texture().map(|x| x.texture().index()),
+        normal_scale: material.normal_texture().map_or(1.0, |x| x.scale()),
+        double_sided: material.double_sided(),
+    }
+}
+
+fn convert_texture(texture: gltf::Texture) -> Texture {
+    let sampler = texture.sampler();
+    let (min_filter, mipmap_mode) = match sampler.min_filter() {
+        Some(MinFilter::Nearest) | Some(MinFilter::NearestMipmapNearest) => {
+            (Filter::NEAREST, SamplerMipmapMode::NEAREST)
+        }
+        Some(MinFilter::NearestMipmapLinear) => (Filter::NEAREST, SamplerMipmapMode::LINEAR),
+        Some(MinFilter::LinearMipmapNearest) => (Filter::LINEAR, SamplerMipmapMode::NEAREST),
+        _ => (Filter::LINEAR, SamplerMipmapMode::LINEAR),
+    };
+
+    Texture {
+        image: texture.source().index(),
+        mag_filter: match sampler.mag_filter() {
+            Some(MagFilter::Nearest) => Filter::NEAREST,
+            _ => Filter::LINEAR,
+        },
+        min_filter,
+        mipmap_mode,
+        address_mode_u: convert_wrapping_mode(sampler.wrap_s()),
+        address_mode_v: convert_wrapping_mode(sampler.wrap_t()),
+    }
+}
+
+fn convert_wrapping_mode(mode: WrappingMode) -> SamplerAddressMode {
+    match mode {
+        WrappingMode::ClampToEdge => SamplerAddressMode::CLAMP_TO_EDGE,
+        WrappingMode::MirroredRepeat => SamplerAddressMode::MIRRORED_REPEAT,
+        WrappingMode::Repeat => SamplerAddressMode::REPEAT,
+    }
+}
+
+fn convert_camera(camera: gltf::Camera) -> Camera {
+    let projection = match camera.projection() {
+        gltf::camera::Projection::Perspective(x) => Projection::Perspective {
+            yfov: x.yfov(),
+            aspect_ratio: x.aspect_ratio(),
+            znear: x.znear(),
+            zfar: x.zfar(),
+        },
+        gltf::camera::Projection::Orthographic(x) => Projection::Orthographic {
+            xmag: x.xmag(),
+            ymag: x.ymag(),
+            znear: x.znear(),
+            zfar: x.zfar(),
+        },
+    };
+
+    Camera {
+        name: camera.name().unwrap_or_default().to_string(),
+        projection,
+    }
+}
+
+//16 bit channels keep their high byte, float images are not supported
+fn expand_to_rgba8(format: Format, pixels: &[u8]) -> Option<Vec<u8>> {
+    let (channels, bytes_per_channel) = match format {
+        Format::R8 => (1, 1),
+        Format::R8G8 => (2, 1),
+        Format::R8G8B8 => (3, 1),
+        Format::R8G8B8A8 => (4, 1),
+        Format::R16 => (1, 2),
+        Format::R16G16 => (2, 2),
+        Format::R16G16B16 => (3, 2),
+        Format::R16G16B16A16 => (4, 2),
+        _ => return None,
+    };
+
+    let texel_size = channels * bytes_per_channel;
+    let mut rgba = Vec::with_capacity(pixels.len() / texel_size * 4);
+    for texel in pixels.chunks_exact(texel_size) {
+        //Channels are little endian so the high byte is the last one
+        let channel = |i: usize| texel[i * bytes_per_channel + bytes_per_channel - 1];
+        match channels {
+            1 => rgba.extend_from_slice(&[channel(0), channel(0), channel(0), 255]),
+            2 => rgba.extend_from_slice(&[channel(0), channel(1), 0, 255]),
+            3 => rgba.extend_from_slice(&[channel(0), channel(1), channel(2), 255]),
+            _ => rgba.extend_from_slice(&[channel(0), channel(1), channel(2), channel(3)]),
+        }
+    }
+    Some(rgba)
+}
@@ -1 +1,6 @@
-pub mod file;
\ No newline at end of file
+#[allow(dead_code)]
+pub mod file;
+pub mod gltf;
+pub mod model;
+pub mod obj;
+pub mod scene;
@@ -0,0 +1,21 @@
+use crate::vulkan::mesh::Mesh;
+
+#[derive(Debug, Clone, Default)]
+pub struct Material {
+    pub name: String,
+    pub ambient: [f32; 3],
+    pub diffuse: [f32; 3],
+    pub specular: [f32; 3],
+    pub shininess: f32,
+    pub dissolve: f32,
+    pub diffuse_texture: Option<String>,
+    pub normal_texture: Option<String>,
+}
+
+//A mesh plus the material of every triangle, face i uses indices 3i..3i+3
+#[derive(Debug, Clone, Default)]
+pub struct Model {
+    pub mesh: Mesh,
+    pub face_materials: Vec<Option<usize>>,
+    pub materials: Vec<Material>,
+}
@@ -0,0 +1,105 @@
+use super::model::{Material, Model};
+use crate::error::PotatoError;
+use crate::vulkan::vertex::Vertex;
+use log::{debug, warn};
+use std::path::Path;
+use tobj::{LoadOptions, GPU_LOAD_OPTIONS};
+
+//Polygons are triangulated and identical position/normal/uv triples share one index
+pub fn load_obj<P: AsRef<Path>>(path: P) -> Result<Model, PotatoError> {
+    let path = path.as_ref();
+    let load_options = LoadOptions {
+        ignore_points: true,
+        ignore_lines: true,
+        ..GPU_LOAD_OPTIONS
+    };
+
+    let (obj_models, obj_materials) =
+        tobj::load_obj(path, &load_options).map_err(|e| PotatoError::ModelLoad {
+            path: path.display().to_string(),
+            message: e.to_string(),
+        })?;
+
+    let materials: Vec<Material> = match obj_materials {
+        Ok(x) => x.into_iter().map(convert_material).collect(),
+        Err(e) => {
+            warn!("Failed to load materials for {:?}: {}", path, e);
+            vec![]
+        }
+    };
+
+    let mut model = Model {
+        materials,
+        ..Default::default()
+    };
+
+    for obj_model in obj_models {
+        let obj_mesh = obj_model.mesh;
+        let material_id = obj_mesh.material_id.filter(|x| *x < model.materials.len());
+        let material_color = material_id.map(|x| model.materials[x].diffuse);
+        let base_index = model.mesh.vertices.len() as u32;
+
+        debug!(
+            "Loaded {} with {} vertices and {} triangles",
+            obj_model.name,
+            obj_mesh.positions.len() / 3,
+            obj_mesh.indices.len() / 3
+        );
+
+        for i in 0..obj_mesh.positions.len() / 3 {
+            let vertex_color = if obj_mesh.vertex_color.len() >= 3 * i + 3 {
+                Some(read_vec3(&obj_mesh.vertex_color, i))
+            } else {
+                None
+            };
+            let normal = if obj_mesh.normals.len() >= 3 * i + 3 {
+                read_vec3(&obj_mesh.normals, i)
+            } else {
+                [0.0, 0.0, 0.0]
+            };
+            //OBJ puts v = 0 at the bottom of the image, Vulkan samples it from the top
+            let tex_coord = if obj_mesh.texcoords.len() >= 2 * i + 2 {
+                [
+                    obj_mesh.texcoords[2 * i],
+                    1.0 - obj_mesh.texcoords[2 * i + 1],
+                ]
+            } else {
+                [0.0, 0.0]
+            };
+
+            model.mesh.vertices.push(Vertex {
+                pos: read_vec3(&obj_mesh.positions, i),
+                color: vertex_color.or(material_color).unwrap_or([1.0, 1.0, 1.0]),
+                normal,
+                tex_coord,
+            });
+        }
+
+        model
+            .mesh
+            .indices
+            .extend(obj_mesh.indices.iter().map(|x| base_index + x));
+        model
+            .face_materials
+            .extend(std::iter::repeat_n(material_id, obj_mesh.indices.len() / 3));
+    }
+
+    Ok(model)
+}
+
+fn read_vec3(data: &[f32], i: usize) -> [f32; 3] {
+    [data[3 * i], data[3 * i + 1], data[3 * i + 2]]
+}
+
+fn convert_material(material: tobj::Material) -> Material {
+    Material {
+        name: material.name,
+        ambient: material.ambient.unwrap_or([0.0, 0.0, 0.0]),
+        diffuse: material.diffuse.unwrap_or([1.0, 1.0, 1.0]),
+        specular: material.specular.unwrap_or([0.0, 0.0, 0.0]),
+        shininess: material.shininess.unwrap_or(0.0),
+        dissolve: material.dissolve.unwrap_or(1.0),
+        diffuse_texture: material.diffuse_texture,
+        normal_texture: material.normal_texture,
+    }
+}
@@ -0,0 +1,229 @@
+use crate::vulkan::mesh::Mesh;
+use crate::vulkan::vertex::Vertex;
+use ash::vk::{Filter, SamplerAddressMode, SamplerMipmapMode};
+use cgmath::{InnerSpace, Matrix, Matrix3, Matrix4, Point3, SquareMatrix, Transform, Vector3};
+
+//Metallic-roughness material, texture fields index into Scene::textures
+#[derive(Debug, Clone)]
+pub struct PbrMaterial {
+    pub name: String,
+    pub base_color_factor: [f32; 4],
+    pub base_color_texture: Option<usize>,
+    pub metallic_factor: f32,
+    pub roughness_factor: f32,
+    pub metallic_roughness_texture: Option<usize>,
+    pub normal_texture: Option<usize>,
+    pub normal_scale: f32,
+    pub double_sided: bool,
+}
+
+impl Default for PbrMaterial {
+    fn default() -> Self {
+        PbrMaterial {
+            name: String::new(),
+            base_color_factor: [1.0, 1.0, 1.0, 1.0],
+            base_color_texture: None,
+            metallic_factor: 1.0,
+            roughness_factor: 1.0,
+            metallic_roughness_texture: None,
+            normal_texture: None,
+            normal_scale: 1.0,
+            double_sided: false,
+        }
+    }
+}
+
+//Decoded image, always expanded to RGBA8
+#[derive(Debug, Clone, Default)]
+pub struct TextureImage {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl TextureImage {
+    //1x1 image, white leaves vertex colors unchanged when sampled
+    pub fn solid(rgba: [u8; 4]) -> TextureImage {
+        TextureImage {
+            name: String::new(),
+            width: 1,
+            height: 1,
+            pixels: rgba.to_vec(),
+        }
+    }
+}
+
+//An image plus how to sample it, image indexes into Scene::images
+#[derive(Debug, Clone, Copy)]
+pub struct Texture {
+    pub image: usize,
+    pub mag_filter: Filter,
+    pub min_filter: Filter,
+    pub mipmap_mode: SamplerMipmapMode,
+    pub address_mode_u: SamplerAddressMode,
+    pub address_mode_v: SamplerAddressMode,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    Perspective {
+        yfov: f32,
+        aspect_ratio: Option<f32>,
+        znear: f32,
+        zfar: Option<f32>,
+    },
+    Orthographic {
+        xmag: f32,
+        ymag: f32,
+        znear: f32,
+        zfar: f32,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct Camera {
+    pub name: String,
+    pub projection: Projection,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Primitive {
+    pub mesh: Mesh,
+    pub material: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SceneMesh {
+    pub name: String,
+    pub primitives: Vec<Primitive>,
+}
+
+//Transform is relative to the parent node
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub name: String,
+    pub transform: Matrix4<f32>,
+    pub children: Vec<usize>,
+    pub mesh: Option<usize>,
+    pub camera: Option<usize>,
+}
+
+//One primitive placed in world space
+#[derive(Debug, Clone, Copy)]
+pub struct DrawItem {
+    pub node: usize,
+    pub mesh: usize,
+    pub primitive: usize,
+    pub transform: Matrix4<f32>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Scene {
+    pub nodes: Vec<Node>,
+    pub roots: Vec<usize>,
+    pub meshes: Vec<SceneMesh>,
+    pub materials: Vec<PbrMaterial>,
+    pub textures: Vec<Texture>,
+    pub images: Vec<TextureImage>,
+    pub cameras: Vec<Camera>,
+}
+
+impl Scene {
+    //World transform of every node reachable from the roots, None for detached nodes
+    pub fn world_transforms(&self) -> Vec<Option<Matrix4<f32>>> {
+        let mut world_transforms = vec![None; self.nodes.len()];
+        let mut stack: Vec<(usize, Matrix4<f32>)> = self
+            .roots
+            .iter()
+            .map(|x| (*x, Matrix4::identity()))
+            .collect();
+
+        while let Some((node_index, parent_transform)) = stack.pop() {
+            //glTF requires a tree, a revisited node means a broken file so stop instead of looping
+            if world_transforms[node_index].is_some() {
+                continue;
+            }
+            let node = &self.nodes[node_index];
+            let transform = parent_transform * node.transform;
+            world_transforms[node_index] = Some(transform);
+            stack.extend(node.children.iter().map(|x| (*x, transform)));
+        }
+
+        world_transforms
+    }
+
+    pub fn draw_list(&self) -> Vec<DrawItem> {
+        let mut draw_items = vec![];
+        for (node_index, world_transform) in self.world_transforms().into_iter().enumerate() {
+            let (transform, mesh) = match (world_transform, self.nodes[node_index].mesh) {
+                (Some(transform), Some(mesh)) => (transform, mesh),
+                _ => continue,
+            };
+            for primitive in 0..self.meshes[mesh].primitives.len() {
+                draw_items.push(DrawItem {
+                    node: node_index,
+                    mesh,
+                    primitive,
+                    transform,
+                });
+            }
+        }
+        draw_items
+    }
+
+    //Cameras in the scene with their world transform
+    pub fn camera_list(&self) -> Vec<(usize, Matrix4<f32>)> {
+        self.world_transforms()
+            .into_iter()
+            .zip(self.nodes.iter())
+            .filter_map(|(transform, node)| Some((node.camera?, transform?)))
+            .collect()
+    }
+
+    //Bakes every draw item into one mesh for renderers that take a single mesh
+    pub fn flatten(&self) -> Mesh {
+        let mut mesh = Mesh::default();
+
+        for draw_item in self.draw_list() {
+            let primitive = &self.meshes[draw_item.mesh].primitives[draw_item.primitive];
+            let normal_matrix = normal_matrix(&draw_item.transform);
+            let base_index = mesh.vertices.len() as u32;
+
+            mesh.vertices
+                .extend(primitive.mesh.vertices.iter().map(|x| {
+                    Vertex {
+                        pos: draw_item
+                            .transform
+                            .transform_point(Point3::from(x.pos))
+                            .into(),
+                        normal: transform_normal(&normal_matrix, x.normal),
+                        ..*x
+                    }
+                }));
+            mesh.indices
+                .extend(primitive.mesh.indices.iter().map(|x| base_index + x));
+        }
+
+        mesh
+    }
+}
+
+//Missing normals stay zero instead of turning into NaN
+fn transform_normal(normal_matrix: &Matrix3<f32>, normal: [f32; 3]) -> [f32; 3] {
+    let normal = normal_matrix * Vector3::from(normal);
+    if normal.magnitude2() > 0.0 {
+        normal.normalize().into()
+    } else {
+        normal.into()
+    }
+}
+
+fn normal_matrix(transform: &Matrix4<f32>) -> Matrix3<f32> {
+    let upper = Matrix3::from_cols(
+        transform.x.truncate(),
+        transform.y.truncate(),
+        transform.z.truncate(),
+    );
+    upper.invert().map_or(upper, |x| x.transpose())
+}
@@ -1,3 +1,4 @@
+mod error;
 mod io;
 mod vulkan;
 
@@ -6,22 +7,110 @@ use simple_logger::SimpleLogger;
 use vulkan::vulk_init::VulkanApiObjects;
 use winit::event_loop::EventLoop;
 
-pub fn init_graphics() {
-    SimpleLogger::new().env().init().unwrap();
+pub use error::PotatoError;
+pub use io::file::read_image;
+pub use io::gltf::load_gltf;
+pub use io::model::{Material, Model};
+pub use io::obj::load_obj;
+pub use io::scene::{
+    Camera, DrawItem, Node, PbrMaterial, Primitive, Projection, Scene, SceneMesh, Texture,
+    TextureImage,
+};
+pub use vulkan::allocator::HeapStats;
+pub use vulkan::capabilities::{
+    select_available, CapabilityReport, EnabledInstanceCapabilities, InstanceRequirements,
+};
+pub use vulkan::config::{GraphicsConfig, OffscreenConfig};
+pub use vulkan::debug_utils::{CommandLabel, PotatoDebugUtils, QueueLabel};
+pub use vulkan::device_features::{DeviceFeature, DeviceRequirements, EnabledDeviceFeatures};
+pub use vulkan::device_selection::{pick_device, DeviceCandidate, DeviceSelector};
+pub use vulkan::frame::{
+    available_recording_threads, record_default_frame, split_draws, FrameContext, RecordCallback,
+};
+pub use vulkan::frame_pacing::{choose_swapchain_image_count, ImagesInFlight};
+pub use vulkan::mesh::{Mesh, MeshBuffers};
+pub use vulkan::mipmap::{generate_mip_chain, mip_level_count, MipFilter};
+pub use vulkan::msaa::{clamp_sample_count, next_sample_count};
+pub use vulkan::present_mode::{choose_present_mode, PresentPolicy};
+pub use vulkan::queue_family::{pick_queue_families, QueueFamily};
+pub use vulkan::recording_threads::RecordingThreads;
+pub use vulkan::texture::{PotatoTexture, SamplerSettings};
+pub use vulkan::timeline::{is_timeline_enabled, submit_and_wait, GpuTimeline, Retirements};
+pub use vulkan::validation::{
+    clear_validation_sink, message_level, message_type_names, parse_message_severities,
+    parse_message_types, set_validation_sink, ValidationConfig, ValidationFeatures,
+    ValidationMessage, ValidationMode, ValidationObject,
+};
+pub use vulkan::vertex::Vertex;
+pub use vulkan::vulk_compute::{ComputeApiObjects, StorageBuffer};
+pub use vulkan::vulk_offscreen::OffscreenApiObjects;
+
+pub fn init_graphics(
+    mesh: &Mesh,
+    texture: Option<&TextureImage>,
+    config: &GraphicsConfig,
+) -> Result<(), PotatoError> {
+    init_graphics_with_recorder(mesh, texture, config, record_default_frame)
+}
+
+//Same as init_graphics with record_callback recording every frame instead of the default
+pub fn init_graphics_with_recorder<F>(
+    mesh: &Mesh,
+    texture: Option<&TextureImage>,
+    config: &GraphicsConfig,
+    record_callback: F,
+) -> Result<(), PotatoError>
+where
+    F: FnMut(&FrameContext) -> Result<(), PotatoError> + 'static,
+{
+    init_library_logger();
 
     debug!("Init event_loop");
     let event_loop = EventLoop::new();
     debug!("Init vulkan api objects");
-    let vulkan_api_objects = VulkanApiObjects::init(&event_loop);
+    let mut vulkan_api_objects = VulkanApiObjects::init(&event_loop, mesh, texture, config)?;
+    vulkan_api_objects.set_record_callback(record_callback);
     debug!("Done with init");
 
     debug!("Starting event loop");
     vulkan_api_objects.init_event_loop(event_loop);
+    Ok(())
 }
 
-pub fn init_compute() {
-    SimpleLogger::new().env().init().unwrap();
+pub fn init_compute() -> Result<ComputeApiObjects, PotatoError> {
+    init_library_logger();
 
-    debug!("Init vulkan api objects");
-    let vulkan_api_objects = VulkanApiObjects::init_compute();
+    debug!("Init compute api objects");
+    ComputeApiObjects::init()
+}
+
+pub fn init_offscreen(
+    width: u32,
+    height: u32,
+    mesh: &Mesh,
+    texture: Option<&TextureImage>,
+) -> Result<OffscreenApiObjects, PotatoError> {
+    init_library_logger();
+
+    debug!("Init offscreen api objects");
+    OffscreenApiObjects::init(width, height, mesh, texture)
+}
+
+pub fn render_offscreen(
+    path: &str,
+    width: u32,
+    height: u32,
+    mesh: &Mesh,
+    texture: Option<&TextureImage>,
+) -> Result<(), PotatoError> {
+    let mut offscreen_api_objects = init_offscreen(width, height, mesh, texture)?;
+    debug!("Rendering frame to {}", path);
+    offscreen_api_objects.save_frame(0.0, path)
+}
+
+//Potato can be embedded in other applications, a logger installed by the host is kept
+fn init_library_logger() {
+    if SimpleLogger::new().env().init().is_err() {
+        debug!("Logger already installed, keeping it");
+    }
 }
@@ -1,20 +1,102 @@
-mod io;
-mod vulkan;
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
 
-use log::debug;
-use simple_logger::SimpleLogger;
-use vulkan::vulk_init::VulkanApiObjects;
-use winit::event_loop::EventLoop;
+    let mesh = match args.iter().position(|x| x == "--obj") {
+        Some(i) => match args.get(i + 1).map(potato::load_obj) {
+            Some(Ok(model)) => model.mesh,
+            Some(Err(e)) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+            None => {
+                eprintln!("--obj expects a path to a .obj file");
+                std::process::exit(1);
+            }
+        },
+        None => match args.iter().position(|x| x == "--gltf") {
+            Some(i) => match args.get(i + 1).map(potato::load_gltf) {
+                Some(Ok(scene)) => scene.flatten(),
+                Some(Err(e)) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+                None => {
+                    eprintln!("--gltf expects a path to a .gltf or .glb file");
+                    std::process::exit(1);
+                }
+            },
+            None => potato::Mesh::quad(),
+        },
+    };
 
-fn main() {
-    SimpleLogger::new().env().init().unwrap();
+    let texture = match args.iter().position(|x| x == "--texture") {
+        Some(i) => match args.get(i + 1).map(|x| potato::read_image(x)) {
+            Some(Ok(texture)) => Some(texture),
+            Some(Err(e)) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+            None => {
+                eprintln!("--texture expects a path to a .png or .jpg file");
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+
+    let mut config = potato::GraphicsConfig::from_env();
+    if let Some(i) = args.iter().position(|x| x == "--present-mode") {
+        match args.get(i + 1).map(|x| x.parse()) {
+            Some(Ok(present_policy)) => config.present_policy = present_policy,
+            Some(Err(e)) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+            None => {
+                eprintln!("--present-mode expects one of vsync, immediate, mailbox or relaxed");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if let Some(i) = args.iter().position(|x| x == "--device") {
+        match args.get(i + 1).map(|x| x.parse()) {
+            Some(Ok(device)) => config.device = Some(device),
+            Some(Err(e)) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+            None => {
+                eprintln!("--device expects an index, a name or a vendor:device id");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if let Some(i) = args.iter().position(|x| x == "--validation") {
+        match args.get(i + 1).map(|x| x.parse()) {
+            Some(Ok(mode)) => config.validation.mode = mode,
+            Some(Err(e)) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+            None => {
+                eprintln!("--validation expects one of off, on or required");
+                std::process::exit(1);
+            }
+        }
+    }
 
-    debug!("Init event_loop");
-    let event_loop = EventLoop::new();
-    debug!("Init vulkan api objects");
-    let vulkan_api_objects = VulkanApiObjects::init(&event_loop);
-    debug!("Done with init");
+    let result = match args.iter().position(|x| x == "--offscreen") {
+        Some(i) => {
+            let path = args.get(i + 1).map_or("frame.png", |x| x.as_str());
+            potato::render_offscreen(path, 800, 600, &mesh, texture.as_ref())
+        }
+        None => potato::init_graphics(&mesh, texture.as_ref(), &config),
+    };
 
-    debug!("Starting event loop");
-    vulkan_api_objects.init_event_loop(event_loop);
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
 }
@@ -1,14 +1,17 @@
+use super::allocator::{Allocation, Allocator};
 use super::buffer::create_buffer;
-use super::swapchain::PotatoSwapChain;
+use super::debug_utils::PotatoDebugUtils;
+use super::texture::PotatoTexture;
+use crate::error::PotatoError;
 use ash::vk::{
-    Buffer, BufferUsageFlags, DescriptorBufferInfo, DescriptorPool, DescriptorPoolCreateFlags,
-    DescriptorPoolCreateInfo, DescriptorPoolSize, DescriptorSet, DescriptorSetAllocateInfo,
-    DescriptorSetLayout, DescriptorSetLayoutBinding, DescriptorSetLayoutCreateFlags,
-    DescriptorSetLayoutCreateInfo, DescriptorType, DeviceMemory, MemoryMapFlags,
-    MemoryPropertyFlags, PhysicalDeviceMemoryProperties, ShaderStageFlags, StructureType,
-    WriteDescriptorSet,
+    Buffer, BufferUsageFlags, DescriptorBufferInfo, DescriptorImageInfo, DescriptorPool,
+    DescriptorPoolCreateFlags, DescriptorPoolCreateInfo, DescriptorPoolSize, DescriptorSet,
+    DescriptorSetAllocateInfo, DescriptorSetLayout, DescriptorSetLayoutBinding,
+    DescriptorSetLayoutCreateFlags, DescriptorSetLayoutCreateInfo, DescriptorType, Extent2D,
+    ImageLayout, MemoryPropertyFlags, ShaderStageFlags, StructureType, WriteDescriptorSet,
 };
 use ash::Device;
+use bytemuck::{Pod, Zeroable};
 use cgmath::{perspective, Deg, Matrix4, Point3, Vector3};
 
 #[repr(C)]
@@ -19,14 +22,30 @@ pub struct UniformBufferObject {
     proj: Matrix4<f32>,
 }
 
-pub fn create_descriptor_set_layout(device: &Device) -> DescriptorSetLayout {
-    let ubo_layout_bindings = [DescriptorSetLayoutBinding {
-        binding: 0,
-        descriptor_type: DescriptorType::UNIFORM_BUFFER,
-        descriptor_count: 1,
-        stage_flags: ShaderStageFlags::VERTEX,
-        p_immutable_samplers: std::ptr::null(),
-    }];
+//cgmath matrices are repr(C) columns of f32, so the struct is 48 floats without padding
+unsafe impl Zeroable for UniformBufferObject {}
+unsafe impl Pod for UniformBufferObject {}
+
+pub fn create_descriptor_set_layout(
+    device: &Device,
+    debug_utils: &PotatoDebugUtils,
+) -> Result<DescriptorSetLayout, PotatoError> {
+    let ubo_layout_bindings = [
+        DescriptorSetLayoutBinding {
+            binding: 0,
+            descriptor_type: DescriptorType::UNIFORM_BUFFER,
+            descriptor_count: 1,
+            stage_flags: ShaderStageFlags::VERTEX,
+            p_immutable_samplers: std::ptr::null(),
+        },
+        DescriptorSetLayoutBinding {
+            binding: 1,
+            descriptor_type: DescriptorType::COMBINED_IMAGE_SAMPLER,
+            descriptor_count: 1,
+            stage_flags: ShaderStageFlags::FRAGMENT,
+            p_immutable_samplers: std::ptr::null(),
+        },
+    ];
 
     let ubo_layout_create_info = DescriptorSetLayoutCreateInfo {
         s_type: StructureType::DESCRIPTOR_SET_LAYOUT_CREATE_INFO,
@@ -36,44 +55,46 @@ pub fn create_descriptor_set_layout(device: &Device) -> DescriptorSetLayout {
         p_bindings: ubo_layout_bindings.as_ptr(),
     };
 
-    unsafe {
-        device
-            .create_descriptor_set_layout(&ubo_layout_create_info, None)
-            .expect("Failed to create Descriptor set layout")
-    }
+    let ubo_layout = unsafe { device.create_descriptor_set_layout(&ubo_layout_create_info, None)? };
+    debug_utils.name_object(ubo_layout, "Uniform descriptor set layout");
+    Ok(ubo_layout)
 }
 
 pub fn create_uniform_buffers(
     device: &Device,
-    device_memory_properties: &PhysicalDeviceMemoryProperties,
-    swapchain_image_count: usize,
-) -> (Vec<Buffer>, Vec<DeviceMemory>) {
+    debug_utils: &PotatoDebugUtils,
+    allocator: &mut Allocator,
+    frames_in_flight: usize,
+) -> Result<(Vec<Buffer>, Vec<Allocation>), PotatoError> {
     let buffer_size = std::mem::size_of::<UniformBufferObject>();
     let mut uniform_buffers = vec![];
-    let mut uniform_buffers_memory = vec![];
+    let mut uniform_buffers_allocations = vec![];
 
-    for _ in 0..swapchain_image_count {
-        let (uniform_buffer, uniform_buffer_memory) = create_buffer(
+    for i in 0..frames_in_flight {
+        let (uniform_buffer, uniform_buffer_allocation) = create_buffer(
             device,
+            debug_utils,
+            allocator,
             buffer_size as u64,
             BufferUsageFlags::UNIFORM_BUFFER,
             MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT,
-            device_memory_properties,
-        );
+            &[],
+            &format!("Uniform buffer {}", i),
+        )?;
         uniform_buffers.push(uniform_buffer);
-        uniform_buffers_memory.push(uniform_buffer_memory);
+        uniform_buffers_allocations.push(uniform_buffer_allocation);
     }
 
-    (uniform_buffers, uniform_buffers_memory)
+    Ok((uniform_buffers, uniform_buffers_allocations))
 }
 
+//Uniform buffers are persistently mapped so updating one is a plain memory copy
 pub fn update_uniform_buffer(
-    swapchain: &PotatoSwapChain,
-    device: &Device,
-    current_image: usize,
+    extent: Extent2D,
+    frame_index: usize,
     delta_time: f32,
-    uniform_buffers_memory: &Vec<DeviceMemory>,
-) {
+    uniform_buffers_allocations: &[Allocation],
+) -> Result<(), PotatoError> {
     let ubos = [UniformBufferObject {
         model: Matrix4::from_angle_z(Deg(90.0 * delta_time)),
         view: Matrix4::look_at_rh(
@@ -83,61 +104,57 @@ pub fn update_uniform_buffer(
         ),
         proj: perspective(
             Deg(45.0),
-            swapchain.swapchain_extent.width as f32 / swapchain.swapchain_extent.height as f32,
+            extent.width as f32 / extent.height as f32,
             0.1,
             10.0,
         ),
     }];
 
-    let buffer_size = (std::mem::size_of::<UniformBufferObject>() * ubos.len()) as u64;
-
-    unsafe {
-        let data_ptr = device
-            .map_memory(
-                uniform_buffers_memory[current_image],
-                0,
-                buffer_size,
-                MemoryMapFlags::empty(),
-            )
-            .expect("Failed to map memory") as *mut UniformBufferObject;
-
-        data_ptr.copy_from_nonoverlapping(ubos.as_ptr(), ubos.len());
-
-        device.unmap_memory(uniform_buffers_memory[current_image]);
-    }
+    uniform_buffers_allocations[frame_index].write(&ubos)
 }
 
-pub fn create_descriptor_pool(device: &Device, swapchain_images_size: usize) -> DescriptorPool {
-    let pool_sizes = [DescriptorPoolSize {
-        ty: DescriptorType::UNIFORM_BUFFER,
-        descriptor_count: swapchain_images_size as u32,
-    }];
+pub fn create_descriptor_pool(
+    device: &Device,
+    debug_utils: &PotatoDebugUtils,
+    frames_in_flight: usize,
+) -> Result<DescriptorPool, PotatoError> {
+    let pool_sizes = [
+        DescriptorPoolSize {
+            ty: DescriptorType::UNIFORM_BUFFER,
+            descriptor_count: frames_in_flight as u32,
+        },
+        DescriptorPoolSize {
+            ty: DescriptorType::COMBINED_IMAGE_SAMPLER,
+            descriptor_count: frames_in_flight as u32,
+        },
+    ];
 
     let descriptor_pool_create_info = DescriptorPoolCreateInfo {
         s_type: StructureType::DESCRIPTOR_POOL_CREATE_INFO,
         p_next: std::ptr::null(),
         flags: DescriptorPoolCreateFlags::empty(),
-        max_sets: swapchain_images_size as u32,
+        max_sets: frames_in_flight as u32,
         pool_size_count: pool_sizes.len() as u32,
         p_pool_sizes: pool_sizes.as_ptr(),
     };
 
-    unsafe {
-        device
-            .create_descriptor_pool(&descriptor_pool_create_info, None)
-            .expect("Failed to create descriptor pool")
-    }
+    let descriptor_pool =
+        unsafe { device.create_descriptor_pool(&descriptor_pool_create_info, None)? };
+    debug_utils.name_object(descriptor_pool, "Uniform descriptor pool");
+    Ok(descriptor_pool)
 }
 
 pub fn create_descriptor_sets(
     device: &Device,
+    debug_utils: &PotatoDebugUtils,
     descriptor_pool: DescriptorPool,
     descriptor_set_layout: DescriptorSetLayout,
-    uniform_buffers: &Vec<Buffer>,
-    swapchain_images_size: usize,
-) -> Vec<DescriptorSet> {
+    uniform_buffers: &[Buffer],
+    texture: &PotatoTexture,
+    frames_in_flight: usize,
+) -> Result<Vec<DescriptorSet>, PotatoError> {
     let mut layouts: Vec<DescriptorSetLayout> = vec![];
-    for _ in 0..swapchain_images_size {
+    for _ in 0..frames_in_flight {
         layouts.push(descriptor_set_layout);
     }
 
@@ -145,15 +162,13 @@ pub fn create_descriptor_sets(
         s_type: StructureType::DESCRIPTOR_SET_ALLOCATE_INFO,
         p_next: std::ptr::null(),
         descriptor_pool,
-        descriptor_set_count: swapchain_images_size as u32,
+        descriptor_set_count: frames_in_flight as u32,
         p_set_layouts: layouts.as_ptr(),
     };
 
-    let descriptor_sets = unsafe {
-        device
-            .allocate_descriptor_sets(&descriptor_set_allocate_info)
-            .expect("Failed to allocate descriptor sets")
-    };
+    let descriptor_sets =
+        unsafe { device.allocate_descriptor_sets(&descriptor_set_allocate_info)? };
+    debug_utils.name_objects(&descriptor_sets, "Uniform descriptor set");
 
     descriptor_sets.iter().enumerate().for_each(|(i, x)| {
         let descriptor_buffer_info = [DescriptorBufferInfo {
@@ -162,23 +177,43 @@ pub fn create_descriptor_sets(
             range: std::mem::size_of::<UniformBufferObject>() as u64,
         }];
 
-        let descriptor_write_sets = [WriteDescriptorSet {
-            s_type: StructureType::WRITE_DESCRIPTOR_SET,
-            p_next: std::ptr::null(),
-            dst_set: *x,
-            dst_binding: 0,
-            dst_array_element: 0,
-            descriptor_count: 1,
-            descriptor_type: DescriptorType::UNIFORM_BUFFER,
-            p_image_info: std::ptr::null(),
-            p_buffer_info: descriptor_buffer_info.as_ptr(),
-            p_texel_buffer_view: std::ptr::null(),
+        let descriptor_image_info = [DescriptorImageInfo {
+            sampler: texture.sampler,
+            image_view: texture.image_view,
+            image_layout: ImageLayout::SHADER_READ_ONLY_OPTIMAL,
         }];
 
+        let descriptor_write_sets = [
+            WriteDescriptorSet {
+                s_type: StructureType::WRITE_DESCRIPTOR_SET,
+                p_next: std::ptr::null(),
+                dst_set: *x,
+                dst_binding: 0,
+                dst_array_element: 0,
+                descriptor_count: 1,
+                descriptor_type: DescriptorType::UNIFORM_BUFFER,
+                p_image_info: std::ptr::null(),
+                p_buffer_info: descriptor_buffer_info.as_ptr(),
+                p_texel_buffer_view: std::ptr::null(),
+            },
+            WriteDescriptorSet {
+                s_type: StructureType::WRITE_DESCRIPTOR_SET,
+                p_next: std::ptr::null(),
+                dst_set: *x,
+                dst_binding: 1,
+                dst_array_element: 0,
+                descriptor_count: 1,
+                descriptor_type: DescriptorType::COMBINED_IMAGE_SAMPLER,
+                p_image_info: descriptor_image_info.as_ptr(),
+                p_buffer_info: std::ptr::null(),
+                p_texel_buffer_view: std::ptr::null(),
+            },
+        ];
+
         unsafe {
             device.update_descriptor_sets(&descriptor_write_sets, &[]);
         }
     });
 
-    descriptor_sets
+    Ok(descriptor_sets)
 }
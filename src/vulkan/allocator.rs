@@ -0,0 +1,678 @@
+use crate::error::PotatoError;
+use ash::vk::{
+    DeviceMemory, DeviceSize, MemoryAllocateInfo, MemoryMapFlags, MemoryPropertyFlags,
+    MemoryRequirements, PhysicalDevice, PhysicalDeviceMemoryProperties, Result as VkResult,
+    StructureType,
+};
+use ash::{Device, Instance};
+use bytemuck::Pod;
+use log::{debug, info, warn};
+use std::collections::HashMap;
+
+const DEFAULT_BLOCK_SIZE: DeviceSize = 64 * 1024 * 1024;
+const SMALL_HEAP_SIZE: DeviceSize = 1024 * 1024 * 1024;
+
+//Buffers and linear images live apart from optimal images so bufferImageGranularity never applies
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AllocationKind {
+    Linear,
+    Optimal,
+}
+
+//Like the raw Vulkan handles an allocation is a plain value, it must not be used once freed
+#[derive(Clone, Copy, Debug)]
+pub struct Allocation {
+    memory: DeviceMemory,
+    offset: DeviceSize,
+    size: DeviceSize,
+    mapped_ptr: *mut u8,
+    pool_key: (u32, AllocationKind),
+    block_id: u64,
+}
+
+//The mapped pointer stays valid on any thread until the owning block is released
+unsafe impl Send for Allocation {}
+//Being Copy and Send, sharing a reference across threads allows nothing a copy does not
+unsafe impl Sync for Allocation {}
+
+impl Allocation {
+    pub fn memory(&self) -> DeviceMemory {
+        self.memory
+    }
+
+    pub fn offset(&self) -> DeviceSize {
+        self.offset
+    }
+
+    //Host coherent memory stays mapped for the lifetime of its block, so writes and reads need no flush or invalidate
+    pub fn mapped_ptr(&self) -> Option<*mut u8> {
+        if self.mapped_ptr.is_null() {
+            None
+        } else {
+            Some(self.mapped_ptr)
+        }
+    }
+
+    //Pod keeps the bytes free of pointers and padding, the mapped memory needs no alignment for T
+    pub fn write<T: Pod>(&self, data: &[T]) -> Result<(), PotatoError> {
+        let bytes: &[u8] = bytemuck::cast_slice(data);
+        let data_size = bytes.len() as DeviceSize;
+        if data_size > self.size {
+            return Err(PotatoError::BufferOverflow {
+                size: data_size,
+                capacity: self.size,
+            });
+        }
+
+        let data_ptr = self.mapped_ptr().ok_or(PotatoError::MemoryNotMapped)?;
+        unsafe {
+            data_ptr.copy_from_nonoverlapping(bytes.as_ptr(), bytes.len());
+        }
+
+        Ok(())
+    }
+
+    //Any bytes are a valid T for Pod types, whatever the GPU wrote
+    pub fn read<T: Pod>(&self, count: usize) -> Result<Vec<T>, PotatoError> {
+        let data_size = count as DeviceSize * std::mem::size_of::<T>() as DeviceSize;
+        if data_size > self.size {
+            return Err(PotatoError::BufferOverflow {
+                size: data_size,
+                capacity: self.size,
+            });
+        }
+
+        let data_ptr = self.mapped_ptr().ok_or(PotatoError::MemoryNotMapped)?;
+        let mut data = vec![T::zeroed(); count];
+        let bytes: &mut [u8] = bytemuck::cast_slice_mut(&mut data);
+        unsafe {
+            data_ptr.copy_to_nonoverlapping(bytes.as_mut_ptr(), bytes.len());
+        }
+
+        Ok(data)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HeapStats {
+    pub heap_index: u32,
+    pub heap_size: DeviceSize,
+    pub block_count: usize,
+    pub block_bytes: DeviceSize,
+    pub allocation_count: usize,
+    pub allocated_bytes: DeviceSize,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct MemoryRange {
+    offset: DeviceSize,
+    size: DeviceSize,
+}
+
+struct MemoryBlock {
+    id: u64,
+    memory: DeviceMemory,
+    size: DeviceSize,
+    mapped_ptr: *mut u8,
+    dedicated: bool,
+    allocation_count: usize,
+    allocated_bytes: DeviceSize,
+    //Sorted by offset, neighbouring ranges are always merged
+    free_ranges: Vec<MemoryRange>,
+}
+
+unsafe impl Send for MemoryBlock {}
+
+impl MemoryBlock {
+    fn allocate(&mut self, size: DeviceSize, alignment: DeviceSize) -> Option<DeviceSize> {
+        let (i, range, offset) = self.free_ranges.iter().enumerate().find_map(|(i, x)| {
+            let offset = align_up(x.offset, alignment);
+            if offset + size <= x.offset + x.size {
+                Some((i, *x, offset))
+            } else {
+                None
+            }
+        })?;
+
+        let mut remaining = vec![];
+        if offset > range.offset {
+            remaining.push(MemoryRange {
+                offset: range.offset,
+                size: offset - range.offset,
+            });
+        }
+        if offset + size < range.offset + range.size {
+            remaining.push(MemoryRange {
+                offset: offset + size,
+                size: range.offset + range.size - offset - size,
+            });
+        }
+        self.free_ranges.splice(i..i + 1, remaining);
+
+        self.allocation_count += 1;
+        self.allocated_bytes += size;
+        Some(offset)
+    }
+
+    fn free(&mut self, offset: DeviceSize, size: DeviceSize) {
+        let i = self.free_ranges.partition_point(|x| x.offset < offset);
+        self.free_ranges.insert(i, MemoryRange { offset, size });
+
+        if i + 1 < self.free_ranges.len()
+            && self.free_ranges[i].offset + self.free_ranges[i].size
+                == self.free_ranges[i + 1].offset
+        {
+            self.free_ranges[i].size += self.free_ranges[i + 1].size;
+            self.free_ranges.remove(i + 1);
+        }
+        if i > 0
+            && self.free_ranges[i - 1].offset + self.free_ranges[i - 1].size
+                == self.free_ranges[i].offset
+        {
+            self.free_ranges[i - 1].size += self.free_ranges[i].size;
+            self.free_ranges.remove(i);
+        }
+
+        self.allocation_count -= 1;
+        self.allocated_bytes -= size;
+    }
+
+    fn is_empty(&self) -> bool {
+        self.allocation_count == 0
+    }
+}
+
+//Sub-allocates buffers and images out of large blocks, one pool per memory type and kind
+pub struct Allocator {
+    device: Device,
+    memory_properties: PhysicalDeviceMemoryProperties,
+    max_memory_allocation_count: u32,
+    memory_allocation_count: u32,
+    next_block_id: u64,
+    pools: HashMap<(u32, AllocationKind), Vec<MemoryBlock>>,
+}
+
+impl Allocator {
+    pub fn new(instance: &Instance, physical_device: PhysicalDevice, device: &Device) -> Allocator {
+        let memory_properties =
+            unsafe { instance.get_physical_device_memory_properties(physical_device) };
+        let device_properties = unsafe { instance.get_physical_device_properties(physical_device) };
+
+        Allocator {
+            device: device.clone(),
+            memory_properties,
+            max_memory_allocation_count: device_properties.limits.max_memory_allocation_count,
+            memory_allocation_count: 0,
+            next_block_id: 0,
+            pools: HashMap::new(),
+        }
+    }
+
+    pub fn allocate(
+        &mut self,
+        requirements: MemoryRequirements,
+        required_properties: MemoryPropertyFlags,
+        kind: AllocationKind,
+    ) -> Result<Allocation, PotatoError> {
+        let memory_types =
+            self.find_memory_types(requirements.memory_type_bits, required_properties);
+        if memory_types.is_empty() {
+            return Err(PotatoError::NoSuitableMemoryType);
+        }
+
+        let mut last_error = PotatoError::NoSuitableMemoryType;
+        for memory_type in memory_types {
+            match self.allocate_from_type(memory_type, requirements, kind) {
+                Ok(allocation) => return Ok(allocation),
+                Err(PotatoError::Vulkan(
+                    result @ (VkResult::ERROR_OUT_OF_DEVICE_MEMORY
+                    | VkResult::ERROR_OUT_OF_HOST_MEMORY),
+                )) => {
+                    warn!(
+                        "Memory type {} is out of memory, trying the next one",
+                        memory_type
+                    );
+                    last_error = PotatoError::Vulkan(result);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        Err(last_error)
+    }
+
+    pub fn free(&mut self, allocation: Allocation) {
+        let pool = match self.pools.get_mut(&allocation.pool_key) {
+            Some(pool) => pool,
+            None => {
+                warn!("Freed an allocation from an unknown memory pool");
+                return;
+            }
+        };
+
+        let i = match pool.iter().position(|x| x.id == allocation.block_id) {
+            Some(i) => i,
+            None => {
+                warn!("Freed an allocation from an unknown memory block");
+                return;
+            }
+        };
+
+        pool[i].free(allocation.offset, allocation.size);
+
+        //Keep one empty shared block around per pool so steady state churn does not hit the driver
+        let is_spare = pool[i].is_empty()
+            && (pool[i].dedicated
+                || pool
+                    .iter()
+                    .any(|x| x.id != allocation.block_id && !x.dedicated && x.is_empty()));
+        if is_spare {
+            let block = pool.remove(i);
+            self.release_block(block);
+        }
+    }
+
+    pub fn stats(&self) -> Vec<HeapStats> {
+        let mut heap_stats: Vec<HeapStats> = self.memory_properties.memory_heaps
+            [..self.memory_properties.memory_heap_count as usize]
+            .iter()
+            .enumerate()
+            .map(|(i, x)| HeapStats {
+                heap_index: i as u32,
+                heap_size: x.size,
+                ..Default::default()
+            })
+            .collect();
+
+        self.pools.iter().for_each(|((memory_type, _), blocks)| {
+            let heap_index =
+                self.memory_properties.memory_types[*memory_type as usize].heap_index as usize;
+            let stats = &mut heap_stats[heap_index];
+            blocks.iter().for_each(|x| {
+                stats.block_count += 1;
+                stats.block_bytes += x.size;
+                stats.allocation_count += x.allocation_count;
+                stats.allocated_bytes += x.allocated_bytes;
+            });
+        });
+
+        heap_stats
+    }
+
+    pub fn log_stats(&self) {
+        self.stats().iter().for_each(|x| {
+            info!(
+                "Heap {}: {} block(s), {} of {} bytes reserved, {} allocation(s) using {} bytes",
+                x.heap_index,
+                x.block_count,
+                x.block_bytes,
+                x.heap_size,
+                x.allocation_count,
+                x.allocated_bytes
+            )
+        });
+    }
+
+    //Must be called before the device is destroyed
+    pub fn destroy(&mut self) {
+        let pools: Vec<MemoryBlock> = self.pools.drain().flat_map(|(_, x)| x).collect();
+        pools.into_iter().for_each(|x| {
+            if !x.is_empty() {
+                warn!(
+                    "Destroying memory block with {} live allocation(s)",
+                    x.allocation_count
+                );
+            }
+            self.release_block(x);
+        });
+    }
+
+    //Candidates that satisfy the required flags, those with the fewest extra flags first
+    fn find_memory_types(
+        &self,
+        type_filter: u32,
+        required_properties: MemoryPropertyFlags,
+    ) -> Vec<u32> {
+        let mut memory_types: Vec<u32> = self.memory_properties.memory_types
+            [..self.memory_properties.memory_type_count as usize]
+            .iter()
+            .enumerate()
+            .filter(|(i, x)| {
+                type_filter & (1 << i) > 0 && x.property_flags.contains(required_properties)
+            })
+            .map(|(i, _)| i as u32)
+            .collect();
+
+        memory_types.sort_by_key(|x| {
+            (self.memory_properties.memory_types[*x as usize]
+                .property_flags
+                .as_raw()
+                & !required_properties.as_raw())
+            .count_ones()
+        });
+
+        memory_types
+    }
+
+    fn allocate_from_type(
+        &mut self,
+        memory_type: u32,
+        requirements: MemoryRequirements,
+        kind: AllocationKind,
+    ) -> Result<Allocation, PotatoError> {
+        let pool_key = (memory_type, kind);
+        let block_size = self.block_size(memory_type);
+        let alignment = requirements.alignment.max(1);
+
+        if let Some(pool) = self.pools.get_mut(&pool_key) {
+            for block in pool.iter_mut().filter(|x| !x.dedicated) {
+                if let Some(offset) = block.allocate(requirements.size, alignment) {
+                    return Ok(Allocation {
+                        memory: block.memory,
+                        offset,
+                        size: requirements.size,
+                        mapped_ptr: offset_ptr(block.mapped_ptr, offset),
+                        pool_key,
+                        block_id: block.id,
+                    });
+                }
+            }
+        }
+
+        let dedicated = needs_dedicated_block(requirements.size, block_size);
+        let mut block = self.create_block(
+            memory_type,
+            if dedicated {
+                requirements.size
+            } else {
+                block_size
+            },
+            dedicated,
+        )?;
+        let offset = block
+            .allocate(requirements.size, alignment)
+            .ok_or(PotatoError::Vulkan(VkResult::ERROR_OUT_OF_DEVICE_MEMORY))?;
+
+        let allocation = Allocation {
+            memory: block.memory,
+            offset,
+            size: requirements.size,
+            mapped_ptr: offset_ptr(block.mapped_ptr, offset),
+            pool_key,
+            block_id: block.id,
+        };
+        self.pools.entry(pool_key).or_default().push(block);
+
+        Ok(allocation)
+    }
+
+    fn block_size(&self, memory_type: u32) -> DeviceSize {
+        let heap_index = self.memory_properties.memory_types[memory_type as usize].heap_index;
+        block_size_for_heap(self.memory_properties.memory_heaps[heap_index as usize].size)
+    }
+
+    fn create_block(
+        &mut self,
+        memory_type: u32,
+        size: DeviceSize,
+        dedicated: bool,
+    ) -> Result<MemoryBlock, PotatoError> {
+        if self.memory_allocation_count >= self.max_memory_allocation_count {
+            return Err(PotatoError::Vulkan(VkResult::ERROR_TOO_MANY_OBJECTS));
+        }
+
+        let allocate_info = MemoryAllocateInfo {
+            s_type: StructureType::MEMORY_ALLOCATE_INFO,
+            p_next: std::ptr::null(),
+            allocation_size: size,
+            memory_type_index: memory_type,
+        };
+
+        let memory = unsafe { self.device.allocate_memory(&allocate_info, None)? };
+        self.memory_allocation_count += 1;
+
+        let property_flags =
+            self.memory_properties.memory_types[memory_type as usize].property_flags;
+        //Non coherent memory would need flushes aligned to nonCoherentAtomSize, it is left unmapped instead
+        let mapped_ptr = if property_flags
+            .contains(MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT)
+        {
+            match unsafe {
+                self.device
+                    .map_memory(memory, 0, size, MemoryMapFlags::empty())
+            } {
+                Ok(data_ptr) => data_ptr as *mut u8,
+                Err(e) => {
+                    unsafe { self.device.free_memory(memory, None) };
+                    self.memory_allocation_count -= 1;
+                    return Err(e.into());
+                }
+            }
+        } else {
+            std::ptr::null_mut()
+        };
+
+        let id = self.next_block_id;
+        self.next_block_id += 1;
+        debug!(
+            "Allocated {} byte{} block {} from memory type {}",
+            size,
+            if dedicated { " dedicated" } else { "" },
+            id,
+            memory_type
+        );
+
+        Ok(MemoryBlock {
+            id,
+            memory,
+            size,
+            mapped_ptr,
+            dedicated,
+            allocation_count: 0,
+            allocated_bytes: 0,
+            free_ranges: vec![MemoryRange { offset: 0, size }],
+        })
+    }
+
+    fn release_block(&mut self, block: MemoryBlock) {
+        debug!("Releasing memory block {}", block.id);
+        unsafe {
+            if !block.mapped_ptr.is_null() {
+                self.device.unmap_memory(block.memory);
+            }
+            self.device.free_memory(block.memory, None);
+        }
+        self.memory_allocation_count -= 1;
+    }
+}
+
+fn block_size_for_heap(heap_size: DeviceSize) -> DeviceSize {
+    if heap_size <= SMALL_HEAP_SIZE {
+        heap_size / 8
+    } else {
+        DEFAULT_BLOCK_SIZE
+    }
+}
+
+//Large requests get a block of their own instead of fragmenting the shared ones
+fn needs_dedicated_block(size: DeviceSize, block_size: DeviceSize) -> bool {
+    size > block_size / 2
+}
+
+fn align_up(value: DeviceSize, alignment: DeviceSize) -> DeviceSize {
+    value.div_ceil(alignment) * alignment
+}
+
+fn offset_ptr(mapped_ptr: *mut u8, offset: DeviceSize) -> *mut u8 {
+    if mapped_ptr.is_null() {
+        mapped_ptr
+    } else {
+        unsafe { mapped_ptr.add(offset as usize) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(size: DeviceSize) -> MemoryBlock {
+        MemoryBlock {
+            id: 0,
+            memory: DeviceMemory::null(),
+            size,
+            mapped_ptr: std::ptr::null_mut(),
+            dedicated: false,
+            allocation_count: 0,
+            allocated_bytes: 0,
+            free_ranges: vec![MemoryRange { offset: 0, size }],
+        }
+    }
+
+    fn free_ranges(block: &MemoryBlock) -> Vec<(DeviceSize, DeviceSize)> {
+        block
+            .free_ranges
+            .iter()
+            .map(|x| (x.offset, x.size))
+            .collect()
+    }
+
+    #[test]
+    fn allocation_is_aligned_and_keeps_the_padding_free() {
+        let mut block = block(1024);
+
+        assert_eq!(block.allocate(100, 1), Some(0));
+        assert_eq!(block.allocate(64, 256), Some(256));
+
+        assert_eq!(free_ranges(&block), vec![(100, 156), (320, 704)]);
+        assert_eq!(block.allocation_count, 2);
+        assert_eq!(block.allocated_bytes, 164);
+    }
+
+    #[test]
+    fn padding_is_reused_by_smaller_allocations() {
+        let mut block = block(1024);
+        block.allocate(100, 1);
+        block.allocate(64, 256);
+
+        assert_eq!(block.allocate(50, 4), Some(100));
+        assert_eq!(free_ranges(&block), vec![(150, 106), (320, 704)]);
+    }
+
+    #[test]
+    fn allocation_fails_when_no_range_fits() {
+        let mut block = block(256);
+        block.allocate(100, 1);
+
+        assert_eq!(block.allocate(200, 1), None);
+        assert_eq!(block.allocate(100, 256), None);
+        assert_eq!(block.allocation_count, 1);
+    }
+
+    #[test]
+    fn free_merges_with_both_neighbours() {
+        let mut block = block(400);
+        let first = block.allocate(100, 1).unwrap();
+        let second = block.allocate(100, 1).unwrap();
+        let third = block.allocate(100, 1).unwrap();
+
+        block.free(first, 100);
+        block.free(third, 100);
+        assert_eq!(free_ranges(&block), vec![(0, 100), (200, 200)]);
+
+        block.free(second, 100);
+        assert_eq!(free_ranges(&block), vec![(0, 400)]);
+        assert!(block.is_empty());
+        assert_eq!(block.allocated_bytes, 0);
+    }
+
+    #[test]
+    fn freed_range_is_reused() {
+        let mut block = block(300);
+        let first = block.allocate(100, 1).unwrap();
+        block.allocate(100, 1).unwrap();
+
+        block.free(first, 100);
+
+        assert_eq!(block.allocate(100, 1), Some(first));
+        assert_eq!(free_ranges(&block), vec![(200, 100)]);
+    }
+
+    #[test]
+    fn only_allocations_above_half_a_block_are_dedicated() {
+        assert!(!needs_dedicated_block(32, 64));
+        assert!(needs_dedicated_block(33, 64));
+        assert!(!needs_dedicated_block(
+            DEFAULT_BLOCK_SIZE / 2,
+            DEFAULT_BLOCK_SIZE
+        ));
+        assert!(needs_dedicated_block(
+            DEFAULT_BLOCK_SIZE / 2 + 1,
+            DEFAULT_BLOCK_SIZE
+        ));
+    }
+
+    #[test]
+    fn small_heaps_get_smaller_blocks() {
+        assert_eq!(block_size_for_heap(256 * 1024 * 1024), 32 * 1024 * 1024);
+        assert_eq!(block_size_for_heap(SMALL_HEAP_SIZE), SMALL_HEAP_SIZE / 8);
+        assert_eq!(block_size_for_heap(8 * SMALL_HEAP_SIZE), DEFAULT_BLOCK_SIZE);
+    }
+
+    fn host_allocation(memory: &mut [u8], offset: usize, size: DeviceSize) -> Allocation {
+        Allocation {
+            memory: DeviceMemory::null(),
+            offset: offset as DeviceSize,
+            size,
+            mapped_ptr: memory[offset..].as_mut_ptr(),
+            pool_key: (0, AllocationKind::Linear),
+            block_id: 0,
+        }
+    }
+
+    #[test]
+    fn mapped_writes_and_reads_need_no_alignment() {
+        let mut memory = vec![0u8; 64];
+        let allocation = host_allocation(&mut memory, 1, 32);
+
+        allocation.write(&[1.5f32, -2.0, 3.25]).unwrap();
+
+        assert_eq!(allocation.read::<f32>(3).unwrap(), vec![1.5, -2.0, 3.25]);
+        assert_eq!(memory[0], 0);
+    }
+
+    #[test]
+    fn mapped_access_past_the_allocation_is_an_error() {
+        let mut memory = vec![0u8; 64];
+        let allocation = host_allocation(&mut memory, 0, 8);
+
+        assert!(matches!(
+            allocation.write(&[0u32; 3]),
+            Err(PotatoError::BufferOverflow {
+                size: 12,
+                capacity: 8
+            })
+        ));
+        assert!(allocation.read::<u64>(2).is_err());
+    }
+
+    #[test]
+    fn unmapped_memory_is_an_error() {
+        let allocation = Allocation {
+            mapped_ptr: std::ptr::null_mut(),
+            ..host_allocation(&mut [0u8; 8], 0, 8)
+        };
+
+        assert!(matches!(
+            allocation.read::<u8>(1),
+            Err(PotatoError::MemoryNotMapped)
+        ));
+    }
+
+    #[test]
+    fn align_up_rounds_to_the_next_multiple() {
+        assert_eq!(align_up(0, 256), 0);
+        assert_eq!(align_up(1, 256), 256);
+        assert_eq!(align_up(256, 256), 256);
+        assert_eq!(align_up(100, 1), 100);
+    }
+}
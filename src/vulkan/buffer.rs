@@ -1,144 +1,167 @@
+use super::allocator::{Allocation, AllocationKind, Allocator};
+use super::command_pool::{begin_single_time_commands, end_single_time_commands};
+use super::debug_utils::PotatoDebugUtils;
+use super::timeline::GpuTimeline;
+use crate::error::PotatoError;
 use ash::vk::{
-    Buffer, BufferCopy, BufferCreateFlags, BufferCreateInfo, BufferUsageFlags,
-    CommandBufferAllocateInfo, CommandBufferBeginInfo, CommandBufferLevel, CommandBufferUsageFlags,
-    CommandPool, DeviceMemory, DeviceSize, Fence, MemoryAllocateInfo, MemoryPropertyFlags,
-    PhysicalDeviceMemoryProperties, Queue, SharingMode, StructureType, SubmitInfo,
+    Buffer, BufferCopy, BufferCreateFlags, BufferCreateInfo, BufferUsageFlags, CommandPool,
+    DeviceSize, MemoryPropertyFlags, Queue, SharingMode, StructureType,
 };
 use ash::Device;
+use bytemuck::Pod;
 
+//Buffers used from more than one queue family are shared instead of transferring ownership after every upload
+//TODO Reduce number of arguments
+#[allow(clippy::too_many_arguments)]
 pub fn create_buffer(
     device: &Device,
+    debug_utils: &PotatoDebugUtils,
+    allocator: &mut Allocator,
     size: DeviceSize,
     usage: BufferUsageFlags,
     required_memory_properties: MemoryPropertyFlags,
-    device_memory_properties: &PhysicalDeviceMemoryProperties,
-) -> (Buffer, DeviceMemory) {
+    queue_family_indices: &[u32],
+    name: &str,
+) -> Result<(Buffer, Allocation), PotatoError> {
+    let (sharing_mode, queue_family_index_count, p_queue_family_indices) =
+        if queue_family_indices.len() > 1 {
+            (
+                SharingMode::CONCURRENT,
+                queue_family_indices.len() as u32,
+                queue_family_indices.as_ptr(),
+            )
+        } else {
+            (SharingMode::EXCLUSIVE, 0, std::ptr::null())
+        };
+
     let buffer_create_info = BufferCreateInfo {
         s_type: StructureType::BUFFER_CREATE_INFO,
         p_next: std::ptr::null(),
         flags: BufferCreateFlags::empty(),
         size,
         usage,
-        sharing_mode: SharingMode::EXCLUSIVE,
-        queue_family_index_count: 0,
-        p_queue_family_indices: std::ptr::null(),
+        sharing_mode,
+        queue_family_index_count,
+        p_queue_family_indices,
     };
 
-    let buffer = unsafe {
-        device
-            .create_buffer(&buffer_create_info, None)
-            .expect("Failed to create Vertex Buffer")
-    };
+    let buffer = unsafe { device.create_buffer(&buffer_create_info, None)? };
+    debug_utils.name_object(buffer, name);
 
     let mem_requirements = unsafe { device.get_buffer_memory_requirements(buffer) };
 
-    let memory_type = find_mem_type(
-        mem_requirements.memory_type_bits,
+    let allocation = match allocator.allocate(
+        mem_requirements,
         required_memory_properties,
-        *device_memory_properties,
-    );
-
-    let allocate_info = MemoryAllocateInfo {
-        s_type: StructureType::MEMORY_ALLOCATE_INFO,
-        p_next: std::ptr::null(),
-        allocation_size: mem_requirements.size,
-        memory_type_index: memory_type,
-    };
-
-    let buffer_memory = unsafe {
-        device
-            .allocate_memory(&allocate_info, None)
-            .expect("Failed to allocate vertex buffer memory")
+        AllocationKind::Linear,
+    ) {
+        Ok(allocation) => allocation,
+        Err(e) => {
+            unsafe { device.destroy_buffer(buffer, None) };
+            return Err(e);
+        }
     };
 
     unsafe {
-        device
-            .bind_buffer_memory(buffer, buffer_memory, 0)
-            .expect("Failed to bind buffer");
+        device.bind_buffer_memory(buffer, allocation.memory(), allocation.offset())?;
     }
 
-    (buffer, buffer_memory)
+    Ok((buffer, allocation))
 }
 
-fn find_mem_type(
-    type_filter: u32,
-    required_properties: MemoryPropertyFlags,
-    mem_properties: PhysicalDeviceMemoryProperties,
-) -> u32 {
-    mem_properties
-        .memory_types
-        .iter()
-        .enumerate()
-        .position(|(i, x)| {
-            type_filter & (1 << i) > 0 && x.property_flags.contains(required_properties)
-        })
-        .unwrap() as u32
+pub fn destroy_buffer(
+    device: &Device,
+    allocator: &mut Allocator,
+    buffer: Buffer,
+    allocation: Allocation,
+) {
+    unsafe {
+        device.destroy_buffer(buffer, None);
+    }
+    allocator.free(allocation);
 }
 
+//Uploads data through a temporary host visible staging buffer
+//submit_queue may be a transfer queue, queue_family_indices lists every family that uses the result
+//TODO Reduce number of arguments
+#[allow(clippy::too_many_arguments)]
+pub fn create_device_local_buffer<T: Pod>(
+    device: &Device,
+    debug_utils: &PotatoDebugUtils,
+    allocator: &mut Allocator,
+    command_pool: CommandPool,
+    submit_queue: Queue,
+    timeline: Option<&GpuTimeline>,
+    data: &[T],
+    buffer_usage_flags: BufferUsageFlags,
+    queue_family_indices: &[u32],
+    name: &str,
+) -> Result<(Buffer, Allocation), PotatoError> {
+    let buffer_size = std::mem::size_of_val(data) as DeviceSize;
+
+    let (staging_buffer, staging_allocation) = create_buffer(
+        device,
+        debug_utils,
+        allocator,
+        buffer_size,
+        BufferUsageFlags::TRANSFER_SRC,
+        MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT,
+        &[],
+        &format!("{} staging", name),
+    )?;
+
+    let result = staging_allocation.write(data).and_then(|_| {
+        let (buffer, allocation) = create_buffer(
+            device,
+            debug_utils,
+            allocator,
+            buffer_size,
+            BufferUsageFlags::TRANSFER_DST | buffer_usage_flags,
+            MemoryPropertyFlags::DEVICE_LOCAL,
+            queue_family_indices,
+            name,
+        )?;
+
+        match copy_buffer(
+            device,
+            submit_queue,
+            command_pool,
+            timeline,
+            staging_buffer,
+            buffer,
+            buffer_size,
+        ) {
+            Ok(_) => Ok((buffer, allocation)),
+            Err(e) => {
+                destroy_buffer(device, allocator, buffer, allocation);
+                Err(e)
+            }
+        }
+    });
+
+    destroy_buffer(device, allocator, staging_buffer, staging_allocation);
+
+    result
+}
+
+//Blocks until the copy has executed, see submit_and_wait
 pub fn copy_buffer(
     device: &Device,
     submit_queue: Queue,
     command_pool: CommandPool,
+    timeline: Option<&GpuTimeline>,
     src_buffer: Buffer,
     dst_buffer: Buffer,
     size: DeviceSize,
-) {
-    let allocate_info = CommandBufferAllocateInfo {
-        s_type: StructureType::COMMAND_BUFFER_ALLOCATE_INFO,
-        p_next: std::ptr::null(),
-        command_buffer_count: 1,
-        command_pool,
-        level: CommandBufferLevel::PRIMARY,
-    };
+) -> Result<(), PotatoError> {
+    let command_buffer = begin_single_time_commands(device, command_pool)?;
 
-    let command_buffers = unsafe {
-        device
-            .allocate_command_buffers(&allocate_info)
-            .expect("Failed to allocate command buffer")
-    };
-
-    let begin_info = CommandBufferBeginInfo {
-        s_type: StructureType::COMMAND_BUFFER_BEGIN_INFO,
-        p_next: std::ptr::null(),
-        flags: CommandBufferUsageFlags::ONE_TIME_SUBMIT,
-        p_inheritance_info: std::ptr::null(),
-    };
-
-    unsafe {
-        device
-            .begin_command_buffer(command_buffers[0], &begin_info)
-            .expect("Failed to begin command buffer");
-        let copy_regions = [BufferCopy {
-            src_offset: 0,
-            dst_offset: 0,
-            size,
-        }];
-        device.cmd_copy_buffer(command_buffers[0], src_buffer, dst_buffer, &copy_regions);
-        device
-            .end_command_buffer(command_buffers[0])
-            .expect("Failed to end command buffer");
-    }
-
-    let submit_info = [SubmitInfo {
-        s_type: StructureType::SUBMIT_INFO,
-        p_next: std::ptr::null(),
-        wait_semaphore_count: 0,
-        p_wait_semaphores: std::ptr::null(),
-        p_wait_dst_stage_mask: std::ptr::null(),
-        command_buffer_count: 1,
-        p_command_buffers: &command_buffers[0],
-        signal_semaphore_count: 0,
-        p_signal_semaphores: std::ptr::null(),
+    let copy_regions = [BufferCopy {
+        src_offset: 0,
+        dst_offset: 0,
+        size,
     }];
+    unsafe { device.cmd_copy_buffer(command_buffer, src_buffer, dst_buffer, &copy_regions) };
 
-    //TODO add logic for semaphore or fence for multiple submissions.
-    unsafe {
-        device
-            .queue_submit(submit_queue, &submit_info, Fence::null())
-            .expect("Failed to submit queue");
-        device
-            .queue_wait_idle(submit_queue)
-            .expect("Failed to wait on queue");
-        device.free_command_buffers(command_pool, &command_buffers);
-    }
+    end_single_time_commands(device, command_pool, submit_queue, timeline, command_buffer)
 }
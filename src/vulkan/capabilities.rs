@@ -0,0 +1,209 @@
+use super::utilities::vk_to_string;
+use crate::error::PotatoError;
+use ash::Entry;
+use log::debug;
+use std::fmt::{Display, Formatter};
+
+//Everything required that is missing, optional items are dropped without being reported
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CapabilityReport {
+    pub missing_layers: Vec<String>,
+    pub missing_instance_extensions: Vec<String>,
+    pub missing_device_extensions: Vec<String>,
+    pub missing_device_features: Vec<String>,
+}
+
+impl CapabilityReport {
+    pub fn is_satisfied(&self) -> bool {
+        self.missing_layers.is_empty()
+            && self.missing_instance_extensions.is_empty()
+            && self.missing_device_extensions.is_empty()
+            && self.missing_device_features.is_empty()
+    }
+
+    //Ok when nothing is missing, otherwise the report as an error
+    pub fn into_result(self) -> Result<(), PotatoError> {
+        if self.is_satisfied() {
+            Ok(())
+        } else {
+            Err(PotatoError::MissingCapabilities(self))
+        }
+    }
+}
+
+impl Display for CapabilityReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let sections = [
+            ("instance layers", &self.missing_layers),
+            ("instance extensions", &self.missing_instance_extensions),
+            ("device extensions", &self.missing_device_extensions),
+            ("device features", &self.missing_device_features),
+        ];
+        let missing: Vec<String> = sections
+            .iter()
+            .filter(|(_, names)| !names.is_empty())
+            .map(|(section, names)| format!("{} {}", section, names.join(", ")))
+            .collect();
+        write!(f, "{}", missing.join("; "))
+    }
+}
+
+//Returns what to enable and what is missing, every required name must be available while optional ones are kept only when they are
+pub fn select_available<S: AsRef<str>>(
+    required: &[S],
+    optional: &[S],
+    available: &[String],
+) -> (Vec<String>, Vec<String>) {
+    let is_available = |name: &S| available.iter().any(|x| x == name.as_ref());
+
+    let mut enabled = vec![];
+    let mut missing = vec![];
+    for name in required {
+        if is_available(name) {
+            enabled.push(name.as_ref().to_string());
+        } else {
+            missing.push(name.as_ref().to_string());
+        }
+    }
+    for name in optional {
+        if is_available(name) {
+            enabled.push(name.as_ref().to_string());
+        } else {
+            debug!("Dropping optional {}", name.as_ref());
+        }
+    }
+
+    (enabled, missing)
+}
+
+//Layers and extensions the instance is created with
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct InstanceRequirements {
+    pub required_layers: Vec<String>,
+    pub optional_layers: Vec<String>,
+    pub required_extensions: Vec<String>,
+    pub optional_extensions: Vec<String>,
+}
+
+//What create_instance passed to the driver
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EnabledInstanceCapabilities {
+    pub layers: Vec<String>,
+    pub extensions: Vec<String>,
+}
+
+impl EnabledInstanceCapabilities {
+    pub fn has_layer(&self, name: &str) -> bool {
+        self.layers.iter().any(|x| x == name)
+    }
+
+    pub fn has_extension(&self, name: &str) -> bool {
+        self.extensions.iter().any(|x| x == name)
+    }
+}
+
+impl InstanceRequirements {
+    pub fn new() -> InstanceRequirements {
+        InstanceRequirements::default()
+    }
+
+    pub fn require_layer(mut self, name: &str) -> InstanceRequirements {
+        self.optional_layers.retain(|x| x != name);
+        if !self.required_layers.iter().any(|x| x == name) {
+            self.required_layers.push(name.to_string());
+        }
+        self
+    }
+
+    pub fn request_layer(mut self, name: &str) -> InstanceRequirements {
+        if !self.required_layers.iter().any(|x| x == name)
+            && !self.optional_layers.iter().any(|x| x == name)
+        {
+            self.optional_layers.push(name.to_string());
+        }
+        self
+    }
+
+    pub fn require_extension(mut self, name: &str) -> InstanceRequirements {
+        self.optional_extensions.retain(|x| x != name);
+        if !self.required_extensions.iter().any(|x| x == name) {
+            self.required_extensions.push(name.to_string());
+        }
+        self
+    }
+
+    pub fn request_extension(mut self, name: &str) -> InstanceRequirements {
+        if !self.required_extensions.iter().any(|x| x == name)
+            && !self.optional_extensions.iter().any(|x| x == name)
+        {
+            self.optional_extensions.push(name.to_string());
+        }
+        self
+    }
+
+    pub fn check(
+        &self,
+        available_layers: &[String],
+        available_extensions: &[String],
+    ) -> Result<EnabledInstanceCapabilities, PotatoError> {
+        let (layers, missing_layers) = select_available(
+            &self.required_layers,
+            &self.optional_layers,
+            available_layers,
+        );
+        let (extensions, missing_instance_extensions) = select_available(
+            &self.required_extensions,
+            &self.optional_extensions,
+            available_extensions,
+        );
+
+        CapabilityReport {
+            missing_layers,
+            missing_instance_extensions,
+            ..Default::default()
+        }
+        .into_result()?;
+
+        Ok(EnabledInstanceCapabilities { layers, extensions })
+    }
+}
+
+//Checks against the layers and extensions the loader reports, extensions provided by enabled layers are included
+pub fn check_instance_support(
+    entry: &Entry,
+    requirements: &InstanceRequirements,
+) -> Result<EnabledInstanceCapabilities, PotatoError> {
+    let available_layers: Vec<String> = entry
+        .enumerate_instance_layer_properties()?
+        .iter()
+        .map(|x| vk_to_string(&x.layer_name))
+        .collect();
+    debug!("Available layers {:?}", available_layers);
+
+    let mut available_extensions: Vec<String> = entry
+        .enumerate_instance_extension_properties(None)?
+        .iter()
+        .map(|x| vk_to_string(&x.extension_name))
+        .collect();
+    for layer in &available_layers {
+        let is_requested = requirements
+            .required_layers
+            .iter()
+            .chain(requirements.optional_layers.iter())
+            .any(|x| x == layer);
+        if is_requested {
+            let layer_name = std::ffi::CString::new(layer.as_str()).unwrap();
+            for extension in entry.enumerate_instance_extension_properties(Some(&layer_name))? {
+                available_extensions.push(vk_to_string(&extension.extension_name));
+            }
+        }
+    }
+    debug!("Available instance extensions {:?}", available_extensions);
+
+    let enabled = requirements.check(&available_layers, &available_extensions)?;
+    debug!(
+        "Enabled layers {:?}, instance extensions {:?}",
+        enabled.layers, enabled.extensions
+    );
+    Ok(enabled)
+}
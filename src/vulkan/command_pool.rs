@@ -1,149 +1,121 @@
-use super::constants::INDICES_DATA;
-use super::queue_family::QueueFamily;
+use super::debug_utils::PotatoDebugUtils;
+use super::timeline::{submit_and_wait, GpuTimeline};
+use crate::error::PotatoError;
 use ash::vk::{
-    Buffer, ClearColorValue, ClearValue, CommandBuffer, CommandBufferAllocateInfo,
-    CommandBufferBeginInfo, CommandBufferLevel, CommandBufferUsageFlags, CommandPool,
-    CommandPoolCreateFlags, CommandPoolCreateInfo, DescriptorSet, Extent2D, Framebuffer, IndexType,
-    Offset2D, Pipeline, PipelineBindPoint, PipelineLayout, Rect2D, RenderPass, RenderPassBeginInfo,
-    StructureType, SubpassContents,
+    CommandBuffer, CommandBufferAllocateInfo, CommandBufferBeginInfo, CommandBufferInheritanceInfo,
+    CommandBufferLevel, CommandBufferUsageFlags, CommandPool, CommandPoolCreateFlags,
+    CommandPoolCreateInfo, Framebuffer, QueryControlFlags, QueryPipelineStatisticFlags, Queue,
+    RenderPass, StructureType,
 };
 use ash::Device;
 
-pub fn create_command_pool(device: &Device, queue_familes: &QueueFamily) -> CommandPool {
+pub fn create_command_pool(
+    device: &Device,
+    debug_utils: &PotatoDebugUtils,
+    queue_family_index: u32,
+    flags: CommandPoolCreateFlags,
+    name: &str,
+) -> Result<CommandPool, PotatoError> {
     let command_pool_create_info = CommandPoolCreateInfo {
         s_type: StructureType::COMMAND_POOL_CREATE_INFO,
         p_next: std::ptr::null(),
-        flags: CommandPoolCreateFlags::empty(),
-        queue_family_index: queue_familes.graphics_family.unwrap() as u32,
+        flags,
+        queue_family_index,
     };
 
-    unsafe {
-        device
-            .create_command_pool(&command_pool_create_info, None)
-            .expect("Failed to create Command Pool")
-    }
+    let command_pool = unsafe { device.create_command_pool(&command_pool_create_info, None)? };
+    debug_utils.name_object(command_pool, name);
+    Ok(command_pool)
 }
 
-//TODO Reduce number of arguments
-pub fn create_command_buffers(
+pub fn allocate_command_buffers(
     device: &Device,
     command_pool: CommandPool,
-    graphics_pipeline: Pipeline,
-    framebuffers: &[Framebuffer],
-    render_pass: RenderPass,
-    surface_extent: Extent2D,
-    vertex_buffer: Buffer,
-    index_buffer: Buffer,
-    pipeline_layout: PipelineLayout,
-    descriptor_sets: &Vec<DescriptorSet>,
-) -> Vec<CommandBuffer> {
-    let command_buffer_allocate_info = CommandBufferAllocateInfo {
+    level: CommandBufferLevel,
+    count: u32,
+) -> Result<Vec<CommandBuffer>, PotatoError> {
+    let allocate_info = CommandBufferAllocateInfo {
         s_type: StructureType::COMMAND_BUFFER_ALLOCATE_INFO,
         p_next: std::ptr::null(),
-        command_buffer_count: framebuffers.len() as u32,
+        command_buffer_count: count,
         command_pool,
-        level: CommandBufferLevel::PRIMARY,
+        level,
     };
 
-    let command_buffers = unsafe {
-        device
-            .allocate_command_buffers(&command_buffer_allocate_info)
-            .expect("failed to create command buffers")
+    Ok(unsafe { device.allocate_command_buffers(&allocate_info)? })
+}
+
+//Secondary buffers continue the first subpass of render_pass, the viewport and scissor are not inherited and have to be set again
+pub fn begin_secondary_command_buffer(
+    device: &Device,
+    command_buffer: CommandBuffer,
+    render_pass: RenderPass,
+    framebuffer: Framebuffer,
+) -> Result<(), PotatoError> {
+    let inheritance_info = CommandBufferInheritanceInfo {
+        s_type: StructureType::COMMAND_BUFFER_INHERITANCE_INFO,
+        p_next: std::ptr::null(),
+        render_pass,
+        subpass: 0,
+        framebuffer,
+        occlusion_query_enable: 0,
+        query_flags: QueryControlFlags::empty(),
+        pipeline_statistics: QueryPipelineStatisticFlags::empty(),
     };
 
-    command_buffers.iter().enumerate().for_each(|(i, x)| {
-        process_command_buffer(
-            i,
-            x,
-            render_pass,
-            framebuffers,
-            surface_extent,
-            device,
-            graphics_pipeline,
-            vertex_buffer,
-            index_buffer,
-            pipeline_layout,
-            descriptor_sets,
-        )
-    });
+    let begin_info = CommandBufferBeginInfo {
+        s_type: StructureType::COMMAND_BUFFER_BEGIN_INFO,
+        p_next: std::ptr::null(),
+        flags: CommandBufferUsageFlags::RENDER_PASS_CONTINUE
+            | CommandBufferUsageFlags::ONE_TIME_SUBMIT,
+        p_inheritance_info: &inheritance_info,
+    };
 
-    command_buffers
+    unsafe { device.begin_command_buffer(command_buffer, &begin_info)? };
+    Ok(())
 }
 
-//TODO Reduce number of arguments
-fn process_command_buffer(
-    index: usize,
-    command_buffer: &CommandBuffer,
-    render_pass: RenderPass,
-    framebuffers: &[Framebuffer],
-    surface_extent: Extent2D,
+pub fn begin_single_time_commands(
     device: &Device,
-    graphics_pipeline: Pipeline,
-    vertex_buffer: Buffer,
-    index_buffer: Buffer,
-    pipeline_layout: PipelineLayout,
-    descriptor_sets: &Vec<DescriptorSet>,
-) {
-    let command_buffer_begin_info = CommandBufferBeginInfo {
+    command_pool: CommandPool,
+) -> Result<CommandBuffer, PotatoError> {
+    let allocate_info = CommandBufferAllocateInfo {
+        s_type: StructureType::COMMAND_BUFFER_ALLOCATE_INFO,
+        p_next: std::ptr::null(),
+        command_buffer_count: 1,
+        command_pool,
+        level: CommandBufferLevel::PRIMARY,
+    };
+
+    let command_buffer = unsafe { device.allocate_command_buffers(&allocate_info)?[0] };
+
+    let begin_info = CommandBufferBeginInfo {
         s_type: StructureType::COMMAND_BUFFER_BEGIN_INFO,
         p_next: std::ptr::null(),
+        flags: CommandBufferUsageFlags::ONE_TIME_SUBMIT,
         p_inheritance_info: std::ptr::null(),
-        flags: CommandBufferUsageFlags::SIMULTANEOUS_USE,
     };
 
-    unsafe {
-        device
-            .begin_command_buffer(*command_buffer, &command_buffer_begin_info)
-            .expect("Failed to begin recording Command Buffer at beginning!");
+    if let Err(e) = unsafe { device.begin_command_buffer(command_buffer, &begin_info) } {
+        unsafe { device.free_command_buffers(command_pool, &[command_buffer]) };
+        return Err(e.into());
     }
 
-    let clear_values = [ClearValue {
-        color: ClearColorValue {
-            float32: [0.0, 0.0, 0.0, 1.0],
-        },
-    }];
+    Ok(command_buffer)
+}
 
-    let render_pass_begin_info = RenderPassBeginInfo {
-        s_type: StructureType::RENDER_PASS_BEGIN_INFO,
-        p_next: std::ptr::null(),
-        render_pass,
-        framebuffer: framebuffers[index],
-        render_area: Rect2D {
-            offset: Offset2D { x: 0, y: 0 },
-            extent: surface_extent,
-        },
-        clear_value_count: clear_values.len() as u32,
-        p_clear_values: clear_values.as_ptr(),
-    };
+//Submits the commands and blocks until they have executed, other work on the queue keeps running
+pub fn end_single_time_commands(
+    device: &Device,
+    command_pool: CommandPool,
+    submit_queue: Queue,
+    timeline: Option<&GpuTimeline>,
+    command_buffer: CommandBuffer,
+) -> Result<(), PotatoError> {
+    let result = unsafe { device.end_command_buffer(command_buffer) }
+        .map_err(PotatoError::from)
+        .and_then(|_| submit_and_wait(device, submit_queue, command_buffer, timeline));
+    unsafe { device.free_command_buffers(command_pool, &[command_buffer]) };
 
-    unsafe {
-        device.cmd_begin_render_pass(
-            *command_buffer,
-            &render_pass_begin_info,
-            SubpassContents::INLINE,
-        );
-        device.cmd_bind_pipeline(
-            *command_buffer,
-            PipelineBindPoint::GRAPHICS,
-            graphics_pipeline,
-        );
-        let vertex_buffers = [vertex_buffer];
-        let offsets = [0_u64];
-        let descriptor_sets_to_bind = [descriptor_sets[index]];
-        device.cmd_bind_vertex_buffers(*command_buffer, 0, &vertex_buffers, &offsets);
-        device.cmd_bind_index_buffer(*command_buffer, index_buffer, 0, IndexType::UINT32);
-        device.cmd_bind_descriptor_sets(
-            *command_buffer,
-            PipelineBindPoint::GRAPHICS,
-            pipeline_layout,
-            0,
-            &descriptor_sets_to_bind,
-            &[],
-        );
-        device.cmd_draw_indexed(*command_buffer, INDICES_DATA.len() as u32, 1, 0, 0, 0);
-        device.cmd_end_render_pass(*command_buffer);
-        device
-            .end_command_buffer(*command_buffer)
-            .expect("Failed to record command buffer ending");
-    }
+    result
 }
@@ -0,0 +1,200 @@
+use super::debug_utils::PotatoDebugUtils;
+use super::graphics_pipeline::{create_shader_module, read_shader};
+use crate::error::PotatoError;
+use ash::vk::{
+    Buffer, ComputePipelineCreateInfo, DescriptorBufferInfo, DescriptorPool,
+    DescriptorPoolCreateFlags, DescriptorPoolCreateInfo, DescriptorPoolSize, DescriptorSet,
+    DescriptorSetAllocateInfo, DescriptorSetLayout, DescriptorSetLayoutBinding,
+    DescriptorSetLayoutCreateFlags, DescriptorSetLayoutCreateInfo, DescriptorType, Pipeline,
+    PipelineCache, PipelineCreateFlags, PipelineLayout, PipelineLayoutCreateFlags,
+    PipelineLayoutCreateInfo, PipelineShaderStageCreateFlags, PipelineShaderStageCreateInfo,
+    ShaderStageFlags, StructureType, WriteDescriptorSet, WHOLE_SIZE,
+};
+use ash::Device;
+use std::ffi::CString;
+
+//Storage buffers are bound in order, buffer i is visible to the shader at binding i of set 0
+pub fn create_compute_descriptor_set_layout(
+    device: &Device,
+    debug_utils: &PotatoDebugUtils,
+    storage_buffer_count: usize,
+) -> Result<DescriptorSetLayout, PotatoError> {
+    let layout_bindings: Vec<DescriptorSetLayoutBinding> = (0..storage_buffer_count)
+        .map(|i| DescriptorSetLayoutBinding {
+            binding: i as u32,
+            descriptor_type: DescriptorType::STORAGE_BUFFER,
+            descriptor_count: 1,
+            stage_flags: ShaderStageFlags::COMPUTE,
+            p_immutable_samplers: std::ptr::null(),
+        })
+        .collect();
+
+    let layout_create_info = DescriptorSetLayoutCreateInfo {
+        s_type: StructureType::DESCRIPTOR_SET_LAYOUT_CREATE_INFO,
+        p_next: std::ptr::null(),
+        flags: DescriptorSetLayoutCreateFlags::empty(),
+        binding_count: layout_bindings.len() as u32,
+        p_bindings: layout_bindings.as_ptr(),
+    };
+
+    let set_layout = unsafe { device.create_descriptor_set_layout(&layout_create_info, None)? };
+    debug_utils.name_object(set_layout, "Compute descriptor set layout");
+    Ok(set_layout)
+}
+
+pub fn create_compute_pipeline(
+    device: &Device,
+    debug_utils: &PotatoDebugUtils,
+    shader_path: &str,
+    set_layout: DescriptorSetLayout,
+) -> Result<(Pipeline, PipelineLayout), PotatoError> {
+    let comp_shader = read_shader(shader_path)?;
+    let comp_module = create_shader_module(device, debug_utils, comp_shader, shader_path)?;
+
+    let main_function_name = CString::new("main").unwrap();
+
+    let shader_stage = PipelineShaderStageCreateInfo {
+        s_type: StructureType::PIPELINE_SHADER_STAGE_CREATE_INFO,
+        p_next: std::ptr::null(),
+        flags: PipelineShaderStageCreateFlags::empty(),
+        module: comp_module,
+        p_name: main_function_name.as_ptr(),
+        p_specialization_info: std::ptr::null(),
+        stage: ShaderStageFlags::COMPUTE,
+    };
+
+    let set_layouts = [set_layout];
+    let pipeline_layout_create_info = PipelineLayoutCreateInfo {
+        s_type: StructureType::PIPELINE_LAYOUT_CREATE_INFO,
+        p_next: std::ptr::null(),
+        flags: PipelineLayoutCreateFlags::empty(),
+        set_layout_count: set_layouts.len() as u32,
+        p_set_layouts: set_layouts.as_ptr(),
+        push_constant_range_count: 0,
+        p_push_constant_ranges: std::ptr::null(),
+    };
+
+    let pipeline_layout =
+        match unsafe { device.create_pipeline_layout(&pipeline_layout_create_info, None) } {
+            Ok(pipeline_layout) => pipeline_layout,
+            Err(e) => {
+                unsafe { device.destroy_shader_module(comp_module, None) };
+                return Err(e.into());
+            }
+        };
+    debug_utils.name_object(
+        pipeline_layout,
+        &format!("Compute pipeline layout {}", shader_path),
+    );
+
+    let compute_pipeline_create_infos = [ComputePipelineCreateInfo {
+        s_type: StructureType::COMPUTE_PIPELINE_CREATE_INFO,
+        p_next: std::ptr::null(),
+        flags: PipelineCreateFlags::empty(),
+        stage: shader_stage,
+        layout: pipeline_layout,
+        base_pipeline_handle: Pipeline::null(),
+        base_pipeline_index: -1,
+    }];
+
+    let compute_pipelines = unsafe {
+        device
+            .create_compute_pipelines(PipelineCache::null(), &compute_pipeline_create_infos, None)
+            .map_err(|(_, result)| result)
+    };
+    unsafe {
+        device.destroy_shader_module(comp_module, None);
+    }
+    let compute_pipelines = match compute_pipelines {
+        Ok(compute_pipelines) => compute_pipelines,
+        Err(e) => {
+            unsafe { device.destroy_pipeline_layout(pipeline_layout, None) };
+            return Err(e.into());
+        }
+    };
+    debug_utils.name_object(
+        compute_pipelines[0],
+        &format!("Compute pipeline {}", shader_path),
+    );
+
+    Ok((compute_pipelines[0], pipeline_layout))
+}
+
+pub fn create_compute_descriptor_pool(
+    device: &Device,
+    debug_utils: &PotatoDebugUtils,
+    storage_buffer_count: usize,
+) -> Result<DescriptorPool, PotatoError> {
+    let pool_sizes = [DescriptorPoolSize {
+        ty: DescriptorType::STORAGE_BUFFER,
+        descriptor_count: storage_buffer_count as u32,
+    }];
+
+    let descriptor_pool_create_info = DescriptorPoolCreateInfo {
+        s_type: StructureType::DESCRIPTOR_POOL_CREATE_INFO,
+        p_next: std::ptr::null(),
+        flags: DescriptorPoolCreateFlags::empty(),
+        max_sets: 1,
+        pool_size_count: pool_sizes.len() as u32,
+        p_pool_sizes: pool_sizes.as_ptr(),
+    };
+
+    let descriptor_pool =
+        unsafe { device.create_descriptor_pool(&descriptor_pool_create_info, None)? };
+    debug_utils.name_object(descriptor_pool, "Compute descriptor pool");
+    Ok(descriptor_pool)
+}
+
+pub fn create_compute_descriptor_set(
+    device: &Device,
+    debug_utils: &PotatoDebugUtils,
+    descriptor_pool: DescriptorPool,
+    descriptor_set_layout: DescriptorSetLayout,
+    storage_buffers: &[Buffer],
+) -> Result<DescriptorSet, PotatoError> {
+    let layouts = [descriptor_set_layout];
+
+    let descriptor_set_allocate_info = DescriptorSetAllocateInfo {
+        s_type: StructureType::DESCRIPTOR_SET_ALLOCATE_INFO,
+        p_next: std::ptr::null(),
+        descriptor_pool,
+        descriptor_set_count: layouts.len() as u32,
+        p_set_layouts: layouts.as_ptr(),
+    };
+
+    let descriptor_set =
+        unsafe { device.allocate_descriptor_sets(&descriptor_set_allocate_info)?[0] };
+    debug_utils.name_object(descriptor_set, "Compute descriptor set");
+
+    let descriptor_buffer_infos: Vec<DescriptorBufferInfo> = storage_buffers
+        .iter()
+        .map(|x| DescriptorBufferInfo {
+            buffer: *x,
+            offset: 0,
+            range: WHOLE_SIZE,
+        })
+        .collect();
+
+    let descriptor_write_sets: Vec<WriteDescriptorSet> = descriptor_buffer_infos
+        .iter()
+        .enumerate()
+        .map(|(i, x)| WriteDescriptorSet {
+            s_type: StructureType::WRITE_DESCRIPTOR_SET,
+            p_next: std::ptr::null(),
+            dst_set: descriptor_set,
+            dst_binding: i as u32,
+            dst_array_element: 0,
+            descriptor_count: 1,
+            descriptor_type: DescriptorType::STORAGE_BUFFER,
+            p_image_info: std::ptr::null(),
+            p_buffer_info: x,
+            p_texel_buffer_view: std::ptr::null(),
+        })
+        .collect();
+
+    unsafe {
+        device.update_descriptor_sets(&descriptor_write_sets, &[]);
+    }
+
+    Ok(descriptor_set)
+}
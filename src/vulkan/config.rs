@@ -0,0 +1,180 @@
+use super::constants::{
+    DEFAULT_FRAMES_IN_FLIGHT, DEFAULT_RECORDING_THREADS, OPTIONAL_DEVICE_EXTENSIONS,
+};
+use super::device_features::{DeviceFeature, DeviceRequirements};
+use super::device_selection::DeviceSelector;
+use super::present_mode::PresentPolicy;
+use super::validation::{parse_message_severities, parse_message_types, ValidationConfig};
+use crate::error::PotatoError;
+use log::warn;
+use std::str::FromStr;
+
+pub const PRESENT_MODE_ENV: &str = "POTATO_PRESENT_MODE";
+pub const DEVICE_ENV: &str = "POTATO_DEVICE";
+pub const VALIDATION_ENV: &str = "POTATO_VALIDATION";
+pub const VALIDATION_SEVERITY_ENV: &str = "POTATO_VALIDATION_SEVERITY";
+pub const VALIDATION_TYPES_ENV: &str = "POTATO_VALIDATION_TYPES";
+pub const VALIDATION_FEATURES_ENV: &str = "POTATO_VALIDATION_FEATURES";
+pub const RECORDING_THREADS_ENV: &str = "POTATO_RECORDING_THREADS";
+pub const FRAMES_IN_FLIGHT_ENV: &str = "POTATO_FRAMES_IN_FLIGHT";
+
+//Startup settings for the windowed renderer
+#[derive(Clone, Debug, PartialEq)]
+pub struct GraphicsConfig {
+    pub present_policy: PresentPolicy,
+    //Overrides the scored device choice
+    pub device: Option<DeviceSelector>,
+    //Features and extensions on top of the swapchain, which is always required
+    pub device_requirements: DeviceRequirements,
+    pub validation: ValidationConfig,
+    //Threads recording secondary command buffers in FrameContext::record_parallel_render_pass,
+    //secondary command pools are only created when this is more than one
+    pub recording_threads: usize,
+    //Frames recorded ahead of the GPU, each with its own command buffers, uniform buffer and fence
+    pub frames_in_flight: usize,
+}
+
+impl Default for GraphicsConfig {
+    fn default() -> Self {
+        let mut device_requirements = DeviceRequirements::new()
+            .request_feature(DeviceFeature::SamplerAnisotropy)
+            .request_feature(DeviceFeature::SampleRateShading)
+            .request_feature(DeviceFeature::FillModeNonSolid)
+            .request_feature(DeviceFeature::TimelineSemaphore);
+        for name in OPTIONAL_DEVICE_EXTENSIONS.names {
+            device_requirements = device_requirements.request_extension(name);
+        }
+
+        GraphicsConfig {
+            present_policy: PresentPolicy::default(),
+            device: None,
+            device_requirements,
+            validation: ValidationConfig::default(),
+            recording_threads: DEFAULT_RECORDING_THREADS,
+            frames_in_flight: DEFAULT_FRAMES_IN_FLIGHT,
+        }
+    }
+}
+
+impl GraphicsConfig {
+    //Defaults overridden by any POTATO_* environment variables that are set, invalid values are logged and ignored
+    pub fn from_env() -> GraphicsConfig {
+        let mut config = GraphicsConfig::default();
+        if let Some(present_policy) = setting_from_env(PRESENT_MODE_ENV) {
+            config.present_policy = present_policy;
+        }
+        config.device = device_selector_from_env();
+        config.validation = validation_config_from_env();
+        config.recording_threads = recording_threads_from_env();
+        config.frames_in_flight = frames_in_flight_from_env();
+        config
+    }
+}
+
+//Startup settings for the offscreen renderer, the subset of GraphicsConfig without a window
+#[derive(Clone, Debug, PartialEq)]
+pub struct OffscreenConfig {
+    pub device: Option<DeviceSelector>,
+    pub validation: ValidationConfig,
+    pub recording_threads: usize,
+    pub frames_in_flight: usize,
+    //Color images taken in an order that is not round robin, like the images of a swapchain
+    //More than one makes frames claim images other frames still render to
+    pub target_count: usize,
+    //Paces frames and uploads with a timeline semaphore when the device has one, with fences otherwise
+    pub timeline_semaphores: bool,
+}
+
+impl Default for OffscreenConfig {
+    fn default() -> Self {
+        OffscreenConfig {
+            device: None,
+            validation: ValidationConfig::default(),
+            recording_threads: DEFAULT_RECORDING_THREADS,
+            frames_in_flight: DEFAULT_FRAMES_IN_FLIGHT,
+            target_count: 1,
+            timeline_semaphores: true,
+        }
+    }
+}
+
+impl OffscreenConfig {
+    //Defaults overridden by the POTATO_* environment variables that apply without a window
+    pub fn from_env() -> OffscreenConfig {
+        OffscreenConfig {
+            device: device_selector_from_env(),
+            validation: validation_config_from_env(),
+            recording_threads: recording_threads_from_env(),
+            frames_in_flight: frames_in_flight_from_env(),
+            ..Default::default()
+        }
+    }
+}
+
+//Compute has no config, the environment is its only override
+pub fn device_selector_from_env() -> Option<DeviceSelector> {
+    setting_from_env(DEVICE_ENV)
+}
+
+//Defaults overridden by the POTATO_VALIDATION* variables, shared by every renderer
+pub fn validation_config_from_env() -> ValidationConfig {
+    let mut validation = ValidationConfig::default();
+    if let Some(mode) = setting_from_env(VALIDATION_ENV) {
+        validation.mode = mode;
+    }
+    if let Some(severities) = parsed_from_env(VALIDATION_SEVERITY_ENV, parse_message_severities) {
+        validation.severities = severities;
+    }
+    if let Some(message_types) = parsed_from_env(VALIDATION_TYPES_ENV, parse_message_types) {
+        validation.message_types = message_types;
+    }
+    if let Some(features) = setting_from_env(VALIDATION_FEATURES_ENV) {
+        validation.features = features;
+    }
+    validation
+}
+
+//Shared by every renderer that records frames
+pub fn recording_threads_from_env() -> usize {
+    parsed_from_env(RECORDING_THREADS_ENV, |x| {
+        parse_count("recording threads", x)
+    })
+    .unwrap_or(DEFAULT_RECORDING_THREADS)
+}
+
+//Shared by every renderer that paces frames
+pub fn frames_in_flight_from_env() -> usize {
+    parsed_from_env(FRAMES_IN_FLIGHT_ENV, |x| parse_count("frames in flight", x))
+        .unwrap_or(DEFAULT_FRAMES_IN_FLIGHT)
+}
+
+fn parse_count(name: &str, s: &str) -> Result<usize, PotatoError> {
+    match s.trim().parse::<usize>() {
+        Ok(count) if count > 0 => Ok(count),
+        _ => Err(PotatoError::InvalidSetting {
+            name: name.to_string(),
+            value: s.to_string(),
+        }),
+    }
+}
+
+fn setting_from_env<T>(name: &str) -> Option<T>
+where
+    T: FromStr<Err = PotatoError>,
+{
+    parsed_from_env(name, |x| x.parse())
+}
+
+fn parsed_from_env<T, F>(name: &str, parse: F) -> Option<T>
+where
+    F: Fn(&str) -> Result<T, PotatoError>,
+{
+    let value = std::env::var(name).ok()?;
+    match parse(&value) {
+        Ok(setting) => Some(setting),
+        Err(e) => {
+            warn!("Ignoring {}: {}", name, e);
+            None
+        }
+    }
+}
@@ -1,14 +1,7 @@
-use super::vertex::Vertex;
+use ash::vk::{Format, SampleCountFlags};
 
-pub struct ValidationInfo {
-    pub is_enable: bool,
-    pub required_validation_layers: [&'static str; 1],
-}
-
-pub const VALIDATION: ValidationInfo = ValidationInfo {
-    is_enable: true,
-    required_validation_layers: ["VK_LAYER_KHRONOS_validation"],
-};
+//Loaded when validation is on, see ValidationConfig
+pub const VALIDATION_LAYER: &str = "VK_LAYER_KHRONOS_validation";
 
 pub struct DeviceExtension {
     pub names: [&'static str; 1],
@@ -18,25 +11,32 @@ pub const DEVICE_EXTENSTIONS: DeviceExtension = DeviceExtension {
     names: ["VK_KHR_swapchain"],
 };
 
-pub const MAX_FRAMES_IN_FLIGHT: usize = 2;
-
-pub const VERTICES_DATA: [Vertex; 4] = [
-    Vertex {
-        pos: [-0.5, -0.5],
-        color: [0.0, 0.2, 0.2],
-    },
-    Vertex {
-        pos: [0.5, -0.5],
-        color: [1.0, 1.0, 1.0],
-    },
-    Vertex {
-        pos: [0.5, 0.5],
-        color: [1.0, 1.0, 1.0],
-    },
-    Vertex {
-        pos: [-0.5, 0.5],
-        color: [0.0, 0.2, 0.2],
-    },
-];
+//Requested by the default GraphicsConfig, not needed to run but raise the score of devices that have them
+pub const OPTIONAL_DEVICE_EXTENSIONS: DeviceExtension = DeviceExtension {
+    names: ["VK_EXT_memory_budget"],
+};
+
+//Overridden by GraphicsConfig::frames_in_flight
+pub const DEFAULT_FRAMES_IN_FLIGHT: usize = 2;
+
+//Overridden by GraphicsConfig::recording_threads, one thread records inline without secondary command buffers
+pub const DEFAULT_RECORDING_THREADS: usize = 1;
 
-pub const INDICES_DATA: [u32; 6] = [0, 1, 2, 2, 3, 0];
+//Upper bound of available_recording_threads
+pub const MAX_RECORDING_THREADS: usize = 8;
+
+//Background of the default frame recording
+pub const CLEAR_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+
+//Clamped to what the device supports
+pub const MSAA_SAMPLES: SampleCountFlags = SampleCountFlags::TYPE_4;
+
+pub const OFFSCREEN_FORMAT: Format = Format::R8G8B8A8_SRGB;
+
+pub const TEXTURE_FORMAT: Format = Format::R8G8B8A8_SRGB;
+
+pub const DEPTH_FORMAT_CANDIDATES: [Format; 3] = [
+    Format::D32_SFLOAT,
+    Format::D24_UNORM_S8_UINT,
+    Format::D16_UNORM,
+];
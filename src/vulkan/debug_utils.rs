@@ -0,0 +1,198 @@
+use super::capabilities::EnabledInstanceCapabilities;
+use ash::extensions::ext::DebugUtils;
+use ash::vk::{
+    CommandBuffer, DebugUtilsLabelEXT, DebugUtilsObjectNameInfoEXT, Handle, Queue, StructureType,
+};
+use ash::Device;
+use log::{debug, warn};
+use std::ffi::CString;
+
+//Names objects and labels command buffers and queues for validation messages and captures
+//Every call does nothing when the instance was created without debug utils
+#[derive(Clone)]
+pub struct PotatoDebugUtils {
+    debug_utils_loader: Option<DebugUtils>,
+    device: ash::vk::Device,
+}
+
+impl PotatoDebugUtils {
+    pub fn new(
+        debug_utils_loader: &DebugUtils,
+        device: &Device,
+        instance_capabilities: &EnabledInstanceCapabilities,
+    ) -> PotatoDebugUtils {
+        let is_available =
+            instance_capabilities.has_extension(&DebugUtils::name().to_string_lossy());
+        if !is_available {
+            debug!("Debug utils unavailable, objects stay unnamed");
+        }
+
+        PotatoDebugUtils {
+            debug_utils_loader: is_available.then(|| debug_utils_loader.clone()),
+            device: device.handle(),
+        }
+    }
+
+    pub fn disabled() -> PotatoDebugUtils {
+        PotatoDebugUtils {
+            debug_utils_loader: None,
+            device: ash::vk::Device::null(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.debug_utils_loader.is_some()
+    }
+
+    //Failing to name an object is never worth failing the creation of it, so errors are only logged
+    pub fn name_object<T: Handle>(&self, object: T, name: &str) {
+        let debug_utils_loader = match &self.debug_utils_loader {
+            Some(debug_utils_loader) => debug_utils_loader,
+            None => return,
+        };
+        let object_handle = object.as_raw();
+        if object_handle == 0 {
+            return;
+        }
+        let object_name = match label_name(name) {
+            Some(object_name) => object_name,
+            None => return,
+        };
+
+        let name_info = DebugUtilsObjectNameInfoEXT {
+            s_type: StructureType::DEBUG_UTILS_OBJECT_NAME_INFO_EXT,
+            p_next: std::ptr::null(),
+            object_type: T::TYPE,
+            object_handle,
+            p_object_name: object_name.as_ptr(),
+        };
+        if let Err(e) =
+            unsafe { debug_utils_loader.set_debug_utils_object_name(self.device, &name_info) }
+        {
+            warn!("Failed to name {:?} {}: {}", T::TYPE, name, e);
+        }
+    }
+
+    //Objects created together get the same name followed by their position
+    pub fn name_objects<T: Handle + Copy>(&self, objects: &[T], name: &str) {
+        if !self.is_enabled() {
+            return;
+        }
+        for (i, x) in objects.iter().enumerate() {
+            self.name_object(*x, &format!("{} {}", name, i));
+        }
+    }
+
+    //The region ends when the returned label is dropped, which has to happen before recording ends
+    pub fn begin_command_label(
+        &self,
+        command_buffer: CommandBuffer,
+        name: &str,
+    ) -> CommandLabel<'_> {
+        let debug_utils_loader = self
+            .debug_utils_loader
+            .as_ref()
+            .and_then(|debug_utils_loader| {
+                let label_name = label_name(name)?;
+                unsafe {
+                    debug_utils_loader
+                        .cmd_begin_debug_utils_label(command_buffer, &create_label(&label_name))
+                };
+                Some(debug_utils_loader)
+            });
+
+        CommandLabel {
+            debug_utils_loader,
+            command_buffer,
+        }
+    }
+
+    pub fn insert_command_label(&self, command_buffer: CommandBuffer, name: &str) {
+        if let (Some(debug_utils_loader), Some(label_name)) =
+            (&self.debug_utils_loader, label_name(name))
+        {
+            unsafe {
+                debug_utils_loader
+                    .cmd_insert_debug_utils_label(command_buffer, &create_label(&label_name))
+            };
+        }
+    }
+
+    //The region ends when the returned label is dropped
+    pub fn begin_queue_label(&self, queue: Queue, name: &str) -> QueueLabel<'_> {
+        let debug_utils_loader = self
+            .debug_utils_loader
+            .as_ref()
+            .and_then(|debug_utils_loader| {
+                let label_name = label_name(name)?;
+                unsafe {
+                    debug_utils_loader
+                        .queue_begin_debug_utils_label(queue, &create_label(&label_name))
+                };
+                Some(debug_utils_loader)
+            });
+
+        QueueLabel {
+            debug_utils_loader,
+            queue,
+        }
+    }
+
+    pub fn insert_queue_label(&self, queue: Queue, name: &str) {
+        if let (Some(debug_utils_loader), Some(label_name)) =
+            (&self.debug_utils_loader, label_name(name))
+        {
+            unsafe {
+                debug_utils_loader.queue_insert_debug_utils_label(queue, &create_label(&label_name))
+            };
+        }
+    }
+}
+
+//Open label region in a command buffer
+pub struct CommandLabel<'a> {
+    debug_utils_loader: Option<&'a DebugUtils>,
+    command_buffer: CommandBuffer,
+}
+
+impl Drop for CommandLabel<'_> {
+    fn drop(&mut self) {
+        if let Some(debug_utils_loader) = self.debug_utils_loader {
+            unsafe { debug_utils_loader.cmd_end_debug_utils_label(self.command_buffer) };
+        }
+    }
+}
+
+//Open label region on a queue
+pub struct QueueLabel<'a> {
+    debug_utils_loader: Option<&'a DebugUtils>,
+    queue: Queue,
+}
+
+impl Drop for QueueLabel<'_> {
+    fn drop(&mut self) {
+        if let Some(debug_utils_loader) = self.debug_utils_loader {
+            unsafe { debug_utils_loader.queue_end_debug_utils_label(self.queue) };
+        }
+    }
+}
+
+//Names with an interior nul can not be passed to the driver and are skipped
+fn label_name(name: &str) -> Option<CString> {
+    match CString::new(name) {
+        Ok(label_name) => Some(label_name),
+        Err(_) => {
+            debug!("Skipping debug name {:?} containing a nul", name);
+            None
+        }
+    }
+}
+
+fn create_label(label_name: &CString) -> DebugUtilsLabelEXT {
+    DebugUtilsLabelEXT {
+        s_type: StructureType::DEBUG_UTILS_LABEL_EXT,
+        p_next: std::ptr::null(),
+        p_label_name: label_name.as_ptr(),
+        color: [0.0, 0.0, 0.0, 0.0],
+    }
+}
@@ -0,0 +1,74 @@
+use super::allocator::Allocator;
+use super::constants::DEPTH_FORMAT_CANDIDATES;
+use super::debug_utils::PotatoDebugUtils;
+use super::image::{create_attachment_image, create_attachment_images, AttachmentImage};
+use crate::error::PotatoError;
+use ash::vk::{
+    Extent2D, Format, FormatFeatureFlags, ImageAspectFlags, ImageUsageFlags, PhysicalDevice,
+    SampleCountFlags,
+};
+use ash::{Device, Instance};
+
+//First candidate, in order of precision, that can be an optimal tiling depth attachment
+pub fn find_depth_format(
+    instance: &Instance,
+    physical_device: PhysicalDevice,
+) -> Result<Format, PotatoError> {
+    DEPTH_FORMAT_CANDIDATES
+        .iter()
+        .copied()
+        .find(|x| {
+            let format_properties =
+                unsafe { instance.get_physical_device_format_properties(physical_device, *x) };
+            format_properties
+                .optimal_tiling_features
+                .contains(FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT)
+        })
+        .ok_or(PotatoError::NoSuitableDepthFormat)
+}
+
+//Depth is never stored after the render pass so it can stay in transient memory
+pub fn create_depth_image(
+    device: &Device,
+    debug_utils: &PotatoDebugUtils,
+    allocator: &mut Allocator,
+    depth_format: Format,
+    extent: Extent2D,
+    samples: SampleCountFlags,
+    name: &str,
+) -> Result<AttachmentImage, PotatoError> {
+    create_attachment_image(
+        device,
+        debug_utils,
+        allocator,
+        extent,
+        samples,
+        depth_format,
+        ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT | ImageUsageFlags::TRANSIENT_ATTACHMENT,
+        ImageAspectFlags::DEPTH,
+        name,
+    )
+}
+
+//One depth image per framebuffer so images in flight never share a depth attachment
+pub fn create_depth_images(
+    device: &Device,
+    debug_utils: &PotatoDebugUtils,
+    allocator: &mut Allocator,
+    depth_format: Format,
+    extent: Extent2D,
+    samples: SampleCountFlags,
+    count: usize,
+) -> Result<Vec<AttachmentImage>, PotatoError> {
+    create_attachment_images(device, allocator, count, |allocator, i| {
+        create_depth_image(
+            device,
+            debug_utils,
+            allocator,
+            depth_format,
+            extent,
+            samples,
+            &format!("Depth image {}", i),
+        )
+    })
+}
@@ -1,73 +1,134 @@
-use super::constants::VALIDATION;
-use super::queue_family::{find_graphical_queue_family, QueueFamily};
+use super::device_features::{DeviceFeatureStructs, EnabledDeviceFeatures};
+use super::queue_family::{
+    find_compute_queue_family, find_graphical_queue_family, find_offscreen_queue_family,
+    QueueFamily,
+};
 use super::surface::PotatoSurface;
 use super::utilities::conver_str_vec_to_c_str_ptr_vec;
-use ash::extensions::khr::Swapchain;
+use crate::error::PotatoError;
 use ash::vk::{
     DeviceCreateFlags, DeviceCreateInfo, DeviceQueueCreateFlags, DeviceQueueCreateInfo,
-    PhysicalDevice, PhysicalDeviceFeatures, StructureType,
+    PhysicalDevice, PhysicalDeviceFeatures2, StructureType,
 };
 use ash::Device;
 use ash::Instance;
 use log::debug;
+use std::os::raw::c_void;
 
 pub fn create_logical_device(
     instance: &Instance,
     physical_device: PhysicalDevice,
     surface: &PotatoSurface,
-) -> (Device, QueueFamily) {
-    let queue_family = find_graphical_queue_family(instance, physical_device, surface);
+    enabled_features: &EnabledDeviceFeatures,
+) -> Result<(Device, QueueFamily), PotatoError> {
+    let queue_family = find_graphical_queue_family(instance, physical_device, surface)?;
+    if !queue_family.is_complete() {
+        return Err(PotatoError::NoSuitableQueueFamily);
+    }
 
-    let queue_priorities = [1.0_f32];
+    let device = create_device(
+        instance,
+        physical_device,
+        &queue_family.unique_families(),
+        enabled_features,
+    )?;
 
-    let queue_create_info = DeviceQueueCreateInfo {
-        s_type: StructureType::DEVICE_QUEUE_CREATE_INFO,
-        p_next: std::ptr::null(),
-        flags: DeviceQueueCreateFlags::empty(),
-        queue_family_index: queue_family.graphics_family.unwrap() as u32,
-        p_queue_priorities: queue_priorities.as_ptr(),
-        queue_count: queue_priorities.len() as u32,
-    };
+    Ok((device, queue_family))
+}
 
-    let physical_device_features = PhysicalDeviceFeatures {
-        ..Default::default()
-    };
+pub fn create_compute_logical_device(
+    instance: &Instance,
+    physical_device: PhysicalDevice,
+    enabled_features: &EnabledDeviceFeatures,
+) -> Result<(Device, QueueFamily), PotatoError> {
+    let queue_family = find_compute_queue_family(instance, physical_device);
+
+    let device = create_device(
+        instance,
+        physical_device,
+        &[queue_family
+            .compute_family
+            .ok_or(PotatoError::NoSuitableQueueFamily)? as u32],
+        enabled_features,
+    )?;
+
+    Ok((device, queue_family))
+}
+
+pub fn create_offscreen_logical_device(
+    instance: &Instance,
+    physical_device: PhysicalDevice,
+    enabled_features: &EnabledDeviceFeatures,
+) -> Result<(Device, QueueFamily), PotatoError> {
+    let queue_family = find_offscreen_queue_family(instance, physical_device);
+
+    let device = create_device(
+        instance,
+        physical_device,
+        &[queue_family
+            .graphics_family
+            .ok_or(PotatoError::NoSuitableQueueFamily)? as u32],
+        enabled_features,
+    )?;
+
+    Ok((device, queue_family))
+}
+
+fn create_device(
+    instance: &Instance,
+    physical_device: PhysicalDevice,
+    queue_family_indices: &[u32],
+    enabled_features: &EnabledDeviceFeatures,
+) -> Result<Device, PotatoError> {
+    let queue_priorities = [1.0_f32];
+
+    //One queue from each family, the indices must already be unique
+    let queue_create_infos: Vec<DeviceQueueCreateInfo> = queue_family_indices
+        .iter()
+        .map(|x| DeviceQueueCreateInfo {
+            s_type: StructureType::DEVICE_QUEUE_CREATE_INFO,
+            p_next: std::ptr::null(),
+            flags: DeviceQueueCreateFlags::empty(),
+            queue_family_index: *x,
+            p_queue_priorities: queue_priorities.as_ptr(),
+            queue_count: queue_priorities.len() as u32,
+        })
+        .collect();
 
-    let (cstring_vec, enable_layer_names) =
-        conver_str_vec_to_c_str_ptr_vec(VALIDATION.required_validation_layers.to_vec());
-    debug!("{:?}", cstring_vec);
+    //Only the version structs that enable something are chained, older devices do not know the newer ones
+    let max_minor_version = enabled_features
+        .features
+        .iter()
+        .map(|x| x.minor_version())
+        .max()
+        .unwrap_or(0);
+    let mut feature_structs = DeviceFeatureStructs::from_features(&enabled_features.features);
+    let physical_device_features2 = feature_structs.chain(max_minor_version);
 
-    let enable_extension_names = [Swapchain::name().as_ptr()];
+    let (extension_cstrings, enable_extension_names) = conver_str_vec_to_c_str_ptr_vec(
+        enabled_features
+            .extensions
+            .iter()
+            .map(|x| x.as_str())
+            .collect(),
+    );
+    debug!("{:?}", extension_cstrings);
 
+    #[allow(deprecated)]
     let device_create_info = DeviceCreateInfo {
         s_type: StructureType::DEVICE_CREATE_INFO,
-        p_next: std::ptr::null(),
+        p_next: &physical_device_features2 as *const PhysicalDeviceFeatures2 as *const c_void,
         flags: DeviceCreateFlags::empty(),
-        queue_create_info_count: 1,
-        p_queue_create_infos: &queue_create_info,
-        enabled_layer_count: if VALIDATION.is_enable {
-            enable_layer_names.len()
-        } else {
-            0
-        } as u32,
-        pp_enabled_layer_names: if VALIDATION.is_enable {
-            enable_layer_names.as_ptr()
-        } else {
-            std::ptr::null()
-        },
+        queue_create_info_count: queue_create_infos.len() as u32,
+        p_queue_create_infos: queue_create_infos.as_ptr(),
+        //Device layers are deprecated, the instance layers apply to every device
+        enabled_layer_count: 0,
+        pp_enabled_layer_names: std::ptr::null(),
         enabled_extension_count: enable_extension_names.len() as u32,
         pp_enabled_extension_names: enable_extension_names.as_ptr(),
-        p_enabled_features: &physical_device_features,
+        //Core features travel in the p_next chain instead
+        p_enabled_features: std::ptr::null(),
     };
 
-    let device: Device = unsafe {
-        instance
-            .create_device(physical_device, &device_create_info, None)
-            .expect("Failed to create logical device")
-    };
-
-    let _graphics_queue =
-        unsafe { device.get_device_queue(queue_family.graphics_family.unwrap() as u32, 0) };
-
-    (device, queue_family)
+    Ok(unsafe { instance.create_device(physical_device, &device_create_info, None)? })
 }
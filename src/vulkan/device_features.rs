@@ -0,0 +1,366 @@
+use super::capabilities::{select_available, CapabilityReport};
+use super::utilities::vk_to_string;
+use crate::error::PotatoError;
+use ash::vk::{
+    api_version_minor, Bool32, PhysicalDevice, PhysicalDeviceFeatures, PhysicalDeviceFeatures2,
+    PhysicalDeviceVulkan11Features, PhysicalDeviceVulkan12Features, PhysicalDeviceVulkan13Features,
+    StructureType, TRUE,
+};
+use ash::Instance;
+use log::debug;
+use std::fmt::{Display, Formatter};
+use std::os::raw::c_void;
+
+//Features an application can ask for, grouped by the Vulkan version whose feature struct holds them
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DeviceFeature {
+    //Vulkan 1.0
+    FillModeNonSolid,
+    SamplerAnisotropy,
+    SampleRateShading,
+    WideLines,
+    GeometryShader,
+    TessellationShader,
+    MultiDrawIndirect,
+    ShaderInt64,
+    //Vulkan 1.1
+    Multiview,
+    ShaderDrawParameters,
+    //Vulkan 1.2
+    DescriptorIndexing,
+    RuntimeDescriptorArray,
+    DescriptorBindingPartiallyBound,
+    DescriptorBindingVariableDescriptorCount,
+    ShaderSampledImageArrayNonUniformIndexing,
+    BufferDeviceAddress,
+    TimelineSemaphore,
+    ScalarBlockLayout,
+    //Vulkan 1.3
+    DynamicRendering,
+    Synchronization2,
+    Maintenance4,
+}
+
+impl DeviceFeature {
+    pub const ALL: [DeviceFeature; 21] = [
+        DeviceFeature::FillModeNonSolid,
+        DeviceFeature::SamplerAnisotropy,
+        DeviceFeature::SampleRateShading,
+        DeviceFeature::WideLines,
+        DeviceFeature::GeometryShader,
+        DeviceFeature::TessellationShader,
+        DeviceFeature::MultiDrawIndirect,
+        DeviceFeature::ShaderInt64,
+        DeviceFeature::Multiview,
+        DeviceFeature::ShaderDrawParameters,
+        DeviceFeature::DescriptorIndexing,
+        DeviceFeature::RuntimeDescriptorArray,
+        DeviceFeature::DescriptorBindingPartiallyBound,
+        DeviceFeature::DescriptorBindingVariableDescriptorCount,
+        DeviceFeature::ShaderSampledImageArrayNonUniformIndexing,
+        DeviceFeature::BufferDeviceAddress,
+        DeviceFeature::TimelineSemaphore,
+        DeviceFeature::ScalarBlockLayout,
+        DeviceFeature::DynamicRendering,
+        DeviceFeature::Synchronization2,
+        DeviceFeature::Maintenance4,
+    ];
+
+    //Minor version of the feature struct, 0 is the core PhysicalDeviceFeatures
+    pub fn minor_version(&self) -> u32 {
+        match self {
+            DeviceFeature::FillModeNonSolid
+            | DeviceFeature::SamplerAnisotropy
+            | DeviceFeature::SampleRateShading
+            | DeviceFeature::WideLines
+            | DeviceFeature::GeometryShader
+            | DeviceFeature::TessellationShader
+            | DeviceFeature::MultiDrawIndirect
+            | DeviceFeature::ShaderInt64 => 0,
+            DeviceFeature::Multiview | DeviceFeature::ShaderDrawParameters => 1,
+            DeviceFeature::DescriptorIndexing
+            | DeviceFeature::RuntimeDescriptorArray
+            | DeviceFeature::DescriptorBindingPartiallyBound
+            | DeviceFeature::DescriptorBindingVariableDescriptorCount
+            | DeviceFeature::ShaderSampledImageArrayNonUniformIndexing
+            | DeviceFeature::BufferDeviceAddress
+            | DeviceFeature::TimelineSemaphore
+            | DeviceFeature::ScalarBlockLayout => 2,
+            DeviceFeature::DynamicRendering
+            | DeviceFeature::Synchronization2
+            | DeviceFeature::Maintenance4 => 3,
+        }
+    }
+
+    //Spelled as in the Vulkan spec so log lines can be searched for
+    pub fn name(&self) -> &'static str {
+        match self {
+            DeviceFeature::FillModeNonSolid => "fillModeNonSolid",
+            DeviceFeature::SamplerAnisotropy => "samplerAnisotropy",
+            DeviceFeature::SampleRateShading => "sampleRateShading",
+            DeviceFeature::WideLines => "wideLines",
+            DeviceFeature::GeometryShader => "geometryShader",
+            DeviceFeature::TessellationShader => "tessellationShader",
+            DeviceFeature::MultiDrawIndirect => "multiDrawIndirect",
+            DeviceFeature::ShaderInt64 => "shaderInt64",
+            DeviceFeature::Multiview => "multiview",
+            DeviceFeature::ShaderDrawParameters => "shaderDrawParameters",
+            DeviceFeature::DescriptorIndexing => "descriptorIndexing",
+            DeviceFeature::RuntimeDescriptorArray => "runtimeDescriptorArray",
+            DeviceFeature::DescriptorBindingPartiallyBound => "descriptorBindingPartiallyBound",
+            DeviceFeature::DescriptorBindingVariableDescriptorCount => {
+                "descriptorBindingVariableDescriptorCount"
+            }
+            DeviceFeature::ShaderSampledImageArrayNonUniformIndexing => {
+                "shaderSampledImageArrayNonUniformIndexing"
+            }
+            DeviceFeature::BufferDeviceAddress => "bufferDeviceAddress",
+            DeviceFeature::TimelineSemaphore => "timelineSemaphore",
+            DeviceFeature::ScalarBlockLayout => "scalarBlockLayout",
+            DeviceFeature::DynamicRendering => "dynamicRendering",
+            DeviceFeature::Synchronization2 => "synchronization2",
+            DeviceFeature::Maintenance4 => "maintenance4",
+        }
+    }
+}
+
+impl Display for DeviceFeature {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+//What the application declares, required items make a device unsuitable when missing, optional ones are dropped
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DeviceRequirements {
+    pub required_features: Vec<DeviceFeature>,
+    pub optional_features: Vec<DeviceFeature>,
+    pub required_extensions: Vec<String>,
+    pub optional_extensions: Vec<String>,
+}
+
+impl DeviceRequirements {
+    pub fn new() -> DeviceRequirements {
+        DeviceRequirements::default()
+    }
+
+    //Requiring something that was optional makes it required
+    pub fn require_feature(mut self, feature: DeviceFeature) -> DeviceRequirements {
+        self.optional_features.retain(|x| *x != feature);
+        if !self.required_features.contains(&feature) {
+            self.required_features.push(feature);
+        }
+        self
+    }
+
+    pub fn request_feature(mut self, feature: DeviceFeature) -> DeviceRequirements {
+        if !self.required_features.contains(&feature) && !self.optional_features.contains(&feature)
+        {
+            self.optional_features.push(feature);
+        }
+        self
+    }
+
+    pub fn require_extension(mut self, name: &str) -> DeviceRequirements {
+        self.optional_extensions.retain(|x| x != name);
+        if !self.required_extensions.iter().any(|x| x == name) {
+            self.required_extensions.push(name.to_string());
+        }
+        self
+    }
+
+    pub fn request_extension(mut self, name: &str) -> DeviceRequirements {
+        if !self.required_extensions.iter().any(|x| x == name)
+            && !self.optional_extensions.iter().any(|x| x == name)
+        {
+            self.optional_extensions.push(name.to_string());
+        }
+        self
+    }
+
+    //Enables everything required and whatever optional items the device has
+    pub fn negotiate(
+        &self,
+        supported_features: &[DeviceFeature],
+        available_extensions: &[String],
+    ) -> Result<EnabledDeviceFeatures, PotatoError> {
+        let missing_device_features: Vec<String> = self
+            .required_features
+            .iter()
+            .filter(|x| !supported_features.contains(x))
+            .map(|x| x.to_string())
+            .collect();
+        let (extensions, missing_device_extensions) = select_available(
+            &self.required_extensions,
+            &self.optional_extensions,
+            available_extensions,
+        );
+
+        CapabilityReport {
+            missing_device_extensions,
+            missing_device_features,
+            ..Default::default()
+        }
+        .into_result()?;
+
+        let mut features = self.required_features.clone();
+        features.extend(
+            self.optional_features
+                .iter()
+                .filter(|x| supported_features.contains(x)),
+        );
+
+        Ok(EnabledDeviceFeatures {
+            features,
+            extensions,
+        })
+    }
+}
+
+//Everything a logical device was created with, renderer code branches on this rather than on device support
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EnabledDeviceFeatures {
+    pub features: Vec<DeviceFeature>,
+    pub extensions: Vec<String>,
+}
+
+impl EnabledDeviceFeatures {
+    pub fn has_feature(&self, feature: DeviceFeature) -> bool {
+        self.features.contains(&feature)
+    }
+
+    pub fn has_extension(&self, name: &str) -> bool {
+        self.extensions.iter().any(|x| x == name)
+    }
+}
+
+//The core struct and one struct per Vulkan version, chained through p_next when querying or enabling
+#[derive(Clone, Copy, Default)]
+pub struct DeviceFeatureStructs {
+    pub core: PhysicalDeviceFeatures,
+    pub vulkan_11: PhysicalDeviceVulkan11Features,
+    pub vulkan_12: PhysicalDeviceVulkan12Features,
+    pub vulkan_13: PhysicalDeviceVulkan13Features,
+}
+
+impl DeviceFeatureStructs {
+    pub fn from_features(features: &[DeviceFeature]) -> DeviceFeatureStructs {
+        let mut feature_structs = DeviceFeatureStructs::default();
+        for feature in features {
+            *feature_structs.field_mut(*feature) = TRUE;
+        }
+        feature_structs
+    }
+
+    fn field_mut(&mut self, feature: DeviceFeature) -> &mut Bool32 {
+        match feature {
+            DeviceFeature::FillModeNonSolid => &mut self.core.fill_mode_non_solid,
+            DeviceFeature::SamplerAnisotropy => &mut self.core.sampler_anisotropy,
+            DeviceFeature::SampleRateShading => &mut self.core.sample_rate_shading,
+            DeviceFeature::WideLines => &mut self.core.wide_lines,
+            DeviceFeature::GeometryShader => &mut self.core.geometry_shader,
+            DeviceFeature::TessellationShader => &mut self.core.tessellation_shader,
+            DeviceFeature::MultiDrawIndirect => &mut self.core.multi_draw_indirect,
+            DeviceFeature::ShaderInt64 => &mut self.core.shader_int64,
+            DeviceFeature::Multiview => &mut self.vulkan_11.multiview,
+            DeviceFeature::ShaderDrawParameters => &mut self.vulkan_11.shader_draw_parameters,
+            DeviceFeature::DescriptorIndexing => &mut self.vulkan_12.descriptor_indexing,
+            DeviceFeature::RuntimeDescriptorArray => &mut self.vulkan_12.runtime_descriptor_array,
+            DeviceFeature::DescriptorBindingPartiallyBound => {
+                &mut self.vulkan_12.descriptor_binding_partially_bound
+            }
+            DeviceFeature::DescriptorBindingVariableDescriptorCount => {
+                &mut self.vulkan_12.descriptor_binding_variable_descriptor_count
+            }
+            DeviceFeature::ShaderSampledImageArrayNonUniformIndexing => {
+                &mut self
+                    .vulkan_12
+                    .shader_sampled_image_array_non_uniform_indexing
+            }
+            DeviceFeature::BufferDeviceAddress => &mut self.vulkan_12.buffer_device_address,
+            DeviceFeature::TimelineSemaphore => &mut self.vulkan_12.timeline_semaphore,
+            DeviceFeature::ScalarBlockLayout => &mut self.vulkan_12.scalar_block_layout,
+            DeviceFeature::DynamicRendering => &mut self.vulkan_13.dynamic_rendering,
+            DeviceFeature::Synchronization2 => &mut self.vulkan_13.synchronization2,
+            DeviceFeature::Maintenance4 => &mut self.vulkan_13.maintenance4,
+        }
+    }
+
+    //Links the version structs up to and including max_minor_version, 1.1 and 1.2 always go together since both need a 1.2 device
+    //The result points into self
+    pub fn chain(&mut self, max_minor_version: u32) -> PhysicalDeviceFeatures2 {
+        let mut p_next: *mut c_void = std::ptr::null_mut();
+        if max_minor_version >= 3 {
+            self.vulkan_13.p_next = p_next;
+            p_next = &mut self.vulkan_13 as *mut PhysicalDeviceVulkan13Features as *mut c_void;
+        }
+        if max_minor_version >= 1 {
+            self.vulkan_12.p_next = p_next;
+            p_next = &mut self.vulkan_12 as *mut PhysicalDeviceVulkan12Features as *mut c_void;
+            self.vulkan_11.p_next = p_next;
+            p_next = &mut self.vulkan_11 as *mut PhysicalDeviceVulkan11Features as *mut c_void;
+        }
+
+        PhysicalDeviceFeatures2 {
+            s_type: StructureType::PHYSICAL_DEVICE_FEATURES_2,
+            p_next,
+            features: self.core,
+        }
+    }
+}
+
+pub fn supported_device_features(
+    instance: &Instance,
+    physical_device: PhysicalDevice,
+) -> Vec<DeviceFeature> {
+    //The 1.1 struct was only added in 1.2, older devices only get the core features queried
+    let properties = unsafe { instance.get_physical_device_properties(physical_device) };
+    let max_minor_version = match api_version_minor(properties.api_version) {
+        0 | 1 => 0,
+        x => x,
+    };
+
+    let mut feature_structs = DeviceFeatureStructs::default();
+    let mut features2 = feature_structs.chain(max_minor_version);
+    unsafe { instance.get_physical_device_features2(physical_device, &mut features2) };
+    feature_structs.core = features2.features;
+
+    let mut supported = vec![];
+    for feature in DeviceFeature::ALL {
+        if feature.minor_version() <= max_minor_version
+            && *feature_structs.field_mut(feature) == TRUE
+        {
+            supported.push(feature);
+        }
+    }
+    supported
+}
+
+pub fn available_device_extensions(
+    instance: &Instance,
+    physical_device: PhysicalDevice,
+) -> Result<Vec<String>, PotatoError> {
+    let available_extensions =
+        unsafe { instance.enumerate_device_extension_properties(physical_device)? };
+
+    Ok(available_extensions
+        .iter()
+        .map(|x| vk_to_string(&x.extension_name))
+        .collect())
+}
+
+pub fn negotiate_device_features(
+    instance: &Instance,
+    physical_device: PhysicalDevice,
+    requirements: &DeviceRequirements,
+) -> Result<EnabledDeviceFeatures, PotatoError> {
+    let supported_features = supported_device_features(instance, physical_device);
+    let available_extensions = available_device_extensions(instance, physical_device)?;
+
+    let enabled = requirements.negotiate(&supported_features, &available_extensions)?;
+    debug!(
+        "Enabled features {:?}, extensions {:?}",
+        enabled.features, enabled.extensions
+    );
+    Ok(enabled)
+}
@@ -0,0 +1,140 @@
+use crate::error::PotatoError;
+use ash::vk::{api_version_minor, PhysicalDeviceType};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+//Everything the score is based on, gathered once per physical device
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeviceCandidate {
+    pub index: usize,
+    pub name: String,
+    pub vendor_id: u32,
+    pub device_id: u32,
+    pub device_type: PhysicalDeviceType,
+    pub api_version: u32,
+    pub device_local_memory: u64,
+    pub optional_features: usize,
+    pub optional_extensions: usize,
+    //Required queues, extensions and features are all present
+    pub is_suitable: bool,
+}
+
+impl DeviceCandidate {
+    //None when the device is missing something required, higher is better otherwise
+    pub fn score(&self) -> Option<u64> {
+        if !self.is_suitable {
+            return None;
+        }
+
+        let type_score = match self.device_type {
+            PhysicalDeviceType::DISCRETE_GPU => 1000,
+            PhysicalDeviceType::INTEGRATED_GPU => 500,
+            PhysicalDeviceType::VIRTUAL_GPU => 250,
+            PhysicalDeviceType::CPU => 100,
+            _ => 50,
+        };
+        //16 points per GiB, enough to split two GPUs of the same type without outweighing it
+        let memory_score = (self.device_local_memory >> 30) * 16;
+        let version_score = api_version_minor(self.api_version) as u64 * 50;
+        let feature_score = self.optional_features as u64 * 25;
+        let extension_score = self.optional_extensions as u64 * 25;
+
+        Some(type_score + memory_score + version_score + feature_score + extension_score)
+    }
+}
+
+//User override of the scored choice
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DeviceSelector {
+    //Position in the enumeration order, as listed in the log
+    Index(usize),
+    //Case insensitive part of the device name
+    Name(String),
+    //PCI ids written in hex as vendor:device
+    Id { vendor_id: u32, device_id: u32 },
+}
+
+impl DeviceSelector {
+    pub fn matches(&self, candidate: &DeviceCandidate) -> bool {
+        match self {
+            DeviceSelector::Index(index) => candidate.index == *index,
+            DeviceSelector::Name(name) => {
+                candidate.name.to_lowercase().contains(&name.to_lowercase())
+            }
+            DeviceSelector::Id {
+                vendor_id,
+                device_id,
+            } => candidate.vendor_id == *vendor_id && candidate.device_id == *device_id,
+        }
+    }
+}
+
+impl Display for DeviceSelector {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeviceSelector::Index(index) => write!(f, "{}", index),
+            DeviceSelector::Name(name) => write!(f, "{}", name),
+            DeviceSelector::Id {
+                vendor_id,
+                device_id,
+            } => write!(f, "{:04x}:{:04x}", vendor_id, device_id),
+        }
+    }
+}
+
+impl FromStr for DeviceSelector {
+    type Err = PotatoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err(PotatoError::InvalidSetting {
+                name: "device".to_string(),
+                value: s.to_string(),
+            });
+        }
+
+        if let Ok(index) = s.parse() {
+            return Ok(DeviceSelector::Index(index));
+        }
+
+        let ids = s.split_once(':').and_then(|(vendor, device)| {
+            let vendor_id = u32::from_str_radix(vendor.trim_start_matches("0x"), 16).ok()?;
+            let device_id = u32::from_str_radix(device.trim_start_matches("0x"), 16).ok()?;
+            Some(DeviceSelector::Id {
+                vendor_id,
+                device_id,
+            })
+        });
+
+        Ok(ids.unwrap_or_else(|| DeviceSelector::Name(s.to_string())))
+    }
+}
+
+//Without a selector the highest score wins, ties go to the first enumerated
+//A selector matching no suitable device is an error, an explicit choice should never silently pick another device
+pub fn pick_device(
+    candidates: &[DeviceCandidate],
+    selector: Option<&DeviceSelector>,
+) -> Result<usize, PotatoError> {
+    if let Some(selector) = selector {
+        return candidates
+            .iter()
+            .position(|x| x.is_suitable && selector.matches(x))
+            .ok_or_else(|| PotatoError::InvalidSetting {
+                name: "device".to_string(),
+                value: selector.to_string(),
+            });
+    }
+
+    candidates
+        .iter()
+        .enumerate()
+        .filter_map(|(i, x)| x.score().map(|score| (i, score)))
+        .fold(None, |best: Option<(usize, u64)>, (i, score)| match best {
+            Some((_, best_score)) if best_score >= score => best,
+            _ => Some((i, score)),
+        })
+        .map(|(i, _)| i)
+        .ok_or(PotatoError::NoSuitableDevice)
+}
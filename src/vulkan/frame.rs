@@ -0,0 +1,370 @@
+use super::command_pool::{
+    allocate_command_buffers, begin_secondary_command_buffer, create_command_pool,
+};
+use super::constants::{CLEAR_COLOR, MAX_RECORDING_THREADS};
+use super::debug_utils::PotatoDebugUtils;
+use super::graphics_pipeline::{create_scissors, create_viewport};
+use super::mesh::MeshBuffers;
+use super::recording_threads::RecordingThreads;
+use crate::error::PotatoError;
+use ash::vk::{
+    ClearColorValue, ClearDepthStencilValue, ClearValue, CommandBuffer, CommandBufferBeginInfo,
+    CommandBufferLevel, CommandBufferUsageFlags, CommandPool, CommandPoolCreateFlags,
+    CommandPoolResetFlags, DescriptorSet, Extent2D, Framebuffer, IndexType, Offset2D, Pipeline,
+    PipelineBindPoint, PipelineLayout, Rect2D, RenderPass, RenderPassBeginInfo, StructureType,
+    SubpassContents,
+};
+use ash::Device;
+use std::ops::Range;
+
+//Records the commands of one frame, called every frame with a command buffer that has just begun
+pub type RecordCallback = Box<dyn FnMut(&FrameContext) -> Result<(), PotatoError>>;
+
+//Everything a record callback needs for the frame being recorded
+#[derive(Clone)]
+pub struct FrameContext<'a> {
+    pub device: &'a Device,
+    pub debug_utils: &'a PotatoDebugUtils,
+    pub command_buffer: CommandBuffer,
+    //One per recording thread, used by record_parallel_render_pass, empty when recording on one thread
+    pub secondary_command_buffers: &'a [CommandBuffer],
+    //Record into secondary_command_buffers, thread i into buffer i
+    pub recording_threads: &'a RecordingThreads,
+    //Position among the frames in flight, owns the command buffer, uniform buffer and descriptor set
+    pub frame_index: usize,
+    //Swapchain image being rendered to, owns the framebuffer
+    pub image_index: usize,
+    pub delta_time: f32,
+    pub extent: Extent2D,
+    pub render_pass: RenderPass,
+    pub framebuffer: Framebuffer,
+    pub graphics_pipeline: Pipeline,
+    pub pipeline_layout: PipelineLayout,
+    pub descriptor_set: DescriptorSet,
+    pub mesh: &'a MeshBuffers,
+}
+
+impl FrameContext<'_> {
+    //Viewport and scissor are dynamic and cover the whole framebuffer until the callback sets its own
+    pub fn begin_render_pass(&self, clear_color: [f32; 4]) {
+        self.begin_render_pass_with_contents(clear_color, SubpassContents::INLINE);
+        self.set_viewport_and_scissor();
+    }
+
+    fn begin_render_pass_with_contents(&self, clear_color: [f32; 4], contents: SubpassContents) {
+        let clear_values = [
+            ClearValue {
+                color: ClearColorValue {
+                    float32: clear_color,
+                },
+            },
+            ClearValue {
+                depth_stencil: ClearDepthStencilValue {
+                    depth: 1.0,
+                    stencil: 0,
+                },
+            },
+        ];
+
+        let render_pass_begin_info = RenderPassBeginInfo {
+            s_type: StructureType::RENDER_PASS_BEGIN_INFO,
+            p_next: std::ptr::null(),
+            render_pass: self.render_pass,
+            framebuffer: self.framebuffer,
+            render_area: Rect2D {
+                offset: Offset2D { x: 0, y: 0 },
+                extent: self.extent,
+            },
+            clear_value_count: clear_values.len() as u32,
+            p_clear_values: clear_values.as_ptr(),
+        };
+
+        unsafe {
+            self.device.cmd_begin_render_pass(
+                self.command_buffer,
+                &render_pass_begin_info,
+                contents,
+            )
+        };
+    }
+
+    pub fn set_viewport_and_scissor(&self) {
+        unsafe {
+            self.device
+                .cmd_set_viewport(self.command_buffer, 0, &create_viewport(&self.extent));
+            self.device
+                .cmd_set_scissor(self.command_buffer, 0, &create_scissors(&self.extent));
+        }
+    }
+
+    //Records draw_count draws on the recording threads, each thread gets a contiguous range and its own secondary command buffer
+    //The primary executes the secondaries in range order, so draws end up in the same order as recorded on one thread
+    //record_draws gets a context recording into the secondary of its thread, with the viewport and scissor already set
+    //Without secondaries, when recording on one thread, all draws are recorded inline into the primary
+    pub fn record_parallel_render_pass<F>(
+        &self,
+        clear_color: [f32; 4],
+        draw_count: usize,
+        record_draws: F,
+    ) -> Result<(), PotatoError>
+    where
+        F: Fn(&FrameContext, Range<usize>) -> Result<(), PotatoError> + Sync,
+    {
+        if self.secondary_command_buffers.is_empty() {
+            self.begin_render_pass(clear_color);
+            let result = record_draws(self, 0..draw_count);
+            self.end_render_pass();
+            return result;
+        }
+
+        let draw_ranges = split_draws(draw_count, self.secondary_command_buffers.len());
+        let command_buffers = &self.secondary_command_buffers[..draw_ranges.len()];
+
+        let record_draws = &record_draws;
+        let jobs: Vec<Box<dyn FnOnce() -> Result<(), PotatoError> + Send + '_>> = command_buffers
+            .iter()
+            .zip(draw_ranges)
+            .map(|(command_buffer, draws)| {
+                Box::new(move || self.record_secondary(*command_buffer, draws, record_draws)) as _
+            })
+            .collect();
+        self.recording_threads
+            .run(jobs)
+            .into_iter()
+            .collect::<Result<(), PotatoError>>()?;
+
+        self.begin_render_pass_with_contents(
+            clear_color,
+            SubpassContents::SECONDARY_COMMAND_BUFFERS,
+        );
+        if !command_buffers.is_empty() {
+            unsafe {
+                self.device
+                    .cmd_execute_commands(self.command_buffer, command_buffers)
+            };
+        }
+        self.end_render_pass();
+        Ok(())
+    }
+
+    fn record_secondary<F>(
+        &self,
+        command_buffer: CommandBuffer,
+        draws: Range<usize>,
+        record_draws: &F,
+    ) -> Result<(), PotatoError>
+    where
+        F: Fn(&FrameContext, Range<usize>) -> Result<(), PotatoError>,
+    {
+        begin_secondary_command_buffer(
+            self.device,
+            command_buffer,
+            self.render_pass,
+            self.framebuffer,
+        )?;
+        let secondary = FrameContext {
+            command_buffer,
+            secondary_command_buffers: &[],
+            ..self.clone()
+        };
+        secondary.set_viewport_and_scissor();
+        record_draws(&secondary, draws)?;
+        unsafe { self.device.end_command_buffer(command_buffer)? };
+        Ok(())
+    }
+
+    pub fn end_render_pass(&self) {
+        unsafe { self.device.cmd_end_render_pass(self.command_buffer) };
+    }
+
+    //Draws mesh with the graphics pipeline and the descriptor set of the frame
+    pub fn draw_mesh(&self, mesh: &MeshBuffers) {
+        let vertex_buffers = [mesh.vertex_buffer];
+        let offsets = [0_u64];
+        let descriptor_sets_to_bind = [self.descriptor_set];
+        unsafe {
+            self.device.cmd_bind_pipeline(
+                self.command_buffer,
+                PipelineBindPoint::GRAPHICS,
+                self.graphics_pipeline,
+            );
+            self.device
+                .cmd_bind_vertex_buffers(self.command_buffer, 0, &vertex_buffers, &offsets);
+            self.device.cmd_bind_index_buffer(
+                self.command_buffer,
+                mesh.index_buffer,
+                0,
+                IndexType::UINT32,
+            );
+            self.device.cmd_bind_descriptor_sets(
+                self.command_buffer,
+                PipelineBindPoint::GRAPHICS,
+                self.pipeline_layout,
+                0,
+                &descriptor_sets_to_bind,
+                &[],
+            );
+            self.device
+                .cmd_draw_indexed(self.command_buffer, mesh.index_count, 1, 0, 0, 0);
+        }
+    }
+}
+
+//What is recorded when no callback is set, the mesh cleared to CLEAR_COLOR
+pub fn record_default_frame(frame: &FrameContext) -> Result<(), PotatoError> {
+    let _frame_label = frame.debug_utils.begin_command_label(
+        frame.command_buffer,
+        &format!("Frame {}", frame.image_index),
+    );
+    frame.begin_render_pass(CLEAR_COLOR);
+    frame
+        .debug_utils
+        .insert_command_label(frame.command_buffer, "Draw mesh");
+    frame.draw_mesh(frame.mesh);
+    frame.end_render_pass();
+    Ok(())
+}
+
+//A pool per frame in flight, resetting the whole pool is cheaper than resetting single buffers
+//With more than one recording thread every thread has its own pool as well, since a pool can only be used by one thread at a time
+pub struct FrameCommands {
+    pub command_pool: CommandPool,
+    pub command_buffer: CommandBuffer,
+    pub secondary_command_pools: Vec<CommandPool>,
+    //One per recording thread, empty when recording on one thread
+    pub secondary_command_buffers: Vec<CommandBuffer>,
+}
+
+impl FrameCommands {
+    //Only valid once the previous submission of this frame has finished
+    pub fn begin(&self, device: &Device) -> Result<CommandBuffer, PotatoError> {
+        let command_buffer_begin_info = CommandBufferBeginInfo {
+            s_type: StructureType::COMMAND_BUFFER_BEGIN_INFO,
+            p_next: std::ptr::null(),
+            p_inheritance_info: std::ptr::null(),
+            flags: CommandBufferUsageFlags::ONE_TIME_SUBMIT,
+        };
+
+        unsafe {
+            device.reset_command_pool(self.command_pool, CommandPoolResetFlags::empty())?;
+            for command_pool in &self.secondary_command_pools {
+                device.reset_command_pool(*command_pool, CommandPoolResetFlags::empty())?;
+            }
+            device.begin_command_buffer(self.command_buffer, &command_buffer_begin_info)?;
+        }
+        Ok(self.command_buffer)
+    }
+
+    pub fn destroy(&self, device: &Device) {
+        unsafe {
+            device.destroy_command_pool(self.command_pool, None);
+            self.secondary_command_pools
+                .iter()
+                .for_each(|x| device.destroy_command_pool(*x, None));
+        }
+    }
+}
+
+pub fn create_frame_commands(
+    device: &Device,
+    debug_utils: &PotatoDebugUtils,
+    queue_family_index: u32,
+    count: usize,
+    thread_count: usize,
+) -> Result<Vec<FrameCommands>, PotatoError> {
+    let mut frame_commands: Vec<FrameCommands> = vec![];
+    for i in 0..count {
+        let mut single_frame_commands = FrameCommands {
+            command_pool: CommandPool::null(),
+            command_buffer: CommandBuffer::null(),
+            secondary_command_pools: vec![],
+            secondary_command_buffers: vec![],
+        };
+        let result = fill_frame_commands(
+            device,
+            debug_utils,
+            queue_family_index,
+            i,
+            thread_count,
+            &mut single_frame_commands,
+        );
+        frame_commands.push(single_frame_commands);
+        if let Err(e) = result {
+            frame_commands.iter().for_each(|x| x.destroy(device));
+            return Err(e);
+        }
+    }
+    Ok(frame_commands)
+}
+
+//Pools are added as soon as they are created so that destroy cleans up after a failure
+fn fill_frame_commands(
+    device: &Device,
+    debug_utils: &PotatoDebugUtils,
+    queue_family_index: u32,
+    frame_index: usize,
+    thread_count: usize,
+    frame_commands: &mut FrameCommands,
+) -> Result<(), PotatoError> {
+    frame_commands.command_pool = create_command_pool(
+        device,
+        debug_utils,
+        queue_family_index,
+        CommandPoolCreateFlags::TRANSIENT,
+        &format!("Frame command pool {}", frame_index),
+    )?;
+    frame_commands.command_buffer = allocate_command_buffers(
+        device,
+        frame_commands.command_pool,
+        CommandBufferLevel::PRIMARY,
+        1,
+    )?[0];
+    debug_utils.name_object(
+        frame_commands.command_buffer,
+        &format!("Frame command buffer {}", frame_index),
+    );
+
+    if thread_count <= 1 {
+        return Ok(());
+    }
+    for thread_index in 0..thread_count {
+        let command_pool = create_command_pool(
+            device,
+            debug_utils,
+            queue_family_index,
+            CommandPoolCreateFlags::TRANSIENT,
+            &format!("Frame {} thread command pool {}", frame_index, thread_index),
+        )?;
+        frame_commands.secondary_command_pools.push(command_pool);
+        let command_buffer =
+            allocate_command_buffers(device, command_pool, CommandBufferLevel::SECONDARY, 1)?[0];
+        debug_utils.name_object(
+            command_buffer,
+            &format!(
+                "Frame {} thread command buffer {}",
+                frame_index, thread_index
+            ),
+        );
+        frame_commands
+            .secondary_command_buffers
+            .push(command_buffer);
+    }
+    Ok(())
+}
+
+//Splits draws into at most thread_count contiguous ranges of nearly equal size, none of them empty
+pub fn split_draws(draw_count: usize, thread_count: usize) -> Vec<Range<usize>> {
+    let range_count = thread_count.max(1).min(draw_count);
+    (0..range_count)
+        .map(|i| (i * draw_count / range_count)..((i + 1) * draw_count / range_count))
+        .collect()
+}
+
+//One thread per core, more threads than that only add contention
+//A good recording_threads when opting into parallel recording
+pub fn available_recording_threads() -> usize {
+    std::thread::available_parallelism()
+        .map(|x| x.get())
+        .unwrap_or(1)
+        .min(MAX_RECORDING_THREADS)
+}
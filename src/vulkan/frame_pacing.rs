@@ -0,0 +1,323 @@
+use super::debug_utils::PotatoDebugUtils;
+use super::timeline::GpuTimeline;
+use crate::error::PotatoError;
+use ash::vk::{
+    CommandBuffer, Fence, FenceCreateFlags, FenceCreateInfo, PipelineStageFlags, Queue, Semaphore,
+    SemaphoreCreateFlags, SemaphoreCreateInfo, StructureType, SubmitInfo, SurfaceCapabilitiesKHR,
+};
+use ash::Device;
+
+//Which frame in flight last rendered to each image
+//Images are acquired in whatever order the presentation engine likes, so a frame can get an image another frame still renders to
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ImagesInFlight {
+    frames: Vec<Option<usize>>,
+}
+
+impl ImagesInFlight {
+    pub fn new(image_count: usize) -> ImagesInFlight {
+        ImagesInFlight {
+            frames: vec![None; image_count],
+        }
+    }
+
+    pub fn image_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    //Hands image_index to frame_index and returns the frame that has to finish before it can be rendered to
+    //The frame doing the claiming has already waited for its own previous submission
+    pub fn claim(&mut self, image_index: usize, frame_index: usize) -> Option<usize> {
+        let previous_frame = self.frames[image_index].replace(frame_index);
+        previous_frame.filter(|x| *x != frame_index)
+    }
+
+    //New images are owned by no frame
+    pub fn reset(&mut self, image_count: usize) {
+        self.frames = vec![None; image_count];
+    }
+}
+
+//One more than the minimum so a frame can be recorded while the driver holds on to the others,
+//asking for the maximum only adds latency and memory
+pub fn choose_swapchain_image_count(capabilities: &SurfaceCapabilitiesKHR) -> u32 {
+    let image_count = capabilities.min_image_count + 1;
+    if capabilities.max_image_count > 0 {
+        image_count.min(capabilities.max_image_count)
+    } else {
+        image_count
+    }
+}
+
+//What the CPU waits on before reusing a frame
+enum FrameSync {
+    //Submissions are counted on the CPU, a signaled fence means its frame_value and all before it have completed
+    Fences {
+        fences: Vec<Fence>,
+        frame_values: Vec<u64>,
+        last_value: u64,
+    },
+    //Value the latest submission of each frame signals, 0 before the first one
+    Timeline {
+        timeline: GpuTimeline,
+        frame_values: Vec<u64>,
+    },
+}
+
+//Tracks the frames in flight and the images they render to, with fences or with the values of one GPU timeline
+//A frame is only recorded again once its previous submission finished, and only renders to an image no other frame still uses
+pub struct FramePacer {
+    current_frame: usize,
+    frames_in_flight: usize,
+    sync: FrameSync,
+    images_in_flight: ImagesInFlight,
+}
+
+impl FramePacer {
+    pub fn new(
+        device: &Device,
+        debug_utils: &PotatoDebugUtils,
+        frames_in_flight: usize,
+        image_count: usize,
+        use_timeline: bool,
+    ) -> Result<FramePacer, PotatoError> {
+        let frames_in_flight = frames_in_flight.max(1);
+        let sync = if use_timeline {
+            FrameSync::Timeline {
+                timeline: GpuTimeline::new(device, debug_utils, "Frame timeline")?,
+                frame_values: vec![0; frames_in_flight],
+            }
+        } else {
+            FrameSync::Fences {
+                fences: create_fences(device, debug_utils, frames_in_flight)?,
+                frame_values: vec![0; frames_in_flight],
+                last_value: 0,
+            }
+        };
+
+        Ok(FramePacer {
+            current_frame: 0,
+            frames_in_flight,
+            sync,
+            images_in_flight: ImagesInFlight::new(image_count),
+        })
+    }
+
+    pub fn current_frame(&self) -> usize {
+        self.current_frame
+    }
+
+    //Shared with uploads and anything else that wants to know what the GPU has finished
+    pub fn timeline(&self) -> Option<&GpuTimeline> {
+        match &self.sync {
+            FrameSync::Fences { .. } => None,
+            FrameSync::Timeline { timeline, .. } => Some(timeline),
+        }
+    }
+
+    //Value of the latest submission, what is retired now can be destroyed once completed_value reaches it
+    pub fn submitted_value(&self) -> u64 {
+        match &self.sync {
+            FrameSync::Fences { last_value, .. } => *last_value,
+            FrameSync::Timeline { timeline, .. } => timeline.last_value(),
+        }
+    }
+
+    //Value of the latest submission the GPU has finished, never blocks
+    pub fn completed_value(&self, device: &Device) -> Result<u64, PotatoError> {
+        match &self.sync {
+            FrameSync::Fences {
+                fences,
+                frame_values,
+                ..
+            } => {
+                let mut completed_value = 0;
+                for (fence, frame_value) in fences.iter().zip(frame_values) {
+                    if unsafe { device.get_fence_status(*fence)? } {
+                        completed_value = completed_value.max(*frame_value);
+                    }
+                }
+                Ok(completed_value)
+            }
+            FrameSync::Timeline { timeline, .. } => timeline.completed_value(device),
+        }
+    }
+
+    fn wait_for_frame(&self, device: &Device, frame_index: usize) -> Result<(), PotatoError> {
+        match &self.sync {
+            FrameSync::Fences { fences, .. } => {
+                let wait_fences = [fences[frame_index]];
+                unsafe { device.wait_for_fences(&wait_fences, true, u64::MAX)? };
+                Ok(())
+            }
+            FrameSync::Timeline {
+                timeline,
+                frame_values,
+            } => timeline.wait(device, frame_values[frame_index]),
+        }
+    }
+
+    //Afterwards everything owned by the current frame can be reused
+    pub fn wait_for_current_frame(&self, device: &Device) -> Result<(), PotatoError> {
+        self.wait_for_frame(device, self.current_frame)
+    }
+
+    //Waits for the frame that last rendered to image_index unless that is the current frame
+    pub fn claim_image(&mut self, device: &Device, image_index: usize) -> Result<(), PotatoError> {
+        if let Some(previous_frame) = self.images_in_flight.claim(image_index, self.current_frame) {
+            self.wait_for_frame(device, previous_frame)?;
+        }
+        Ok(())
+    }
+
+    //Submits the commands of the current frame so that waiting for it waits for them
+    pub fn submit(
+        &mut self,
+        device: &Device,
+        queue: Queue,
+        command_buffer: CommandBuffer,
+        wait_semaphores: &[Semaphore],
+        wait_stages: &[PipelineStageFlags],
+        signal_semaphores: &[Semaphore],
+    ) -> Result<(), PotatoError> {
+        let command_buffers = [command_buffer];
+        match &mut self.sync {
+            FrameSync::Fences {
+                fences,
+                frame_values,
+                last_value,
+            } => {
+                let submit_infos = [SubmitInfo {
+                    s_type: StructureType::SUBMIT_INFO,
+                    p_next: std::ptr::null(),
+                    wait_semaphore_count: wait_semaphores.len() as u32,
+                    p_wait_semaphores: wait_semaphores.as_ptr(),
+                    p_wait_dst_stage_mask: wait_stages.as_ptr(),
+                    command_buffer_count: command_buffers.len() as u32,
+                    p_command_buffers: command_buffers.as_ptr(),
+                    signal_semaphore_count: signal_semaphores.len() as u32,
+                    p_signal_semaphores: signal_semaphores.as_ptr(),
+                }];
+                //Only reset right before submitting, a fence reset for a frame that is never submitted would be waited on forever
+                let in_flight_fence = fences[self.current_frame];
+                unsafe {
+                    device.reset_fences(&[in_flight_fence])?;
+                    device.queue_submit(queue, &submit_infos, in_flight_fence)?;
+                }
+                *last_value += 1;
+                frame_values[self.current_frame] = *last_value;
+            }
+            FrameSync::Timeline {
+                timeline,
+                frame_values,
+            } => {
+                frame_values[self.current_frame] = timeline.submit(
+                    device,
+                    queue,
+                    &command_buffers,
+                    wait_semaphores,
+                    wait_stages,
+                    signal_semaphores,
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn advance(&mut self) {
+        self.current_frame = (self.current_frame + 1) % self.frames_in_flight;
+    }
+
+    pub fn wait_for_all(&self, device: &Device) -> Result<(), PotatoError> {
+        match &self.sync {
+            FrameSync::Fences { fences, .. } => {
+                unsafe { device.wait_for_fences(fences, true, u64::MAX)? };
+                Ok(())
+            }
+            FrameSync::Timeline { timeline, .. } => timeline.wait(device, timeline.last_value()),
+        }
+    }
+
+    //For a new set of images, frames still rendering to the old ones no longer hold up claims
+    pub fn reset_images(&mut self, image_count: usize) {
+        self.images_in_flight.reset(image_count);
+    }
+
+    pub fn destroy(&self, device: &Device) {
+        match &self.sync {
+            FrameSync::Fences { fences, .. } => fences
+                .iter()
+                .for_each(|x| unsafe { device.destroy_fence(*x, None) }),
+            FrameSync::Timeline { timeline, .. } => timeline.destroy(device),
+        }
+    }
+}
+
+fn create_fences(
+    device: &Device,
+    debug_utils: &PotatoDebugUtils,
+    count: usize,
+) -> Result<Vec<Fence>, PotatoError> {
+    let fence_create_info = FenceCreateInfo {
+        s_type: StructureType::FENCE_CREATE_INFO,
+        p_next: std::ptr::null(),
+        flags: FenceCreateFlags::SIGNALED,
+    };
+
+    let mut fences = vec![];
+    for _ in 0..count {
+        match unsafe { device.create_fence(&fence_create_info, None) } {
+            Ok(fence) => fences.push(fence),
+            Err(e) => {
+                fences
+                    .iter()
+                    .for_each(|x| unsafe { device.destroy_fence(*x, None) });
+                return Err(e.into());
+            }
+        }
+    }
+    debug_utils.name_objects(&fences, "In flight fence");
+    Ok(fences)
+}
+
+pub fn create_semaphore(
+    device: &Device,
+    debug_utils: &PotatoDebugUtils,
+    name: &str,
+) -> Result<Semaphore, PotatoError> {
+    let semaphore_create_info = SemaphoreCreateInfo {
+        s_type: StructureType::SEMAPHORE_CREATE_INFO,
+        p_next: std::ptr::null(),
+        flags: SemaphoreCreateFlags::empty(),
+    };
+
+    let semaphore = unsafe { device.create_semaphore(&semaphore_create_info, None)? };
+    debug_utils.name_object(semaphore, name);
+    Ok(semaphore)
+}
+
+//Semaphores named "{name} {i}", the ones already created are destroyed on failure
+pub fn create_semaphores(
+    device: &Device,
+    debug_utils: &PotatoDebugUtils,
+    count: usize,
+    name: &str,
+) -> Result<Vec<Semaphore>, PotatoError> {
+    let mut semaphores = vec![];
+    for i in 0..count {
+        match create_semaphore(device, debug_utils, &format!("{} {}", name, i)) {
+            Ok(semaphore) => semaphores.push(semaphore),
+            Err(e) => {
+                destroy_semaphores(device, &semaphores);
+                return Err(e);
+            }
+        }
+    }
+    Ok(semaphores)
+}
+
+pub fn destroy_semaphores(device: &Device, semaphores: &[Semaphore]) {
+    semaphores
+        .iter()
+        .for_each(|x| unsafe { device.destroy_semaphore(*x, None) });
+}
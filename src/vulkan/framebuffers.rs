@@ -1,3 +1,5 @@
+use super::debug_utils::PotatoDebugUtils;
+use crate::error::PotatoError;
 use ash::vk::{
     Extent2D, Framebuffer, FramebufferCreateFlags, FramebufferCreateInfo, ImageView, RenderPass,
     StructureType,
@@ -6,15 +8,24 @@ use ash::Device;
 
 pub fn create_framebuffers(
     device: &Device,
+    debug_utils: &PotatoDebugUtils,
     render_pass: RenderPass,
     image_views: &[ImageView],
+    depth_image_views: &[ImageView],
+    color_image_views: &[ImageView],
     swapchain_extent: &Extent2D,
-) -> Vec<Framebuffer> {
+) -> Result<Vec<Framebuffer>, PotatoError> {
     image_views
         .iter()
-        .map(|x| {
-            let attachments = [*x];
-            FramebufferCreateInfo {
+        .zip(depth_image_views.iter())
+        .enumerate()
+        .map(|(i, (x, depth))| {
+            //Without multisampled color the image is rendered to directly, otherwise it is the resolve target
+            let attachments = match color_image_views.get(i) {
+                Some(color) => vec![*color, *depth, *x],
+                None => vec![*x, *depth],
+            };
+            let framebuffer_create_info = FramebufferCreateInfo {
                 s_type: StructureType::FRAMEBUFFER_CREATE_INFO,
                 p_next: std::ptr::null(),
                 flags: FramebufferCreateFlags::empty(),
@@ -24,12 +35,11 @@ pub fn create_framebuffers(
                 width: swapchain_extent.width,
                 height: swapchain_extent.height,
                 layers: 1,
-            }
-        })
-        .map(|x| unsafe {
-            device
-                .create_framebuffer(&x, None)
-                .expect("Failed to create framebuffer")
+            };
+
+            let framebuffer = unsafe { device.create_framebuffer(&framebuffer_create_info, None)? };
+            debug_utils.name_object(framebuffer, &format!("Framebuffer {}", i));
+            Ok(framebuffer)
         })
         .collect()
 }
@@ -1,11 +1,14 @@
+use super::debug_utils::PotatoDebugUtils;
 use super::vertex::Vertex;
+use crate::error::PotatoError;
 use crate::io::file::read_file_to_bytes;
 use ash::vk::{
     BlendFactor, BlendOp, ColorComponentFlags, CompareOp, CullModeFlags, DescriptorSetLayout,
-    Extent2D, FrontFace, GraphicsPipelineCreateInfo, LogicOp, Offset2D, Pipeline, PipelineCache,
-    PipelineColorBlendAttachmentState, PipelineColorBlendStateCreateFlags,
+    DynamicState, Extent2D, FrontFace, GraphicsPipelineCreateInfo, LogicOp, Offset2D, Pipeline,
+    PipelineCache, PipelineColorBlendAttachmentState, PipelineColorBlendStateCreateFlags,
     PipelineColorBlendStateCreateInfo, PipelineCreateFlags, PipelineDepthStencilStateCreateFlags,
-    PipelineDepthStencilStateCreateInfo, PipelineInputAssemblyStateCreateFlags,
+    PipelineDepthStencilStateCreateInfo, PipelineDynamicStateCreateFlags,
+    PipelineDynamicStateCreateInfo, PipelineInputAssemblyStateCreateFlags,
     PipelineInputAssemblyStateCreateInfo, PipelineLayout, PipelineLayoutCreateFlags,
     PipelineLayoutCreateInfo, PipelineMultisampleStateCreateFlags,
     PipelineMultisampleStateCreateInfo, PipelineRasterizationStateCreateFlags,
@@ -15,22 +18,24 @@ use ash::vk::{
     PipelineViewportStateCreateInfo, PolygonMode, PrimitiveTopology, Rect2D, RenderPass,
     SampleCountFlags, ShaderModule, ShaderModuleCreateFlags, ShaderModuleCreateInfo,
     ShaderStageFlags, StencilOp, StencilOpState, StructureType, VertexInputAttributeDescription,
-    VertexInputBindingDescription, Viewport, FALSE,
+    VertexInputBindingDescription, Viewport, FALSE, TRUE,
 };
 use ash::Device;
 use std::ffi::CString;
 
 pub fn create_graphics_pipeline(
     device: &Device,
+    debug_utils: &PotatoDebugUtils,
     render_pass: RenderPass,
-    swapchain_extent: Extent2D,
     ubo_set_layout: DescriptorSetLayout,
-) -> (Pipeline, PipelineLayout) {
-    let vert_shader = read_file_to_bytes("src/shaders/spv/shader-vert.spv").unwrap();
-    let frag_shader = read_file_to_bytes("src/shaders/spv/shader-frag.spv").unwrap();
+    samples: SampleCountFlags,
+    polygon_mode: PolygonMode,
+) -> Result<(Pipeline, PipelineLayout), PotatoError> {
+    let vert_shader = read_shader("src/shaders/spv/shader-vert.spv")?;
+    let frag_shader = read_shader("src/shaders/spv/shader-frag.spv")?;
 
-    let vert_module = create_shader_module(device, vert_shader);
-    let frag_module = create_shader_module(device, frag_shader);
+    let vert_module = create_shader_module(device, debug_utils, vert_shader, "Vertex shader")?;
+    let frag_module = create_shader_module(device, debug_utils, frag_shader, "Fragment shader")?;
 
     let main_function_name = CString::new("main").unwrap();
 
@@ -62,12 +67,18 @@ pub fn create_graphics_pipeline(
         create_vertex_input_state_create_info(&attribute_description, &binding_description);
     let vertex_input_assembly_state_info = create_vertex_input_assembly_state_info();
 
-    let viewports = create_viewport(&swapchain_extent);
-    let scissors = create_scissors(&swapchain_extent);
-
-    let viewport_state_create_info = create_viewport_state_create_info(&viewports, &scissors);
-    let rasterization_state_create_info = create_rasterization_state_create_info();
-    let multisample_state_create_info = create_multisample_state_create_info();
+    //Viewport and scissor are recorded with the commands so a resize does not need a new pipeline
+    let viewport_state_create_info = create_viewport_state_create_info();
+    let dynamic_states = [DynamicState::VIEWPORT, DynamicState::SCISSOR];
+    let dynamic_state_create_info = PipelineDynamicStateCreateInfo {
+        s_type: StructureType::PIPELINE_DYNAMIC_STATE_CREATE_INFO,
+        p_next: std::ptr::null(),
+        flags: PipelineDynamicStateCreateFlags::empty(),
+        dynamic_state_count: dynamic_states.len() as u32,
+        p_dynamic_states: dynamic_states.as_ptr(),
+    };
+    let rasterization_state_create_info = create_rasterization_state_create_info(polygon_mode);
+    let multisample_state_create_info = create_multisample_state_create_info(samples);
 
     let stencil_state = create_stencil_state();
 
@@ -79,11 +90,9 @@ pub fn create_graphics_pipeline(
     let set_layouts = [ubo_set_layout];
     let pipeline_layout_create_info = create_pipeline_layout_create_info(&set_layouts);
 
-    let pipeline_layout = unsafe {
-        device
-            .create_pipeline_layout(&pipeline_layout_create_info, None)
-            .expect("Failed to create pipeline layout")
-    };
+    let pipeline_layout =
+        unsafe { device.create_pipeline_layout(&pipeline_layout_create_info, None)? };
+    debug_utils.name_object(pipeline_layout, "Graphics pipeline layout");
 
     let graphics_pipeline_create_infos = [GraphicsPipelineCreateInfo {
         s_type: StructureType::GRAPHICS_PIPELINE_CREATE_INFO,
@@ -99,7 +108,7 @@ pub fn create_graphics_pipeline(
         p_multisample_state: &multisample_state_create_info,
         p_depth_stencil_state: &depth_state_create_info,
         p_color_blend_state: &color_blend_state,
-        p_dynamic_state: std::ptr::null(),
+        p_dynamic_state: &dynamic_state_create_info,
         layout: pipeline_layout,
         render_pass,
         subpass: 0,
@@ -110,17 +119,30 @@ pub fn create_graphics_pipeline(
     let graphics_pipelines = unsafe {
         device
             .create_graphics_pipelines(PipelineCache::null(), &graphics_pipeline_create_infos, None)
-            .expect("Failed to create graphics pipelines")
+            .map_err(|(_, result)| result)?
     };
     unsafe {
         device.destroy_shader_module(vert_module, None);
         device.destroy_shader_module(frag_module, None);
     }
+    debug_utils.name_object(graphics_pipelines[0], "Graphics pipeline");
 
-    (graphics_pipelines[0], pipeline_layout)
+    Ok((graphics_pipelines[0], pipeline_layout))
 }
 
-fn create_shader_module(device: &Device, code: Vec<u8>) -> ShaderModule {
+pub fn read_shader(path: &str) -> Result<Vec<u8>, PotatoError> {
+    read_file_to_bytes(path).map_err(|source| PotatoError::ShaderIo {
+        path: path.to_owned(),
+        source,
+    })
+}
+
+pub fn create_shader_module(
+    device: &Device,
+    debug_utils: &PotatoDebugUtils,
+    code: Vec<u8>,
+    name: &str,
+) -> Result<ShaderModule, PotatoError> {
     #[allow(clippy::cast_ptr_alignment)]
     let shader_module_create_info = ShaderModuleCreateInfo {
         s_type: StructureType::SHADER_MODULE_CREATE_INFO,
@@ -130,11 +152,9 @@ fn create_shader_module(device: &Device, code: Vec<u8>) -> ShaderModule {
         p_code: code.as_ptr() as *const u32,
     };
 
-    unsafe {
-        device
-            .create_shader_module(&shader_module_create_info, None)
-            .expect("Failed to create shader module")
-    }
+    let shader_module = unsafe { device.create_shader_module(&shader_module_create_info, None)? };
+    debug_utils.name_object(shader_module, name);
+    Ok(shader_module)
 }
 
 fn create_vertex_input_state_create_info(
@@ -162,7 +182,7 @@ fn create_vertex_input_assembly_state_info() -> PipelineInputAssemblyStateCreate
     }
 }
 
-fn create_viewport(swapchain_extent: &Extent2D) -> [Viewport; 1] {
+pub fn create_viewport(swapchain_extent: &Extent2D) -> [Viewport; 1] {
     [Viewport {
         x: 0.0,
         y: 0.0,
@@ -173,29 +193,29 @@ fn create_viewport(swapchain_extent: &Extent2D) -> [Viewport; 1] {
     }]
 }
 
-fn create_scissors(swapchain_extent: &Extent2D) -> [Rect2D; 1] {
+pub fn create_scissors(swapchain_extent: &Extent2D) -> [Rect2D; 1] {
     [Rect2D {
         offset: Offset2D { x: 0, y: 0 },
         extent: *swapchain_extent,
     }]
 }
 
-fn create_viewport_state_create_info(
-    viewports: &[Viewport],
-    scissors: &[Rect2D],
-) -> PipelineViewportStateCreateInfo {
+fn create_viewport_state_create_info() -> PipelineViewportStateCreateInfo {
     PipelineViewportStateCreateInfo {
         s_type: StructureType::PIPELINE_VIEWPORT_STATE_CREATE_INFO,
         p_next: std::ptr::null(),
         flags: PipelineViewportStateCreateFlags::empty(),
-        scissor_count: scissors.len() as u32,
-        p_scissors: scissors.as_ptr(),
-        viewport_count: viewports.len() as u32,
-        p_viewports: viewports.as_ptr(),
+        scissor_count: 1,
+        p_scissors: std::ptr::null(),
+        viewport_count: 1,
+        p_viewports: std::ptr::null(),
     }
 }
 
-fn create_rasterization_state_create_info() -> PipelineRasterizationStateCreateInfo {
+//Anything but FILL needs the fillModeNonSolid feature
+fn create_rasterization_state_create_info(
+    polygon_mode: PolygonMode,
+) -> PipelineRasterizationStateCreateInfo {
     PipelineRasterizationStateCreateInfo {
         s_type: StructureType::PIPELINE_RASTERIZATION_STATE_CREATE_INFO,
         p_next: std::ptr::null(),
@@ -204,7 +224,7 @@ fn create_rasterization_state_create_info() -> PipelineRasterizationStateCreateI
         cull_mode: CullModeFlags::BACK,
         front_face: FrontFace::CLOCKWISE,
         line_width: 1.0,
-        polygon_mode: PolygonMode::FILL,
+        polygon_mode,
         rasterizer_discard_enable: FALSE,
         depth_bias_clamp: 0.0,
         depth_bias_constant_factor: 0.0,
@@ -213,12 +233,14 @@ fn create_rasterization_state_create_info() -> PipelineRasterizationStateCreateI
     }
 }
 
-fn create_multisample_state_create_info() -> PipelineMultisampleStateCreateInfo {
+fn create_multisample_state_create_info(
+    samples: SampleCountFlags,
+) -> PipelineMultisampleStateCreateInfo {
     PipelineMultisampleStateCreateInfo {
         s_type: StructureType::PIPELINE_MULTISAMPLE_STATE_CREATE_INFO,
         flags: PipelineMultisampleStateCreateFlags::empty(),
         p_next: std::ptr::null(),
-        rasterization_samples: SampleCountFlags::TYPE_1,
+        rasterization_samples: samples,
         sample_shading_enable: FALSE,
         min_sample_shading: 0.0,
         p_sample_mask: std::ptr::null(),
@@ -246,8 +268,8 @@ fn create_depth_state_create_info(
         s_type: StructureType::PIPELINE_DEPTH_STENCIL_STATE_CREATE_INFO,
         p_next: std::ptr::null(),
         flags: PipelineDepthStencilStateCreateFlags::empty(),
-        depth_test_enable: FALSE,
-        depth_write_enable: FALSE,
+        depth_test_enable: TRUE,
+        depth_write_enable: TRUE,
         depth_compare_op: CompareOp::LESS_OR_EQUAL,
         depth_bounds_test_enable: FALSE,
         stencil_test_enable: FALSE,
@@ -0,0 +1,322 @@
+use super::allocator::{Allocation, AllocationKind, Allocator};
+use super::command_pool::{begin_single_time_commands, end_single_time_commands};
+use super::debug_utils::PotatoDebugUtils;
+use super::swapchain::create_image_view;
+use super::timeline::GpuTimeline;
+use crate::error::PotatoError;
+use ash::vk::{
+    AccessFlags, Buffer, BufferImageCopy, CommandBuffer, CommandPool, DependencyFlags, DeviceSize,
+    Extent2D, Extent3D, Format, Image, ImageAspectFlags, ImageCreateFlags, ImageCreateInfo,
+    ImageLayout, ImageMemoryBarrier, ImageSubresourceLayers, ImageSubresourceRange, ImageTiling,
+    ImageType, ImageUsageFlags, ImageView, MemoryPropertyFlags, Offset3D, PipelineStageFlags,
+    Queue, SampleCountFlags, SharingMode, StructureType, QUEUE_FAMILY_IGNORED,
+};
+use ash::Device;
+
+//Render target that never leaves the device, e.g. a depth buffer or a multisampled color buffer
+#[derive(Clone, Copy, Debug)]
+pub struct AttachmentImage {
+    pub image: Image,
+    pub allocation: Allocation,
+    pub image_view: ImageView,
+}
+
+impl AttachmentImage {
+    pub fn destroy(&self, device: &Device, allocator: &mut Allocator) {
+        unsafe {
+            device.destroy_image_view(self.image_view, None);
+        }
+        destroy_image(device, allocator, self.image, self.allocation);
+    }
+}
+
+//TODO Reduce number of arguments
+#[allow(clippy::too_many_arguments)]
+pub fn create_image(
+    device: &Device,
+    debug_utils: &PotatoDebugUtils,
+    allocator: &mut Allocator,
+    extent: Extent2D,
+    mip_levels: u32,
+    samples: SampleCountFlags,
+    format: Format,
+    usage: ImageUsageFlags,
+    required_memory_properties: MemoryPropertyFlags,
+    name: &str,
+) -> Result<(Image, Allocation), PotatoError> {
+    let image_create_info = ImageCreateInfo {
+        s_type: StructureType::IMAGE_CREATE_INFO,
+        p_next: std::ptr::null(),
+        flags: ImageCreateFlags::empty(),
+        image_type: ImageType::TYPE_2D,
+        format,
+        extent: Extent3D {
+            width: extent.width,
+            height: extent.height,
+            depth: 1,
+        },
+        mip_levels,
+        array_layers: 1,
+        samples,
+        tiling: ImageTiling::OPTIMAL,
+        usage,
+        sharing_mode: SharingMode::EXCLUSIVE,
+        queue_family_index_count: 0,
+        p_queue_family_indices: std::ptr::null(),
+        initial_layout: ImageLayout::UNDEFINED,
+    };
+
+    let image = unsafe { device.create_image(&image_create_info, None)? };
+    debug_utils.name_object(image, name);
+
+    let mem_requirements = unsafe { device.get_image_memory_requirements(image) };
+
+    let allocation = match allocator.allocate(
+        mem_requirements,
+        required_memory_properties,
+        AllocationKind::Optimal,
+    ) {
+        Ok(allocation) => allocation,
+        Err(e) => {
+            unsafe { device.destroy_image(image, None) };
+            return Err(e);
+        }
+    };
+
+    unsafe {
+        device.bind_image_memory(image, allocation.memory(), allocation.offset())?;
+    }
+
+    Ok((image, allocation))
+}
+
+pub fn destroy_image(
+    device: &Device,
+    allocator: &mut Allocator,
+    image: Image,
+    allocation: Allocation,
+) {
+    unsafe {
+        device.destroy_image(image, None);
+    }
+    allocator.free(allocation);
+}
+
+//TODO Reduce number of arguments
+#[allow(clippy::too_many_arguments)]
+pub fn create_attachment_image(
+    device: &Device,
+    debug_utils: &PotatoDebugUtils,
+    allocator: &mut Allocator,
+    extent: Extent2D,
+    samples: SampleCountFlags,
+    format: Format,
+    usage: ImageUsageFlags,
+    aspect_mask: ImageAspectFlags,
+    name: &str,
+) -> Result<AttachmentImage, PotatoError> {
+    let (image, allocation) = create_image(
+        device,
+        debug_utils,
+        allocator,
+        extent,
+        1,
+        samples,
+        format,
+        usage,
+        MemoryPropertyFlags::DEVICE_LOCAL,
+        name,
+    )?;
+
+    match create_image_view(
+        device,
+        debug_utils,
+        format,
+        image,
+        aspect_mask,
+        1,
+        &format!("{} view", name),
+    ) {
+        Ok(image_view) => Ok(AttachmentImage {
+            image,
+            allocation,
+            image_view,
+        }),
+        Err(e) => {
+            destroy_image(device, allocator, image, allocation);
+            Err(e)
+        }
+    }
+}
+
+//One attachment per framebuffer, the ones already created are destroyed if any fails
+//create is called with the position of the attachment
+pub fn create_attachment_images<F>(
+    device: &Device,
+    allocator: &mut Allocator,
+    count: usize,
+    mut create: F,
+) -> Result<Vec<AttachmentImage>, PotatoError>
+where
+    F: FnMut(&mut Allocator, usize) -> Result<AttachmentImage, PotatoError>,
+{
+    let mut attachment_images = vec![];
+    for i in 0..count {
+        match create(allocator, i) {
+            Ok(attachment_image) => attachment_images.push(attachment_image),
+            Err(e) => {
+                for attachment_image in &attachment_images {
+                    attachment_image.destroy(device, allocator);
+                }
+                return Err(e);
+            }
+        }
+    }
+    Ok(attachment_images)
+}
+
+//Expects the image to already be in TRANSFER_SRC_OPTIMAL, e.g. as the final layout of a render pass
+pub fn copy_image_to_buffer(
+    device: &Device,
+    submit_queue: Queue,
+    command_pool: CommandPool,
+    timeline: Option<&GpuTimeline>,
+    src_image: Image,
+    dst_buffer: Buffer,
+    extent: Extent2D,
+) -> Result<(), PotatoError> {
+    let command_buffer = begin_single_time_commands(device, command_pool)?;
+
+    let copy_regions = [BufferImageCopy {
+        buffer_offset: 0,
+        buffer_row_length: 0,
+        buffer_image_height: 0,
+        image_subresource: ImageSubresourceLayers {
+            aspect_mask: ImageAspectFlags::COLOR,
+            mip_level: 0,
+            base_array_layer: 0,
+            layer_count: 1,
+        },
+        image_offset: Offset3D { x: 0, y: 0, z: 0 },
+        image_extent: Extent3D {
+            width: extent.width,
+            height: extent.height,
+            depth: 1,
+        },
+    }];
+    unsafe {
+        device.cmd_copy_image_to_buffer(
+            command_buffer,
+            src_image,
+            ImageLayout::TRANSFER_SRC_OPTIMAL,
+            dst_buffer,
+            &copy_regions,
+        );
+    }
+
+    end_single_time_commands(device, command_pool, submit_queue, timeline, command_buffer)
+}
+
+//Records a barrier for the layout changes a texture upload goes through
+pub fn transition_image_layout(
+    device: &Device,
+    command_buffer: CommandBuffer,
+    image: Image,
+    mip_levels: u32,
+    old_layout: ImageLayout,
+    new_layout: ImageLayout,
+) {
+    let (src_access_mask, dst_access_mask, src_stage, dst_stage) = match (old_layout, new_layout) {
+        (ImageLayout::UNDEFINED, ImageLayout::TRANSFER_DST_OPTIMAL) => (
+            AccessFlags::empty(),
+            AccessFlags::TRANSFER_WRITE,
+            PipelineStageFlags::TOP_OF_PIPE,
+            PipelineStageFlags::TRANSFER,
+        ),
+        (ImageLayout::TRANSFER_DST_OPTIMAL, ImageLayout::SHADER_READ_ONLY_OPTIMAL) => (
+            AccessFlags::TRANSFER_WRITE,
+            AccessFlags::SHADER_READ,
+            PipelineStageFlags::TRANSFER,
+            PipelineStageFlags::FRAGMENT_SHADER,
+        ),
+        //Anything else waits on everything, correct but slow
+        _ => (
+            AccessFlags::MEMORY_WRITE,
+            AccessFlags::MEMORY_READ | AccessFlags::MEMORY_WRITE,
+            PipelineStageFlags::ALL_COMMANDS,
+            PipelineStageFlags::ALL_COMMANDS,
+        ),
+    };
+
+    let image_barriers = [ImageMemoryBarrier {
+        s_type: StructureType::IMAGE_MEMORY_BARRIER,
+        p_next: std::ptr::null(),
+        src_access_mask,
+        dst_access_mask,
+        old_layout,
+        new_layout,
+        src_queue_family_index: QUEUE_FAMILY_IGNORED,
+        dst_queue_family_index: QUEUE_FAMILY_IGNORED,
+        image,
+        subresource_range: ImageSubresourceRange {
+            aspect_mask: ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: mip_levels,
+            base_array_layer: 0,
+            layer_count: 1,
+        },
+    }];
+
+    unsafe {
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            src_stage,
+            dst_stage,
+            DependencyFlags::empty(),
+            &[],
+            &[],
+            &image_barriers,
+        );
+    }
+}
+
+//Expects the image to be in TRANSFER_DST_OPTIMAL, level i is read from the buffer offset of levels[i]
+pub fn copy_buffer_to_image(
+    device: &Device,
+    command_buffer: CommandBuffer,
+    src_buffer: Buffer,
+    dst_image: Image,
+    levels: &[(DeviceSize, Extent2D)],
+) {
+    let copy_regions: Vec<BufferImageCopy> = levels
+        .iter()
+        .enumerate()
+        .map(|(i, (buffer_offset, extent))| BufferImageCopy {
+            buffer_offset: *buffer_offset,
+            buffer_row_length: 0,
+            buffer_image_height: 0,
+            image_subresource: ImageSubresourceLayers {
+                aspect_mask: ImageAspectFlags::COLOR,
+                mip_level: i as u32,
+                base_array_layer: 0,
+                layer_count: 1,
+            },
+            image_offset: Offset3D { x: 0, y: 0, z: 0 },
+            image_extent: Extent3D {
+                width: extent.width,
+                height: extent.height,
+                depth: 1,
+            },
+        })
+        .collect();
+
+    unsafe {
+        device.cmd_copy_buffer_to_image(
+            command_buffer,
+            src_buffer,
+            dst_image,
+            ImageLayout::TRANSFER_DST_OPTIMAL,
+            &copy_regions,
+        );
+    }
+}
@@ -1,6 +1,11 @@
-use super::constants::VALIDATION;
-use super::utilities::{conver_str_vec_to_c_str_ptr_vec, vk_to_string};
+use super::capabilities::{
+    check_instance_support, EnabledInstanceCapabilities, InstanceRequirements,
+};
+use super::constants::VALIDATION_LAYER;
+use super::utilities::conver_str_vec_to_c_str_ptr_vec;
+use super::validation::{ValidationConfig, ValidationMode};
 use super::vulk_validation_layers::populate_debug_messenger_create_info;
+use crate::error::PotatoError;
 use ash::extensions::ext::DebugUtils;
 use ash::extensions::khr::Surface;
 #[cfg(feature = "wayland")]
@@ -8,18 +13,33 @@ use ash::extensions::khr::WaylandSurface;
 #[cfg(feature = "xlib")]
 use ash::extensions::khr::XlibSurface;
 use ash::vk::{
-    make_api_version, ApplicationInfo, DebugUtilsMessengerCreateInfoEXT, InstanceCreateFlags,
-    InstanceCreateInfo, StructureType,
+    make_api_version, ApplicationInfo, DebugUtilsMessengerCreateInfoEXT, ExtValidationFeaturesFn,
+    InstanceCreateFlags, InstanceCreateInfo, StructureType, ValidationFeaturesEXT,
 };
 use ash::Entry;
 use ash::Instance;
-use log::debug;
+use log::{debug, info, warn};
 use std::ffi::CString;
 use std::os::raw::c_void;
 
-pub fn create_instance(entry: &Entry) -> Instance {
-    if VALIDATION.is_enable && !check_validation_layer_support(entry) {
-        panic!("Validation layers requested but not supported");
+pub fn create_instance(
+    entry: &Entry,
+    enable_surface: bool,
+    validation: &ValidationConfig,
+) -> Result<(Instance, EnabledInstanceCapabilities), PotatoError> {
+    let enabled = check_instance_support(
+        entry,
+        &create_instance_requirements(enable_surface, validation),
+    )?;
+    if validation.is_enabled() {
+        if enabled.has_layer(VALIDATION_LAYER) {
+            info!("Validation enabled");
+        } else {
+            warn!(
+                "{} is not installed, running without validation",
+                VALIDATION_LAYER
+            );
+        }
     }
 
     let app_name = CString::new("Potato").unwrap();
@@ -34,82 +54,108 @@ pub fn create_instance(entry: &Entry) -> Instance {
         api_version: make_api_version(0, 1, 3, 260),
     };
 
-    let debug_utils_create_info = populate_debug_messenger_create_info();
+    //Instance creation and destruction are only covered through p_next
+    let validation_feature_enables = validation.features.enables();
+    let validation_features = ValidationFeaturesEXT {
+        s_type: StructureType::VALIDATION_FEATURES_EXT,
+        p_next: std::ptr::null(),
+        enabled_validation_feature_count: validation_feature_enables.len() as u32,
+        p_enabled_validation_features: validation_feature_enables.as_ptr(),
+        disabled_validation_feature_count: 0,
+        p_disabled_validation_features: std::ptr::null(),
+    };
+    let mut debug_utils_create_info = populate_debug_messenger_create_info(validation);
+
+    let mut p_next: *const c_void = std::ptr::null();
+    if !validation_feature_enables.is_empty() {
+        if enabled.has_extension(&ExtValidationFeaturesFn::name().to_string_lossy()) {
+            p_next = &validation_features as *const ValidationFeaturesEXT as *const c_void;
+        } else if enabled.has_layer(VALIDATION_LAYER) {
+            warn!(
+                "Validation layer does not support {:?}",
+                validation.features
+            );
+        }
+    }
+    if has_debug_messenger(validation, &enabled) {
+        debug_utils_create_info.p_next = p_next;
+        p_next =
+            &debug_utils_create_info as *const DebugUtilsMessengerCreateInfoEXT as *const c_void;
+    }
 
-    let extension_names = create_extention_names();
+    let (extension_cstrings, extension_names) =
+        conver_str_vec_to_c_str_ptr_vec(enabled.extensions.iter().map(|x| x.as_str()).collect());
+    debug!("{:?}", extension_cstrings);
 
     let (cstring_vec, enable_layer_names) =
-        conver_str_vec_to_c_str_ptr_vec(VALIDATION.required_validation_layers.to_vec());
+        conver_str_vec_to_c_str_ptr_vec(enabled.layers.iter().map(|x| x.as_str()).collect());
 
     debug!("{:?}", cstring_vec);
     let create_info = InstanceCreateInfo {
         s_type: StructureType::INSTANCE_CREATE_INFO,
-        p_next: if VALIDATION.is_enable {
-            &debug_utils_create_info as *const DebugUtilsMessengerCreateInfoEXT as *const c_void
-        } else {
-            std::ptr::null()
-        },
+        p_next,
         flags: InstanceCreateFlags::empty(),
         p_application_info: &app_info,
-        pp_enabled_layer_names: if VALIDATION.is_enable {
-            enable_layer_names.as_ptr()
-        } else {
-            std::ptr::null()
-        },
-        enabled_layer_count: get_enabled_layers_len(),
+        pp_enabled_layer_names: enable_layer_names.as_ptr(),
+        enabled_layer_count: enable_layer_names.len() as u32,
         pp_enabled_extension_names: extension_names.as_ptr(),
         enabled_extension_count: extension_names.len() as u32,
     };
 
     debug!("Creating Instance with {:?}", create_info);
-    let instance: Instance = unsafe {
-        entry
-            .create_instance(&create_info, None)
-            .expect("Failed to create instance")
-    };
+    let instance: Instance = unsafe { entry.create_instance(&create_info, None)? };
     debug!("Finished creating instance");
-    instance
+    Ok((instance, enabled))
 }
 
-fn check_validation_layer_support(entry: &Entry) -> bool {
-    let layer_properties = entry
-        .enumerate_instance_layer_properties()
-        .expect("Failed to enumerate the Instance Layers Properties!");
-
-    debug!("{:?}", layer_properties);
-    VALIDATION
-        .required_validation_layers
-        .iter()
-        .map(|layers| {
-            layer_properties
-                .iter()
-                .any(|v| vk_to_string(&v.layer_name) == *layers)
-        })
-        .any(|b| b)
+//Messages are only reported when validation is on and debug utils could be enabled
+pub fn has_debug_messenger(
+    validation: &ValidationConfig,
+    enabled: &EnabledInstanceCapabilities,
+) -> bool {
+    validation.is_enabled() && enabled.has_extension(&DebugUtils::name().to_string_lossy())
 }
 
-fn get_enabled_layers_len() -> u32 {
-    if VALIDATION.is_enable {
-        VALIDATION.required_validation_layers.iter().len() as u32
-    } else {
-        0 as u32
+//A window needs the surface extensions, everything for validation is optional unless it is required
+fn create_instance_requirements(
+    enable_surface: bool,
+    validation: &ValidationConfig,
+) -> InstanceRequirements {
+    let debug_utils = DebugUtils::name().to_string_lossy();
+    let mut requirements = InstanceRequirements::new().request_extension(&debug_utils);
+    match validation.mode {
+        ValidationMode::Off => {}
+        ValidationMode::Optional => requirements = requirements.request_layer(VALIDATION_LAYER),
+        ValidationMode::Required => {
+            requirements = requirements
+                .require_layer(VALIDATION_LAYER)
+                .require_extension(&debug_utils)
+        }
+    }
+    if validation.is_enabled() && !validation.features.is_empty() {
+        requirements =
+            requirements.request_extension(&ExtValidationFeaturesFn::name().to_string_lossy());
+    }
+    if enable_surface {
+        for extension in create_extention_names() {
+            requirements = requirements.require_extension(&extension);
+        }
     }
+    requirements
 }
 
 #[cfg(feature = "wayland")]
-fn create_extention_names() -> Vec<*const i8> {
+fn create_extention_names() -> Vec<String> {
     vec![
-        Surface::name().as_ptr(),
-        DebugUtils::name().as_ptr(),
-        WaylandSurface::name().as_ptr(),
+        Surface::name().to_string_lossy().into_owned(),
+        WaylandSurface::name().to_string_lossy().into_owned(),
     ]
 }
 
 #[cfg(feature = "xlib")]
-fn create_extention_names() -> Vec<*const i8> {
+fn create_extention_names() -> Vec<String> {
     vec![
-        Surface::name().as_ptr(),
-        XlibSurface::name().as_ptr(),
-        DebugUtils::name().as_ptr(),
+        Surface::name().to_string_lossy().into_owned(),
+        XlibSurface::name().to_string_lossy().into_owned(),
     ]
 }
@@ -0,0 +1,134 @@
+use super::allocator::{Allocation, Allocator};
+use super::buffer::destroy_buffer;
+use super::debug_utils::PotatoDebugUtils;
+use super::timeline::GpuTimeline;
+use super::vertex::{create_index_buffer, create_vertex_buffer, Vertex};
+use crate::error::PotatoError;
+use ash::vk::{Buffer, CommandPool, Queue};
+use ash::Device;
+
+#[derive(Debug, Clone, Default)]
+pub struct Mesh {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+}
+
+impl Mesh {
+    pub fn new(vertices: &[Vertex], indices: &[u32]) -> Mesh {
+        Mesh {
+            vertices: vertices.to_vec(),
+            indices: indices.to_vec(),
+        }
+    }
+
+    //The unit quad the tutorial has always drawn
+    pub fn quad() -> Mesh {
+        Mesh::new(
+            &[
+                Vertex {
+                    pos: [-0.5, -0.5, 0.0],
+                    color: [0.0, 0.2, 0.2],
+                    normal: [0.0, 0.0, 1.0],
+                    tex_coord: [0.0, 0.0],
+                },
+                Vertex {
+                    pos: [0.5, -0.5, 0.0],
+                    color: [1.0, 1.0, 1.0],
+                    normal: [0.0, 0.0, 1.0],
+                    tex_coord: [1.0, 0.0],
+                },
+                Vertex {
+                    pos: [0.5, 0.5, 0.0],
+                    color: [1.0, 1.0, 1.0],
+                    normal: [0.0, 0.0, 1.0],
+                    tex_coord: [1.0, 1.0],
+                },
+                Vertex {
+                    pos: [-0.5, 0.5, 0.0],
+                    color: [0.0, 0.2, 0.2],
+                    normal: [0.0, 0.0, 1.0],
+                    tex_coord: [0.0, 1.0],
+                },
+            ],
+            &[0, 1, 2, 2, 3, 0],
+        )
+    }
+
+    //queue_family_indices lists the families that use the buffers when uploading from a transfer queue
+    //TODO Reduce number of arguments
+    #[allow(clippy::too_many_arguments)]
+    pub fn upload(
+        &self,
+        device: &Device,
+        debug_utils: &PotatoDebugUtils,
+        allocator: &mut Allocator,
+        command_pool: CommandPool,
+        submit_queue: Queue,
+        timeline: Option<&GpuTimeline>,
+        queue_family_indices: &[u32],
+        name: &str,
+    ) -> Result<MeshBuffers, PotatoError> {
+        if self.vertices.is_empty() || self.indices.is_empty() {
+            return Err(PotatoError::EmptyMesh);
+        }
+
+        let (vertex_buffer, vertex_allocation) = create_vertex_buffer(
+            device,
+            debug_utils,
+            allocator,
+            command_pool,
+            submit_queue,
+            timeline,
+            &self.vertices,
+            queue_family_indices,
+            name,
+        )?;
+        let (index_buffer, index_allocation) = match create_index_buffer(
+            device,
+            debug_utils,
+            allocator,
+            command_pool,
+            submit_queue,
+            timeline,
+            &self.indices,
+            queue_family_indices,
+            name,
+        ) {
+            Ok(buffers) => buffers,
+            Err(e) => {
+                destroy_buffer(device, allocator, vertex_buffer, vertex_allocation);
+                return Err(e);
+            }
+        };
+
+        Ok(MeshBuffers {
+            vertex_buffer,
+            vertex_allocation,
+            index_buffer,
+            index_allocation,
+            index_count: self.indices.len() as u32,
+        })
+    }
+}
+
+//Device local copy of a mesh, everything a draw call needs
+#[derive(Clone, Copy, Debug)]
+pub struct MeshBuffers {
+    pub vertex_buffer: Buffer,
+    pub vertex_allocation: Allocation,
+    pub index_buffer: Buffer,
+    pub index_allocation: Allocation,
+    pub index_count: u32,
+}
+
+impl MeshBuffers {
+    pub fn destroy(&self, device: &Device, allocator: &mut Allocator) {
+        destroy_buffer(device, allocator, self.index_buffer, self.index_allocation);
+        destroy_buffer(
+            device,
+            allocator,
+            self.vertex_buffer,
+            self.vertex_allocation,
+        );
+    }
+}
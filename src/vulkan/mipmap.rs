@@ -0,0 +1,394 @@
+use crate::io::scene::TextureImage;
+use ash::vk::{
+    AccessFlags, CommandBuffer, DependencyFlags, Extent2D, Filter, Format, FormatFeatureFlags,
+    Image, ImageAspectFlags, ImageBlit, ImageLayout, ImageMemoryBarrier, ImageSubresourceLayers,
+    ImageSubresourceRange, Offset3D, PhysicalDevice, PipelineStageFlags, StructureType,
+    QUEUE_FAMILY_IGNORED,
+};
+use ash::{Device, Instance};
+
+//Kernel used by the CPU fallback, Kaiser keeps distant detail sharper than a plain box average
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MipFilter {
+    Box,
+    Kaiser,
+}
+
+//Number of levels down to 1x1
+pub fn mip_level_count(width: u32, height: u32) -> u32 {
+    32 - width.max(height).max(1).leading_zeros()
+}
+
+//Blits filter linearly, without SAMPLED_IMAGE_FILTER_LINEAR the chain has to come from the CPU
+pub fn supports_linear_blit(
+    instance: &Instance,
+    physical_device: PhysicalDevice,
+    format: Format,
+) -> bool {
+    let format_properties =
+        unsafe { instance.get_physical_device_format_properties(physical_device, format) };
+    format_properties.optimal_tiling_features.contains(
+        FormatFeatureFlags::BLIT_SRC
+            | FormatFeatureFlags::BLIT_DST
+            | FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR,
+    )
+}
+
+//Expects every level in TRANSFER_DST_OPTIMAL with level 0 filled, leaves every level in SHADER_READ_ONLY_OPTIMAL
+pub fn record_mipmap_blits(
+    device: &Device,
+    command_buffer: CommandBuffer,
+    image: Image,
+    extent: Extent2D,
+    mip_levels: u32,
+) {
+    let mut mip_width = extent.width as i32;
+    let mut mip_height = extent.height as i32;
+
+    for level in 1..mip_levels {
+        level_barrier(
+            device,
+            command_buffer,
+            image,
+            level - 1,
+            (
+                ImageLayout::TRANSFER_DST_OPTIMAL,
+                ImageLayout::TRANSFER_SRC_OPTIMAL,
+            ),
+            (AccessFlags::TRANSFER_WRITE, AccessFlags::TRANSFER_READ),
+            (PipelineStageFlags::TRANSFER, PipelineStageFlags::TRANSFER),
+        );
+
+        let next_width = (mip_width / 2).max(1);
+        let next_height = (mip_height / 2).max(1);
+        let blits = [ImageBlit {
+            src_subresource: color_layers(level - 1),
+            src_offsets: [
+                Offset3D { x: 0, y: 0, z: 0 },
+                Offset3D {
+                    x: mip_width,
+                    y: mip_height,
+                    z: 1,
+                },
+            ],
+            dst_subresource: color_layers(level),
+            dst_offsets: [
+                Offset3D { x: 0, y: 0, z: 0 },
+                Offset3D {
+                    x: next_width,
+                    y: next_height,
+                    z: 1,
+                },
+            ],
+        }];
+
+        unsafe {
+            device.cmd_blit_image(
+                command_buffer,
+                image,
+                ImageLayout::TRANSFER_SRC_OPTIMAL,
+                image,
+                ImageLayout::TRANSFER_DST_OPTIMAL,
+                &blits,
+                Filter::LINEAR,
+            );
+        }
+
+        level_barrier(
+            device,
+            command_buffer,
+            image,
+            level - 1,
+            (
+                ImageLayout::TRANSFER_SRC_OPTIMAL,
+                ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            ),
+            (AccessFlags::TRANSFER_READ, AccessFlags::SHADER_READ),
+            (
+                PipelineStageFlags::TRANSFER,
+                PipelineStageFlags::FRAGMENT_SHADER,
+            ),
+        );
+
+        mip_width = next_width;
+        mip_height = next_height;
+    }
+
+    //The last level is only ever written to
+    level_barrier(
+        device,
+        command_buffer,
+        image,
+        mip_levels - 1,
+        (
+            ImageLayout::TRANSFER_DST_OPTIMAL,
+            ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        ),
+        (AccessFlags::TRANSFER_WRITE, AccessFlags::SHADER_READ),
+        (
+            PipelineStageFlags::TRANSFER,
+            PipelineStageFlags::FRAGMENT_SHADER,
+        ),
+    );
+}
+
+fn color_layers(mip_level: u32) -> ImageSubresourceLayers {
+    ImageSubresourceLayers {
+        aspect_mask: ImageAspectFlags::COLOR,
+        mip_level,
+        base_array_layer: 0,
+        layer_count: 1,
+    }
+}
+
+fn level_barrier(
+    device: &Device,
+    command_buffer: CommandBuffer,
+    image: Image,
+    mip_level: u32,
+    (old_layout, new_layout): (ImageLayout, ImageLayout),
+    (src_access_mask, dst_access_mask): (AccessFlags, AccessFlags),
+    (src_stage, dst_stage): (PipelineStageFlags, PipelineStageFlags),
+) {
+    let image_barriers = [ImageMemoryBarrier {
+        s_type: StructureType::IMAGE_MEMORY_BARRIER,
+        p_next: std::ptr::null(),
+        src_access_mask,
+        dst_access_mask,
+        old_layout,
+        new_layout,
+        src_queue_family_index: QUEUE_FAMILY_IGNORED,
+        dst_queue_family_index: QUEUE_FAMILY_IGNORED,
+        image,
+        subresource_range: ImageSubresourceRange {
+            aspect_mask: ImageAspectFlags::COLOR,
+            base_mip_level: mip_level,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        },
+    }];
+
+    unsafe {
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            src_stage,
+            dst_stage,
+            DependencyFlags::empty(),
+            &[],
+            &[],
+            &image_barriers,
+        );
+    }
+}
+
+//Every level below the source down to 1x1, sRGB colors are filtered in linear space like a blit would
+pub fn generate_mip_chain(
+    source: &TextureImage,
+    filter: MipFilter,
+    srgb: bool,
+) -> Vec<TextureImage> {
+    let level_count = mip_level_count(source.width, source.height);
+    let mut levels: Vec<TextureImage> = vec![];
+
+    let mut width = source.width as usize;
+    let mut height = source.height as usize;
+    let mut texels: Vec<[f32; 4]> = source
+        .pixels
+        .chunks_exact(4)
+        .map(|x| decode_texel(x, srgb))
+        .collect();
+
+    for _ in 1..level_count {
+        let next_width = (width / 2).max(1);
+        let next_height = (height / 2).max(1);
+
+        let rows = resample_rows(&texels, width, height, next_width, filter);
+        texels = resample_columns(&rows, next_width, height, next_height, filter);
+        width = next_width;
+        height = next_height;
+
+        levels.push(TextureImage {
+            name: source.name.clone(),
+            width: width as u32,
+            height: height as u32,
+            pixels: texels.iter().flat_map(|x| encode_texel(x, srgb)).collect(),
+        });
+    }
+
+    levels
+}
+
+fn resample_rows(
+    texels: &[[f32; 4]],
+    width: usize,
+    height: usize,
+    next_width: usize,
+    filter: MipFilter,
+) -> Vec<[f32; 4]> {
+    let taps = filter_taps(width, next_width, filter);
+    let mut result = Vec::with_capacity(next_width * height);
+    for y in 0..height {
+        let row = &texels[y * width..(y + 1) * width];
+        result.extend(taps.iter().map(|x| weighted_sum(x, |i| row[i])));
+    }
+    result
+}
+
+fn resample_columns(
+    texels: &[[f32; 4]],
+    width: usize,
+    height: usize,
+    next_height: usize,
+    filter: MipFilter,
+) -> Vec<[f32; 4]> {
+    let taps = filter_taps(height, next_height, filter);
+    let mut result = Vec::with_capacity(width * next_height);
+    for column_taps in &taps {
+        for x in 0..width {
+            result.push(weighted_sum(column_taps, |i| texels[i * width + x]));
+        }
+    }
+    result
+}
+
+fn weighted_sum<F: Fn(usize) -> [f32; 4]>(taps: &[(usize, f32)], texel: F) -> [f32; 4] {
+    let mut sum = [0.0; 4];
+    for (index, weight) in taps {
+        let value = texel(*index);
+        for channel in 0..4 {
+            sum[channel] += value[channel] * weight;
+        }
+    }
+    sum
+}
+
+//Source index and normalized weight of every tap for each destination texel, edges are clamped
+fn filter_taps(size: usize, next_size: usize, filter: MipFilter) -> Vec<Vec<(usize, f32)>> {
+    let scale = size as f32 / next_size as f32;
+    //Support radius in destination texels
+    let radius = match filter {
+        MipFilter::Box => 0.5,
+        MipFilter::Kaiser => 2.0,
+    };
+
+    (0..next_size)
+        .map(|i| {
+            let center = (i as f32 + 0.5) * scale;
+            let first = (center - radius * scale).floor() as i64;
+            let last = (center + radius * scale).ceil() as i64;
+
+            let mut taps: Vec<(usize, f32)> = (first..=last)
+                .map(|j| {
+                    let distance = (j as f32 + 0.5 - center) / scale;
+                    let index = j.clamp(0, size as i64 - 1) as usize;
+                    (index, filter_weight(filter, distance))
+                })
+                .filter(|(_, weight)| *weight != 0.0)
+                .collect();
+
+            let total: f32 = taps.iter().map(|(_, weight)| weight).sum();
+            if total.abs() < f32::EPSILON {
+                taps = vec![(((center as usize).min(size - 1)), 1.0)];
+            } else {
+                taps.iter_mut().for_each(|(_, weight)| *weight /= total);
+            }
+            taps
+        })
+        .collect()
+}
+
+fn filter_weight(filter: MipFilter, distance: f32) -> f32 {
+    match filter {
+        MipFilter::Box => {
+            if distance.abs() <= 0.5 {
+                1.0
+            } else {
+                0.0
+            }
+        }
+        MipFilter::Kaiser => {
+            const RADIUS: f32 = 2.0;
+            const ALPHA: f32 = 4.0;
+            let t = distance / RADIUS;
+            if t.abs() >= 1.0 {
+                return 0.0;
+            }
+            sinc(distance) * bessel_i0(ALPHA * (1.0 - t * t).sqrt()) / bessel_i0(ALPHA)
+        }
+    }
+}
+
+fn sinc(x: f32) -> f32 {
+    if x.abs() < 1e-5 {
+        1.0
+    } else {
+        let x = std::f32::consts::PI * x;
+        x.sin() / x
+    }
+}
+
+//Series expansion of the zeroth order modified Bessel function, converges quickly for the alpha used
+fn bessel_i0(x: f32) -> f32 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let half_squared = x * x / 4.0;
+    for k in 1..20 {
+        term *= half_squared / (k * k) as f32;
+        sum += term;
+        if term < sum * 1e-7 {
+            break;
+        }
+    }
+    sum
+}
+
+fn decode_texel(texel: &[u8], srgb: bool) -> [f32; 4] {
+    let channel = |x: u8| {
+        let value = x as f32 / 255.0;
+        if srgb {
+            srgb_to_linear(value)
+        } else {
+            value
+        }
+    };
+    [
+        channel(texel[0]),
+        channel(texel[1]),
+        channel(texel[2]),
+        texel[3] as f32 / 255.0,
+    ]
+}
+
+fn encode_texel(texel: &[f32; 4], srgb: bool) -> [u8; 4] {
+    let quantize = |x: f32| (x.clamp(0.0, 1.0) * 255.0).round() as u8;
+    let channel = |x: f32| {
+        if srgb {
+            quantize(linear_to_srgb(x.clamp(0.0, 1.0)))
+        } else {
+            quantize(x)
+        }
+    };
+    [
+        channel(texel[0]),
+        channel(texel[1]),
+        channel(texel[2]),
+        quantize(texel[3]),
+    ]
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
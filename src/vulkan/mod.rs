@@ -1,6 +1,8 @@
 pub mod vulk_init;
+pub mod vulk_compute;
+pub mod vulk_offscreen;
 mod vulk_validation_layers;
-mod queue_family;
+pub mod queue_family;
 mod physical_device;
 mod utilities;
 mod queue;
@@ -9,11 +11,30 @@ mod surface;
 mod swapchain;
 mod constants;
 mod graphics_pipeline;
+mod compute_pipeline;
 mod render_pass;
 mod framebuffers;
 mod command_pool;
 mod instance;
-mod sync_objects;
-mod vertex;
+pub mod vertex;
+pub mod mesh;
+pub mod allocator;
 mod buffer;
+mod image;
+mod depth;
+pub mod msaa;
+pub mod present_mode;
+pub mod config;
+pub mod device_selection;
+pub mod device_features;
+pub mod capabilities;
+pub mod validation;
+pub mod debug_utils;
+pub mod frame;
+pub mod frame_pacing;
+pub mod recording_threads;
+pub mod timeline;
+pub mod mipmap;
+pub mod texture;
+#[allow(non_snake_case)]
 mod UniformBufferObject;
@@ -0,0 +1,79 @@
+use super::allocator::Allocator;
+use super::debug_utils::PotatoDebugUtils;
+use super::image::{create_attachment_image, create_attachment_images, AttachmentImage};
+use crate::error::PotatoError;
+use ash::vk::{
+    Extent2D, Format, ImageAspectFlags, ImageUsageFlags, PhysicalDevice, SampleCountFlags,
+};
+use ash::{Device, Instance};
+
+//Every count a framebuffer can have, lowest first
+pub const SAMPLE_COUNTS: [SampleCountFlags; 7] = [
+    SampleCountFlags::TYPE_1,
+    SampleCountFlags::TYPE_2,
+    SampleCountFlags::TYPE_4,
+    SampleCountFlags::TYPE_8,
+    SampleCountFlags::TYPE_16,
+    SampleCountFlags::TYPE_32,
+    SampleCountFlags::TYPE_64,
+];
+
+//Highest count both the color and the depth attachments of a framebuffer support
+pub fn max_usable_sample_count(
+    instance: &Instance,
+    physical_device: PhysicalDevice,
+) -> SampleCountFlags {
+    let limits = unsafe { instance.get_physical_device_properties(physical_device) }.limits;
+    let supported = limits.framebuffer_color_sample_counts & limits.framebuffer_depth_sample_counts;
+    clamp_sample_count(SampleCountFlags::TYPE_64, supported)
+}
+
+//Highest supported count that does not exceed the requested one, single sampling is always available
+pub fn clamp_sample_count(
+    requested: SampleCountFlags,
+    supported: SampleCountFlags,
+) -> SampleCountFlags {
+    SAMPLE_COUNTS
+        .iter()
+        .rev()
+        .copied()
+        .find(|x| x.as_raw() <= requested.as_raw() && supported.contains(*x))
+        .unwrap_or(SampleCountFlags::TYPE_1)
+}
+
+//Next supported count after current, wrapping back to single sampling past the maximum
+pub fn next_sample_count(
+    current: SampleCountFlags,
+    max_samples: SampleCountFlags,
+) -> SampleCountFlags {
+    SAMPLE_COUNTS
+        .iter()
+        .copied()
+        .find(|x| x.as_raw() > current.as_raw() && x.as_raw() <= max_samples.as_raw())
+        .unwrap_or(SampleCountFlags::TYPE_1)
+}
+
+//Multisampled color is resolved into the swapchain image at the end of the subpass and never stored
+pub fn create_color_images(
+    device: &Device,
+    debug_utils: &PotatoDebugUtils,
+    allocator: &mut Allocator,
+    color_format: Format,
+    extent: Extent2D,
+    samples: SampleCountFlags,
+    count: usize,
+) -> Result<Vec<AttachmentImage>, PotatoError> {
+    create_attachment_images(device, allocator, count, |allocator, i| {
+        create_attachment_image(
+            device,
+            debug_utils,
+            allocator,
+            extent,
+            samples,
+            color_format,
+            ImageUsageFlags::COLOR_ATTACHMENT | ImageUsageFlags::TRANSIENT_ATTACHMENT,
+            ImageAspectFlags::COLOR,
+            &format!("Multisampled color image {}", i),
+        )
+    })
+}
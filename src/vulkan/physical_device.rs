@@ -1,104 +1,220 @@
+use super::capabilities::select_available;
 use super::constants::DEVICE_EXTENSTIONS;
-use super::queue_family::find_graphical_queue_family;
+use super::device_features::{
+    available_device_extensions, negotiate_device_features, DeviceRequirements,
+    EnabledDeviceFeatures,
+};
+use super::device_selection::{pick_device, DeviceCandidate, DeviceSelector};
+use super::queue_family::{
+    find_compute_queue_family, find_graphical_queue_family, find_offscreen_queue_family,
+};
 use super::surface::PotatoSurface;
 use super::swapchain::determine_swapchain_support;
 use super::utilities::vk_to_string;
+use crate::error::PotatoError;
 use ash::vk::{
-    api_version_major, api_version_minor, api_version_patch, PhysicalDevice,
-    PhysicalDeviceProperties, PhysicalDeviceType, QueueFlags,
+    api_version_major, api_version_minor, api_version_patch, MemoryHeapFlags, PhysicalDevice,
+    PhysicalDeviceType, QueueFlags,
 };
 use ash::Instance;
 use log::{debug, info};
-use std::collections::HashSet;
 
-pub fn select_physical_device(instance: &Instance, surface: &PotatoSurface) -> PhysicalDevice {
-    let physical_devices = unsafe {
-        instance
-            .enumerate_physical_devices()
-            .expect("Failed to enumerate physical devices")
-    };
+pub fn select_physical_device(
+    instance: &Instance,
+    surface: &PotatoSurface,
+    selector: Option<&DeviceSelector>,
+    requirements: &DeviceRequirements,
+) -> Result<PhysicalDevice, PotatoError> {
+    select_scored_physical_device(instance, selector, requirements, |x| {
+        check_device_compatability(instance, x, surface)
+    })
+}
+
+pub fn select_compute_physical_device(
+    instance: &Instance,
+    selector: Option<&DeviceSelector>,
+    requirements: &DeviceRequirements,
+) -> Result<PhysicalDevice, PotatoError> {
+    select_scored_physical_device(instance, selector, requirements, |x| {
+        Ok(find_compute_queue_family(instance, x)
+            .compute_family
+            .is_some())
+    })
+}
+
+pub fn select_offscreen_physical_device(
+    instance: &Instance,
+    selector: Option<&DeviceSelector>,
+    requirements: &DeviceRequirements,
+) -> Result<PhysicalDevice, PotatoError> {
+    select_scored_physical_device(instance, selector, requirements, |x| {
+        Ok(find_offscreen_queue_family(instance, x)
+            .graphics_family
+            .is_some())
+    })
+}
+
+//Scores every device is_suitable accepts and that has the required features and logs the candidates before picking one
+fn select_scored_physical_device<F>(
+    instance: &Instance,
+    selector: Option<&DeviceSelector>,
+    requirements: &DeviceRequirements,
+    is_suitable: F,
+) -> Result<PhysicalDevice, PotatoError>
+where
+    F: Fn(PhysicalDevice) -> Result<bool, PotatoError>,
+{
+    let physical_devices = unsafe { instance.enumerate_physical_devices()? };
     info!("{} GPU device(s) found", physical_devices.len());
 
-    let selected_device = physical_devices
-        .iter()
-        .find(|x| check_device_compatability(instance, **x, surface));
+    let mut candidates = vec![];
+    for (index, physical_device) in physical_devices.iter().enumerate() {
+        let enabled_features =
+            match negotiate_device_features(instance, *physical_device, requirements) {
+                Ok(enabled_features) => Some(enabled_features),
+                Err(e @ PotatoError::MissingCapabilities(_)) => {
+                    debug!("Candidate {}: {}", index, e);
+                    None
+                }
+                Err(e) => return Err(e),
+            };
+        let is_suitable = enabled_features.is_some() && is_suitable(*physical_device)?;
+        candidates.push(create_candidate(
+            instance,
+            *physical_device,
+            index,
+            requirements,
+            enabled_features.as_ref(),
+            is_suitable,
+        )?);
+    }
+
+    for candidate in &candidates {
+        match candidate.score() {
+            Some(score) => info!(
+                "Candidate {}: {} [{:04x}:{:04x}] {}, score {}",
+                candidate.index,
+                candidate.name,
+                candidate.vendor_id,
+                candidate.device_id,
+                find_device_type(candidate.device_type),
+                score
+            ),
+            None => info!(
+                "Candidate {}: {} [{:04x}:{:04x}] {}, unsuitable",
+                candidate.index,
+                candidate.name,
+                candidate.vendor_id,
+                candidate.device_id,
+                find_device_type(candidate.device_type)
+            ),
+        }
+    }
 
-    debug!("{:?}", selected_device);
-    match selected_device {
-        Some(p_physical_device) => *p_physical_device,
-        None => panic!("Failed to find compatable device"),
+    if let Some(selector) = selector {
+        info!("Device override {}", selector);
     }
+    let selected = pick_device(&candidates, selector)?;
+    info!("Selected candidate {}", candidates[selected].index);
+
+    Ok(physical_devices[selected])
+}
+
+fn create_candidate(
+    instance: &Instance,
+    physical_device: PhysicalDevice,
+    index: usize,
+    requirements: &DeviceRequirements,
+    enabled_features: Option<&EnabledDeviceFeatures>,
+    is_suitable: bool,
+) -> Result<DeviceCandidate, PotatoError> {
+    let properties = unsafe { instance.get_physical_device_properties(physical_device) };
+    let memory_properties =
+        unsafe { instance.get_physical_device_memory_properties(physical_device) };
+
+    //Largest device local heap, integrated GPUs report part of system memory here
+    let device_local_memory = memory_properties.memory_heaps
+        [..memory_properties.memory_heap_count as usize]
+        .iter()
+        .filter(|x| x.flags.contains(MemoryHeapFlags::DEVICE_LOCAL))
+        .map(|x| x.size)
+        .max()
+        .unwrap_or(0);
+
+    //Optional items the negotiation kept, everything else it enabled was required
+    let (optional_features, optional_extensions) = match enabled_features {
+        Some(enabled_features) => (
+            enabled_features.features.len() - requirements.required_features.len(),
+            enabled_features.extensions.len() - requirements.required_extensions.len(),
+        ),
+        None => (0, 0),
+    };
+
+    Ok(DeviceCandidate {
+        index,
+        name: vk_to_string(&properties.device_name),
+        vendor_id: properties.vendor_id,
+        device_id: properties.device_id,
+        device_type: properties.device_type,
+        api_version: properties.api_version,
+        device_local_memory,
+        optional_features,
+        optional_extensions,
+        is_suitable,
+    })
 }
 
 fn check_device_compatability(
     instance: &Instance,
     physical_device: PhysicalDevice,
     surface: &PotatoSurface,
-) -> bool {
-    let queue_family_support = is_queue_family_supported(instance, physical_device, surface);
-    let device_extension_support = is_device_extension_supported(instance, physical_device);
+) -> Result<bool, PotatoError> {
+    let queue_family_support = is_queue_family_supported(instance, physical_device, surface)?;
+    let device_extension_support = is_device_extension_supported(instance, physical_device)?;
     let swapchain_support =
-        is_swapchain_supported(device_extension_support, physical_device, surface);
+        is_swapchain_supported(device_extension_support, physical_device, surface)?;
 
     debug!(
         "{}, {}, {}",
         queue_family_support, device_extension_support, swapchain_support
     );
-    queue_family_support && device_extension_support && swapchain_support
+    Ok(queue_family_support && device_extension_support && swapchain_support)
 }
 
 fn is_queue_family_supported(
     instance: &Instance,
     physical_device: PhysicalDevice,
     surface: &PotatoSurface,
-) -> bool {
-    let queue_family = find_graphical_queue_family(instance, physical_device, surface);
-    queue_family.is_complete()
+) -> Result<bool, PotatoError> {
+    Ok(find_graphical_queue_family(instance, physical_device, surface)?.is_complete())
 }
 
-fn is_device_extension_supported(instance: &Instance, physical_device: PhysicalDevice) -> bool {
-    let available_extensions = unsafe {
-        instance
-            .enumerate_device_extension_properties(physical_device)
-            .expect("Failed to get device extension properties")
-    };
-
-    debug!("Available Extensions");
-    available_extensions.iter().for_each(|x| {
-        debug!(
-            "Name: {}, Version: {}",
-            vk_to_string(&x.extension_name),
-            x.spec_version
-        )
-    });
-
-    let required_extensions: HashSet<String> = DEVICE_EXTENSTIONS
-        .names
-        .iter()
-        .map(|x| x.to_string())
-        .collect();
+fn is_device_extension_supported(
+    instance: &Instance,
+    physical_device: PhysicalDevice,
+) -> Result<bool, PotatoError> {
+    let available_extensions = available_device_extensions(instance, physical_device)?;
+    debug!("Available Extensions {:?}", available_extensions);
 
-    required_extensions
-        .iter()
-        .map(|x| {
-            available_extensions
-                .iter()
-                .map(|y| vk_to_string(&y.extension_name) == *x)
-                .any(|z| z)
-        })
-        .any(|a| a)
+    //Every required extension has to be there, not just one of them
+    let (_, missing_extensions) =
+        select_available(&DEVICE_EXTENSTIONS.names, &[], &available_extensions);
+    if !missing_extensions.is_empty() {
+        debug!("Missing device extensions {:?}", missing_extensions);
+    }
+    Ok(missing_extensions.is_empty())
 }
 
 fn is_swapchain_supported(
     device_extension_support: bool,
     physical_device: PhysicalDevice,
     surface: &PotatoSurface,
-) -> bool {
+) -> Result<bool, PotatoError> {
     if device_extension_support {
-        let available_support = determine_swapchain_support(physical_device, surface);
-        !available_support.formats.is_empty() && !available_support.present_modes.is_empty()
+        let available_support = determine_swapchain_support(physical_device, surface)?;
+        Ok(!available_support.formats.is_empty() && !available_support.present_modes.is_empty())
     } else {
-        false
+        Ok(false)
     }
 }
 
@@ -107,12 +223,12 @@ pub fn describe_device(instance: &Instance, physical_device: PhysicalDevice) {
     let device_queue_familes =
         unsafe { instance.get_physical_device_queue_family_properties(physical_device) };
 
-    let device_type = find_device_type(&device_properties);
+    let device_type = find_device_type(device_properties.device_type);
     let device_name = vk_to_string(&device_properties.device_name);
 
     info!(
-        "Device Name: {}, id: {}, type: {}",
-        device_name, device_properties.device_id, device_type
+        "Device Name: {}, id: {:04x}:{:04x}, type: {}",
+        device_name, device_properties.vendor_id, device_properties.device_id, device_type
     );
 
     info!(
@@ -135,12 +251,14 @@ pub fn describe_device(instance: &Instance, physical_device: PhysicalDevice) {
     });
 }
 
-fn find_device_type(properties: &PhysicalDeviceProperties) -> &str {
-    match properties.device_type {
+fn find_device_type(device_type: PhysicalDeviceType) -> &'static str {
+    match device_type {
         PhysicalDeviceType::CPU => "CPU",
         PhysicalDeviceType::INTEGRATED_GPU => "Integrated GPU",
         PhysicalDeviceType::DISCRETE_GPU => "Discrete GPU",
         PhysicalDeviceType::VIRTUAL_GPU => "Virtual GPU",
-        _ => panic!(),
+        PhysicalDeviceType::OTHER => "Other",
+        //Types newer than the headers we were built against
+        _ => "Unknown",
     }
 }
@@ -0,0 +1,109 @@
+use crate::error::PotatoError;
+use ash::vk::PresentModeKHR;
+use log::{debug, warn};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+//How frames are handed to the display, each policy falls back to FIFO which every device supports
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PresentPolicy {
+    //Waits for vertical blank, no tearing and the lowest power draw
+    #[default]
+    VsyncOn,
+    //Presents immediately and may tear
+    VsyncOff,
+    //Replaces the queued frame instead of waiting, no tearing with low latency
+    LowLatency,
+    //Waits for vertical blank unless the frame is already late, then tears instead of stuttering
+    Relaxed,
+}
+
+impl PresentPolicy {
+    pub const ALL: [PresentPolicy; 4] = [
+        PresentPolicy::VsyncOn,
+        PresentPolicy::VsyncOff,
+        PresentPolicy::LowLatency,
+        PresentPolicy::Relaxed,
+    ];
+
+    //Present modes in order of preference
+    pub fn fallback_chain(&self) -> &'static [PresentModeKHR] {
+        match self {
+            PresentPolicy::VsyncOn => &[PresentModeKHR::FIFO],
+            PresentPolicy::VsyncOff => &[
+                PresentModeKHR::IMMEDIATE,
+                PresentModeKHR::MAILBOX,
+                PresentModeKHR::FIFO,
+            ],
+            PresentPolicy::LowLatency => &[
+                PresentModeKHR::MAILBOX,
+                PresentModeKHR::IMMEDIATE,
+                PresentModeKHR::FIFO,
+            ],
+            PresentPolicy::Relaxed => &[PresentModeKHR::FIFO_RELAXED, PresentModeKHR::FIFO],
+        }
+    }
+
+    //Cycles through every policy, used for switching at runtime
+    pub fn next(&self) -> PresentPolicy {
+        let index = PresentPolicy::ALL
+            .iter()
+            .position(|x| x == self)
+            .unwrap_or(0);
+        PresentPolicy::ALL[(index + 1) % PresentPolicy::ALL.len()]
+    }
+}
+
+impl Display for PresentPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            PresentPolicy::VsyncOn => "vsync",
+            PresentPolicy::VsyncOff => "immediate",
+            PresentPolicy::LowLatency => "mailbox",
+            PresentPolicy::Relaxed => "relaxed",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for PresentPolicy {
+    type Err = PotatoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "vsync" | "vsync-on" | "fifo" => Ok(PresentPolicy::VsyncOn),
+            "immediate" | "vsync-off" => Ok(PresentPolicy::VsyncOff),
+            "mailbox" | "low-latency" => Ok(PresentPolicy::LowLatency),
+            "relaxed" | "fifo-relaxed" => Ok(PresentPolicy::Relaxed),
+            _ => Err(PotatoError::InvalidSetting {
+                name: "present mode".to_string(),
+                value: s.to_string(),
+            }),
+        }
+    }
+}
+
+//First mode of the policy's chain the surface supports, FIFO is required by the spec so it is the last resort
+pub fn choose_present_mode(
+    available_present_modes: &[PresentModeKHR],
+    policy: PresentPolicy,
+) -> PresentModeKHR {
+    let chain = policy.fallback_chain();
+    let present_mode = chain
+        .iter()
+        .find(|x| available_present_modes.contains(x))
+        .copied()
+        .unwrap_or(PresentModeKHR::FIFO);
+
+    if present_mode != chain[0] {
+        warn!(
+            "Present mode {:?} unsupported for policy {}, falling back to {:?}",
+            chain[0], policy, present_mode
+        );
+    }
+    debug!(
+        "Using present mode {:?} for policy {}, available {:?}",
+        present_mode, policy, available_present_modes
+    );
+    present_mode
+}
@@ -1,16 +1,94 @@
 use super::surface::PotatoSurface;
-use ash::vk::{PhysicalDevice, QueueFlags};
+use crate::error::PotatoError;
+use ash::vk::{PhysicalDevice, QueueFamilyProperties, QueueFlags};
 use ash::Instance;
 use log::debug;
 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub struct QueueFamily {
     pub graphics_family: Option<usize>,
     pub present_family: Option<usize>,
+    pub compute_family: Option<usize>,
+    pub transfer_family: Option<usize>,
 }
 
 impl QueueFamily {
+    //Everything a windowed renderer needs, compute and transfer fall back to the graphics family
     pub fn is_complete(&self) -> bool {
-        self.graphics_family.is_some()
+        self.graphics_family.is_some() && self.present_family.is_some()
+    }
+
+    //Every distinct family in use, one queue is created for each
+    pub fn unique_families(&self) -> Vec<u32> {
+        let mut families: Vec<u32> = [
+            self.graphics_family,
+            self.present_family,
+            self.compute_family,
+            self.transfer_family,
+        ]
+        .iter()
+        .flatten()
+        .map(|x| *x as u32)
+        .collect();
+        families.sort_unstable();
+        families.dedup();
+        families
+    }
+}
+
+//Picks a family for each role from the properties, present_support holds whether family i can present
+pub fn pick_queue_families(
+    queue_families: &[QueueFamilyProperties],
+    present_support: &[bool],
+) -> QueueFamily {
+    let has = |family: &QueueFamilyProperties, flags: QueueFlags| {
+        family.queue_count > 0 && family.queue_flags.contains(flags)
+    };
+    let lacks =
+        |family: &QueueFamilyProperties, flags: QueueFlags| !family.queue_flags.intersects(flags);
+    let can_present = |i: usize| present_support.get(i).copied().unwrap_or(false);
+
+    let graphics_family = queue_families
+        .iter()
+        .position(|x| has(x, QueueFlags::GRAPHICS));
+
+    //Presenting from the graphics family saves a hand over between queues
+    let present_family = graphics_family.filter(|x| can_present(*x)).or_else(|| {
+        queue_families
+            .iter()
+            .enumerate()
+            .position(|(i, x)| x.queue_count > 0 && can_present(i))
+    });
+
+    //Async compute runs best on a family without graphics
+    let compute_family = queue_families
+        .iter()
+        .position(|x| has(x, QueueFlags::COMPUTE) && lacks(x, QueueFlags::GRAPHICS))
+        .or_else(|| graphics_family.filter(|x| has(&queue_families[*x], QueueFlags::COMPUTE)))
+        .or_else(|| {
+            queue_families
+                .iter()
+                .position(|x| has(x, QueueFlags::COMPUTE))
+        });
+
+    //A transfer only family is usually a dedicated DMA engine, graphics and compute imply transfer
+    let transfer_family = queue_families
+        .iter()
+        .position(|x| {
+            has(x, QueueFlags::TRANSFER) && lacks(x, QueueFlags::GRAPHICS | QueueFlags::COMPUTE)
+        })
+        .or_else(|| {
+            queue_families
+                .iter()
+                .position(|x| has(x, QueueFlags::TRANSFER) && lacks(x, QueueFlags::GRAPHICS))
+        })
+        .or(graphics_family);
+
+    QueueFamily {
+        graphics_family,
+        present_family,
+        compute_family,
+        transfer_family,
     }
 }
 
@@ -18,32 +96,57 @@ pub fn find_graphical_queue_family(
     instance: &Instance,
     physical_device: PhysicalDevice,
     surface: &PotatoSurface,
+) -> Result<QueueFamily, PotatoError> {
+    let queue_families =
+        unsafe { instance.get_physical_device_queue_family_properties(physical_device) };
+
+    let mut present_support = vec![];
+    for i in 0..queue_families.len() {
+        present_support.push(unsafe {
+            surface.surface_loader.get_physical_device_surface_support(
+                physical_device,
+                i as u32,
+                surface.surface,
+            )?
+        });
+    }
+
+    let queue_family = pick_queue_families(&queue_families, &present_support);
+    debug!("Queue families {:?}", queue_family);
+    Ok(queue_family)
+}
+
+//Offscreen rendering never presents so any graphics family will do
+pub fn find_offscreen_queue_family(
+    instance: &Instance,
+    physical_device: PhysicalDevice,
 ) -> QueueFamily {
     let queue_families =
         unsafe { instance.get_physical_device_queue_family_properties(physical_device) };
-    let graphics_family = queue_families
-        .iter()
-        .position(|x| x.queue_count > 0 && x.queue_flags.contains(QueueFlags::GRAPHICS));
+    let queue_family = QueueFamily {
+        graphics_family: pick_queue_families(&queue_families, &[]).graphics_family,
+        ..Default::default()
+    };
 
-    debug!(
-        "Position of graphical queue family {}",
-        graphics_family.unwrap()
-    );
+    debug!("Offscreen queue families {:?}", queue_family);
+    queue_family
+}
 
-    let is_present_supported = unsafe {
-        surface.surface_loader.get_physical_device_surface_support(
-            physical_device,
-            graphics_family.unwrap() as u32,
-            surface.surface,
-        )
+//Prefers a compute only family so dispatches do not compete with graphics work
+pub fn find_compute_queue_family(
+    instance: &Instance,
+    physical_device: PhysicalDevice,
+) -> QueueFamily {
+    let queue_families =
+        unsafe { instance.get_physical_device_queue_family_properties(physical_device) };
+    let queue_family = QueueFamily {
+        compute_family: pick_queue_families(&queue_families, &[]).compute_family,
+        ..Default::default()
     };
 
-    if queue_families[graphics_family.unwrap()].queue_count > 0 && is_present_supported.unwrap() {
-        QueueFamily {
-            graphics_family,
-            present_family: graphics_family,
-        }
-    } else {
-        panic!("Could not find a graphical queue that also supports surface present");
-    }
+    debug!(
+        "Position of compute queue family {:?}",
+        queue_family.compute_family
+    );
+    queue_family
 }
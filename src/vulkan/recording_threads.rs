@@ -0,0 +1,95 @@
+use crate::error::PotatoError;
+use std::any::Any;
+use std::panic::AssertUnwindSafe;
+use std::sync::mpsc::{channel, Sender};
+use std::thread::JoinHandle;
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+//Threads started once and reused by every frame, starting threads per frame would cost what parallel recording saves
+//Worker i always runs job i, so it can own the command pools of thread i
+pub struct RecordingThreads {
+    senders: Vec<Sender<Job>>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl RecordingThreads {
+    //No threads are started for a thread_count of 0 or 1, frames are recorded inline then
+    pub fn new(thread_count: usize) -> Result<RecordingThreads, PotatoError> {
+        let mut recording_threads = RecordingThreads {
+            senders: vec![],
+            threads: vec![],
+        };
+        if thread_count <= 1 {
+            return Ok(recording_threads);
+        }
+
+        for i in 0..thread_count {
+            let (sender, receiver) = channel::<Job>();
+            let thread = std::thread::Builder::new()
+                .name(format!("potato-recording-{}", i))
+                .spawn(move || {
+                    for job in receiver {
+                        job();
+                    }
+                })
+                .map_err(PotatoError::Thread)?;
+            recording_threads.senders.push(sender);
+            recording_threads.threads.push(thread);
+        }
+        Ok(recording_threads)
+    }
+
+    pub fn thread_count(&self) -> usize {
+        self.threads.len()
+    }
+
+    //Runs jobs[i] on thread i and returns their results once all of them have finished
+    //Jobs may borrow from the caller since nothing returns before they are done, a panic is resumed afterwards
+    pub fn run<'scope, T>(&self, jobs: Vec<Box<dyn FnOnce() -> T + Send + 'scope>>) -> Vec<T>
+    where
+        T: Send + 'static,
+    {
+        assert!(
+            jobs.len() <= self.thread_count(),
+            "{} jobs for {} recording threads",
+            jobs.len(),
+            self.thread_count()
+        );
+
+        let mut receivers = vec![];
+        for (job, sender) in jobs.into_iter().zip(&self.senders) {
+            let (result_sender, result_receiver) = channel();
+            let job: Box<dyn FnOnce() + Send + 'scope> = Box::new(move || {
+                let result = std::panic::catch_unwind(AssertUnwindSafe(job));
+                let _ = result_sender.send(result);
+            });
+            //Every result is waited for below before anything can return or panic,
+            //and the result is only sent once the job has run, so the job never outlives 'scope
+            let job: Job = unsafe { std::mem::transmute(job) };
+            //A job that fails to send is handed back and dropped right here
+            receivers.push(sender.send(job).ok().map(|_| result_receiver));
+        }
+
+        let results: Vec<Option<Result<T, Box<dyn Any + Send>>>> = receivers
+            .into_iter()
+            .map(|x| x.and_then(|x| x.recv().ok()))
+            .collect();
+        results
+            .into_iter()
+            .map(|x| {
+                x.expect("Recording thread stopped while the pool is alive")
+                    .unwrap_or_else(|e| std::panic::resume_unwind(e))
+            })
+            .collect()
+    }
+}
+
+impl Drop for RecordingThreads {
+    fn drop(&mut self) {
+        self.senders.clear();
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
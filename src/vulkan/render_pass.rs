@@ -1,3 +1,5 @@
+use super::debug_utils::PotatoDebugUtils;
+use crate::error::PotatoError;
 use ash::vk::{
     AccessFlags, AttachmentDescription, AttachmentDescriptionFlags, AttachmentLoadOp,
     AttachmentReference, AttachmentStoreOp, DependencyFlags, Format, ImageLayout,
@@ -7,17 +9,48 @@ use ash::vk::{
 };
 use ash::Device;
 
-pub fn create_render_pass(device: &Device, surface_format: Format) -> RenderPass {
+pub fn create_render_pass(
+    device: &Device,
+    debug_utils: &PotatoDebugUtils,
+    surface_format: Format,
+    depth_format: Format,
+    samples: SampleCountFlags,
+    final_layout: ImageLayout,
+) -> Result<RenderPass, PotatoError> {
+    //Multisampled color is resolved into a single sampled attachment that takes its place as the output
+    let multisampled = samples != SampleCountFlags::TYPE_1;
+
     let color_attachment = AttachmentDescription {
         flags: AttachmentDescriptionFlags::empty(),
         format: surface_format,
-        samples: SampleCountFlags::TYPE_1,
+        samples,
         load_op: AttachmentLoadOp::CLEAR,
-        store_op: AttachmentStoreOp::STORE,
+        store_op: if multisampled {
+            AttachmentStoreOp::DONT_CARE
+        } else {
+            AttachmentStoreOp::STORE
+        },
+        stencil_load_op: AttachmentLoadOp::DONT_CARE,
+        stencil_store_op: AttachmentStoreOp::DONT_CARE,
+        initial_layout: ImageLayout::UNDEFINED,
+        final_layout: if multisampled {
+            ImageLayout::COLOR_ATTACHMENT_OPTIMAL
+        } else {
+            final_layout
+        },
+    };
+
+    //Depth is only needed while the subpass runs so it is never stored
+    let depth_attachment = AttachmentDescription {
+        flags: AttachmentDescriptionFlags::empty(),
+        format: depth_format,
+        samples,
+        load_op: AttachmentLoadOp::CLEAR,
+        store_op: AttachmentStoreOp::DONT_CARE,
         stencil_load_op: AttachmentLoadOp::DONT_CARE,
         stencil_store_op: AttachmentStoreOp::DONT_CARE,
         initial_layout: ImageLayout::UNDEFINED,
-        final_layout: ImageLayout::PRESENT_SRC_KHR,
+        final_layout: ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
     };
 
     let color_attachment_ref = AttachmentReference {
@@ -25,6 +58,28 @@ pub fn create_render_pass(device: &Device, surface_format: Format) -> RenderPass
         layout: ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
     };
 
+    let depth_attachment_ref = AttachmentReference {
+        attachment: 1,
+        layout: ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+    };
+
+    let resolve_attachment = AttachmentDescription {
+        flags: AttachmentDescriptionFlags::empty(),
+        format: surface_format,
+        samples: SampleCountFlags::TYPE_1,
+        load_op: AttachmentLoadOp::DONT_CARE,
+        store_op: AttachmentStoreOp::STORE,
+        stencil_load_op: AttachmentLoadOp::DONT_CARE,
+        stencil_store_op: AttachmentStoreOp::DONT_CARE,
+        initial_layout: ImageLayout::UNDEFINED,
+        final_layout,
+    };
+
+    let resolve_attachment_ref = AttachmentReference {
+        attachment: 2,
+        layout: ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+    };
+
     let subpass = SubpassDescription {
         flags: SubpassDescriptionFlags::empty(),
         pipeline_bind_point: PipelineBindPoint::GRAPHICS,
@@ -32,24 +87,50 @@ pub fn create_render_pass(device: &Device, surface_format: Format) -> RenderPass
         p_input_attachments: std::ptr::null(),
         color_attachment_count: 1,
         p_color_attachments: &color_attachment_ref,
-        p_resolve_attachments: std::ptr::null(),
-        p_depth_stencil_attachment: std::ptr::null(),
+        p_resolve_attachments: if multisampled {
+            &resolve_attachment_ref
+        } else {
+            std::ptr::null()
+        },
+        p_depth_stencil_attachment: &depth_attachment_ref,
         preserve_attachment_count: 0,
         p_preserve_attachments: std::ptr::null(),
     };
 
-    let render_pass_attachments = [color_attachment];
+    let mut render_pass_attachments = vec![color_attachment, depth_attachment];
+    if multisampled {
+        render_pass_attachments.push(resolve_attachment);
+    }
 
-    let subpass_dependencies = [SubpassDependency {
+    let mut subpass_dependencies = vec![SubpassDependency {
         src_subpass: SUBPASS_EXTERNAL,
         dst_subpass: 0,
-        src_stage_mask: PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
-        dst_stage_mask: PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
-        src_access_mask: AccessFlags::empty(),
-        dst_access_mask: AccessFlags::COLOR_ATTACHMENT_WRITE,
+        //Writes of an earlier frame to the same attachments have to finish before they are cleared again
+        src_stage_mask: PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+            | PipelineStageFlags::EARLY_FRAGMENT_TESTS
+            | PipelineStageFlags::LATE_FRAGMENT_TESTS,
+        dst_stage_mask: PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+            | PipelineStageFlags::EARLY_FRAGMENT_TESTS,
+        src_access_mask: AccessFlags::COLOR_ATTACHMENT_WRITE
+            | AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+        dst_access_mask: AccessFlags::COLOR_ATTACHMENT_WRITE
+            | AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
         dependency_flags: DependencyFlags::empty(),
     }];
 
+    //Offscreen targets are read back with a transfer once the pass is done
+    if final_layout == ImageLayout::TRANSFER_SRC_OPTIMAL {
+        subpass_dependencies.push(SubpassDependency {
+            src_subpass: 0,
+            dst_subpass: SUBPASS_EXTERNAL,
+            src_stage_mask: PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            dst_stage_mask: PipelineStageFlags::TRANSFER,
+            src_access_mask: AccessFlags::COLOR_ATTACHMENT_WRITE,
+            dst_access_mask: AccessFlags::TRANSFER_READ,
+            dependency_flags: DependencyFlags::empty(),
+        });
+    }
+
     let render_pass_create_info = RenderPassCreateInfo {
         s_type: StructureType::RENDER_PASS_CREATE_INFO,
         flags: RenderPassCreateFlags::empty(),
@@ -62,9 +143,7 @@ pub fn create_render_pass(device: &Device, surface_format: Format) -> RenderPass
         p_dependencies: subpass_dependencies.as_ptr(),
     };
 
-    unsafe {
-        device
-            .create_render_pass(&render_pass_create_info, None)
-            .expect("Failed to create render pass")
-    }
+    let render_pass = unsafe { device.create_render_pass(&render_pass_create_info, None)? };
+    debug_utils.name_object(render_pass, "Render pass");
+    Ok(render_pass)
 }
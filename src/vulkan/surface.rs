@@ -1,3 +1,4 @@
+use crate::error::PotatoError;
 use ash::extensions::khr::Surface;
 use ash::extensions::khr::WaylandSurface;
 #[cfg(feature = "xlib")]
@@ -16,15 +17,19 @@ pub struct PotatoSurface {
 }
 //TODO make this support multiple platforms
 //TODO support multiple windows
-pub fn create_surface(entry: &Entry, instance: &Instance, window: &WinitWindow) -> PotatoSurface {
-    let surface = unsafe { create_platform_surface(entry, instance, window) };
+pub fn create_surface(
+    entry: &Entry,
+    instance: &Instance,
+    window: &WinitWindow,
+) -> Result<PotatoSurface, PotatoError> {
+    let surface = unsafe { create_platform_surface(entry, instance, window)? };
 
     let surface_loader = Surface::new(entry, instance);
 
-    PotatoSurface {
+    Ok(PotatoSurface {
         surface_loader,
-        surface: surface.unwrap(),
-    }
+        surface,
+    })
 }
 
 #[cfg(feature = "xlib")]
@@ -32,17 +37,19 @@ unsafe fn create_platform_surface(
     entry: &Entry,
     instance: &Instance,
     window: &WinitWindow,
-) -> Result<SurfaceKHR, ash::vk::Result> {
+) -> Result<SurfaceKHR, PotatoError> {
     debug!("Creating Xlib surface");
     let x11_create_info = XlibSurfaceCreateInfoKHR {
         s_type: StructureType::XLIB_SURFACE_CREATE_INFO_KHR,
         p_next: std::ptr::null(),
         flags: Default::default(),
-        window: window.xlib_window().unwrap() as Window,
-        dpy: window.xlib_display().unwrap() as *mut Display,
+        window: window.xlib_window().ok_or(PotatoError::UnsupportedWindow)? as Window,
+        dpy: window
+            .xlib_display()
+            .ok_or(PotatoError::UnsupportedWindow)? as *mut Display,
     };
     let xlib_surface_loader = XlibSurface::new(entry, instance);
-    xlib_surface_loader.create_xlib_surface(&x11_create_info, None)
+    Ok(xlib_surface_loader.create_xlib_surface(&x11_create_info, None)?)
 }
 
 #[cfg(feature = "wayland")]
@@ -50,15 +57,19 @@ unsafe fn create_platform_surface(
     entry: &Entry,
     instance: &Instance,
     window: &WinitWindow,
-) -> Result<SurfaceKHR, ash::vk::Result> {
+) -> Result<SurfaceKHR, PotatoError> {
     debug!("Creating Wayland surface");
     let wayland_create_info = WaylandSurfaceCreateInfoKHR {
         s_type: StructureType::WAYLAND_SURFACE_CREATE_INFO_KHR,
         p_next: std::ptr::null(),
         flags: Default::default(),
-        display: window.wayland_display().unwrap(),
-        surface: window.wayland_surface().unwrap(),
+        display: window
+            .wayland_display()
+            .ok_or(PotatoError::UnsupportedWindow)?,
+        surface: window
+            .wayland_surface()
+            .ok_or(PotatoError::UnsupportedWindow)?,
     };
     let wayland_surface_loader = WaylandSurface::new(entry, instance);
-    wayland_surface_loader.create_wayland_surface(&wayland_create_info, None)
+    Ok(wayland_surface_loader.create_wayland_surface(&wayland_create_info, None)?)
 }
@@ -1,5 +1,9 @@
+use super::debug_utils::PotatoDebugUtils;
+use super::frame_pacing::choose_swapchain_image_count;
+use super::present_mode::{choose_present_mode, PresentPolicy};
 use super::queue_family::QueueFamily;
 use super::surface::PotatoSurface;
+use crate::error::PotatoError;
 use ash::extensions::khr::Swapchain;
 use ash::vk::{
     ColorSpaceKHR, ComponentMapping, ComponentSwizzle, CompositeAlphaFlagsKHR, Extent2D, Format,
@@ -18,6 +22,7 @@ pub struct PotatoSwapChain {
     pub swapchain_format: Format,
     pub swapchain_extent: Extent2D,
     pub swapchain_image_views: Vec<ImageView>,
+    pub present_mode: PresentModeKHR,
 }
 
 pub struct SwapChainSupportDetail {
@@ -26,27 +31,43 @@ pub struct SwapChainSupportDetail {
     pub present_modes: Vec<PresentModeKHR>,
 }
 
+//TODO Reduce number of arguments
+#[allow(clippy::too_many_arguments)]
 pub fn create_swapchain(
     instance: &Instance,
     device: &Device,
+    debug_utils: &PotatoDebugUtils,
     physical_device: PhysicalDevice,
     surface: &PotatoSurface,
-    _queue_family: &QueueFamily,
-) -> PotatoSwapChain {
-    let swapchain_support = determine_swapchain_support(physical_device, surface);
-
-    let surface_format = choose_swapchain_format(&swapchain_support.formats);
-    let present_mode = choose_swapchain_present_mode(&swapchain_support.present_modes);
+    queue_family: &QueueFamily,
+    present_policy: PresentPolicy,
+    old_swapchain: SwapchainKHR,
+) -> Result<PotatoSwapChain, PotatoError> {
+    let swapchain_support = determine_swapchain_support(physical_device, surface)?;
+
+    let surface_format = choose_swapchain_format(&swapchain_support.formats)?;
+    let present_mode = choose_present_mode(&swapchain_support.present_modes, present_policy);
     let extent = choose_swapchain_extent(&swapchain_support.capabilities);
 
-    let image_count = if swapchain_support.capabilities.max_image_count > 0 {
-        swapchain_support.capabilities.max_image_count
-    } else {
-        swapchain_support.capabilities.min_image_count + 1
-    };
+    let image_count = choose_swapchain_image_count(&swapchain_support.capabilities);
 
+    //Images are shared when presenting from another family, saving an ownership transfer every frame
+    let queue_family_indices: Vec<u32> =
+        [queue_family.graphics_family, queue_family.present_family]
+            .iter()
+            .flatten()
+            .map(|x| *x as u32)
+            .collect();
     let (image_sharing_mode, queue_family_index_count, queue_family_indices) =
-        (SharingMode::EXCLUSIVE, 0, vec![]);
+        if queue_family.graphics_family != queue_family.present_family {
+            (
+                SharingMode::CONCURRENT,
+                queue_family_indices.len() as u32,
+                queue_family_indices,
+            )
+        } else {
+            (SharingMode::EXCLUSIVE, 0, vec![])
+        };
 
     let swapchain_create_info = SwapchainCreateInfoKHR {
         s_type: StructureType::SWAPCHAIN_CREATE_INFO_KHR,
@@ -65,80 +86,86 @@ pub fn create_swapchain(
         composite_alpha: CompositeAlphaFlagsKHR::OPAQUE,
         present_mode,
         clipped: TRUE,
-        old_swapchain: SwapchainKHR::null(),
+        //Lets the driver hand resources over from the retiring swapchain
+        old_swapchain,
         image_array_layers: 1,
     };
 
     let swapchain_loader = Swapchain::new(instance, device);
-    let swapchain = unsafe {
-        swapchain_loader
-            .create_swapchain(&swapchain_create_info, None)
-            .expect("Failed to create swapchain")
-    };
+    let swapchain = unsafe { swapchain_loader.create_swapchain(&swapchain_create_info, None)? };
+    debug_utils.name_object(swapchain, "Swapchain");
 
-    let swapchain_images = unsafe {
-        swapchain_loader
-            .get_swapchain_images(swapchain)
-            .expect("Failed to get swapchain images")
-    };
+    let swapchain_images = unsafe { swapchain_loader.get_swapchain_images(swapchain)? };
+    debug_utils.name_objects(&swapchain_images, "Swapchain image");
 
-    let swapchain_image_views =
-        create_image_views(device, surface_format.format, &swapchain_images);
+    let swapchain_image_views = create_image_views(
+        device,
+        debug_utils,
+        surface_format.format,
+        &swapchain_images,
+    )?;
 
-    PotatoSwapChain {
+    Ok(PotatoSwapChain {
         swapchain_loader,
         swapchain,
         swapchain_format: surface_format.format,
         swapchain_extent: extent,
         swapchain_images,
         swapchain_image_views,
+        present_mode,
+    })
+}
+
+//Destroys the image views and the swapchain, the images themselves belong to the swapchain
+pub fn destroy_swapchain(device: &Device, swapchain: &PotatoSwapChain) {
+    unsafe {
+        swapchain
+            .swapchain_image_views
+            .iter()
+            .for_each(|x| device.destroy_image_view(*x, None));
+        swapchain
+            .swapchain_loader
+            .destroy_swapchain(swapchain.swapchain, None);
     }
 }
 
 pub fn determine_swapchain_support(
     physical_device: PhysicalDevice,
     surface: &PotatoSurface,
-) -> SwapChainSupportDetail {
+) -> Result<SwapChainSupportDetail, PotatoError> {
     unsafe {
         let capabilities = surface
             .surface_loader
-            .get_physical_device_surface_capabilities(physical_device, surface.surface)
-            .expect("Failed to find surface capabilities");
+            .get_physical_device_surface_capabilities(physical_device, surface.surface)?;
         let formats = surface
             .surface_loader
-            .get_physical_device_surface_formats(physical_device, surface.surface)
-            .expect("Failed to find surface formats");
+            .get_physical_device_surface_formats(physical_device, surface.surface)?;
         let present_modes = surface
             .surface_loader
-            .get_physical_device_surface_present_modes(physical_device, surface.surface)
-            .expect("Failed to find surface present modes");
-        SwapChainSupportDetail {
+            .get_physical_device_surface_present_modes(physical_device, surface.surface)?;
+        Ok(SwapChainSupportDetail {
             capabilities,
             formats,
             present_modes,
-        }
+        })
     }
 }
 
-fn choose_swapchain_format(available_foramts: &[SurfaceFormatKHR]) -> SurfaceFormatKHR {
-    *available_foramts
+fn choose_swapchain_format(
+    available_foramts: &[SurfaceFormatKHR],
+) -> Result<SurfaceFormatKHR, PotatoError> {
+    available_foramts
         .iter()
         .find(|x| {
             x.format == Format::B8G8R8A8_SRGB && x.color_space == ColorSpaceKHR::SRGB_NONLINEAR
         })
-        .unwrap_or_else(|| available_foramts.first().unwrap())
-}
-
-fn choose_swapchain_present_mode(available_present_modes: &[PresentModeKHR]) -> PresentModeKHR {
-    available_present_modes
-        .iter()
-        .find(|x| **x == PresentModeKHR::MAILBOX)
-        .unwrap_or(&PresentModeKHR::FIFO)
-        .to_owned()
+        .or_else(|| available_foramts.first())
+        .copied()
+        .ok_or(PotatoError::NoSuitableDevice)
 }
 
 fn choose_swapchain_extent(capabilities: &SurfaceCapabilitiesKHR) -> Extent2D {
-    if capabilities.current_extent.width != u32::max_value() {
+    if capabilities.current_extent.width != u32::MAX {
         capabilities.current_extent
     } else {
         Extent2D {
@@ -156,14 +183,38 @@ fn choose_swapchain_extent(capabilities: &SurfaceCapabilitiesKHR) -> Extent2D {
     }
 }
 
-fn create_image_views(device: &Device, surface_format: Format, images: &[Image]) -> Vec<ImageView> {
+fn create_image_views(
+    device: &Device,
+    debug_utils: &PotatoDebugUtils,
+    surface_format: Format,
+    images: &[Image],
+) -> Result<Vec<ImageView>, PotatoError> {
     images
         .iter()
-        .map(|x| create_image_view(surface_format, *x, device))
+        .enumerate()
+        .map(|(i, x)| {
+            create_image_view(
+                device,
+                debug_utils,
+                surface_format,
+                *x,
+                ImageAspectFlags::COLOR,
+                1,
+                &format!("Swapchain image view {}", i),
+            )
+        })
         .collect()
 }
 
-fn create_image_view(surface_format: Format, image: Image, device: &Device) -> ImageView {
+pub fn create_image_view(
+    device: &Device,
+    debug_utils: &PotatoDebugUtils,
+    surface_format: Format,
+    image: Image,
+    aspect_mask: ImageAspectFlags,
+    mip_levels: u32,
+    name: &str,
+) -> Result<ImageView, PotatoError> {
     let image_view_create_info = ImageViewCreateInfo {
         s_type: StructureType::IMAGE_VIEW_CREATE_INFO,
         p_next: std::ptr::null(),
@@ -177,18 +228,16 @@ fn create_image_view(surface_format: Format, image: Image, device: &Device) -> I
             a: ComponentSwizzle::IDENTITY,
         },
         subresource_range: ImageSubresourceRange {
-            aspect_mask: ImageAspectFlags::COLOR,
+            aspect_mask,
             base_mip_level: 0,
-            level_count: 1,
+            level_count: mip_levels,
             base_array_layer: 0,
             layer_count: 1,
         },
         image,
     };
 
-    unsafe {
-        device
-            .create_image_view(&image_view_create_info, None)
-            .expect("Failed to create image view")
-    }
+    let image_view = unsafe { device.create_image_view(&image_view_create_info, None)? };
+    debug_utils.name_object(image_view, name);
+    Ok(image_view)
 }
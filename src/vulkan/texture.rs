@@ -0,0 +1,348 @@
+use super::allocator::{Allocation, Allocator};
+use super::buffer::{create_buffer, destroy_buffer};
+use super::command_pool::{begin_single_time_commands, end_single_time_commands};
+use super::constants::TEXTURE_FORMAT;
+use super::debug_utils::PotatoDebugUtils;
+use super::device_features::{DeviceFeature, EnabledDeviceFeatures};
+use super::image::{copy_buffer_to_image, create_image, destroy_image, transition_image_layout};
+use super::mipmap::{
+    generate_mip_chain, mip_level_count, record_mipmap_blits, supports_linear_blit, MipFilter,
+};
+use super::swapchain::create_image_view;
+use super::timeline::GpuTimeline;
+use crate::error::PotatoError;
+use crate::io::scene::{Texture, TextureImage};
+use ash::vk::{
+    BorderColor, BufferUsageFlags, CommandPool, CompareOp, DeviceSize, Extent2D, Filter, Format,
+    Image, ImageAspectFlags, ImageLayout, ImageUsageFlags, ImageView, MemoryPropertyFlags,
+    PhysicalDevice, Queue, SampleCountFlags, Sampler, SamplerAddressMode, SamplerCreateFlags,
+    SamplerCreateInfo, SamplerMipmapMode, StructureType, FALSE, TRUE,
+};
+use ash::{Device, Instance};
+use log::debug;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SamplerSettings {
+    pub mag_filter: Filter,
+    pub min_filter: Filter,
+    pub mipmap_mode: SamplerMipmapMode,
+    pub address_mode_u: SamplerAddressMode,
+    pub address_mode_v: SamplerAddressMode,
+    pub address_mode_w: SamplerAddressMode,
+    //Clamped to what the device supports, 1.0 turns anisotropic filtering off
+    pub max_anisotropy: f32,
+}
+
+impl Default for SamplerSettings {
+    fn default() -> Self {
+        SamplerSettings {
+            mag_filter: Filter::LINEAR,
+            min_filter: Filter::LINEAR,
+            mipmap_mode: SamplerMipmapMode::LINEAR,
+            address_mode_u: SamplerAddressMode::REPEAT,
+            address_mode_v: SamplerAddressMode::REPEAT,
+            address_mode_w: SamplerAddressMode::REPEAT,
+            max_anisotropy: 16.0,
+        }
+    }
+}
+
+impl SamplerSettings {
+    //Defaults, without anisotropic filtering unless the device was created with it
+    pub fn for_features(enabled_features: &EnabledDeviceFeatures) -> SamplerSettings {
+        if enabled_features.has_feature(DeviceFeature::SamplerAnisotropy) {
+            SamplerSettings::default()
+        } else {
+            SamplerSettings {
+                max_anisotropy: 1.0,
+                ..Default::default()
+            }
+        }
+    }
+}
+
+//Sampler state a glTF texture asks for
+impl From<&Texture> for SamplerSettings {
+    fn from(texture: &Texture) -> Self {
+        SamplerSettings {
+            mag_filter: texture.mag_filter,
+            min_filter: texture.min_filter,
+            mipmap_mode: texture.mipmap_mode,
+            address_mode_u: texture.address_mode_u,
+            address_mode_v: texture.address_mode_v,
+            ..Default::default()
+        }
+    }
+}
+
+//Sampled image ready to be bound as a combined image sampler
+#[derive(Clone, Copy, Debug)]
+pub struct PotatoTexture {
+    pub image: Image,
+    pub allocation: Allocation,
+    pub image_view: ImageView,
+    pub sampler: Sampler,
+    pub format: Format,
+    pub extent: Extent2D,
+    pub mip_levels: u32,
+}
+
+impl PotatoTexture {
+    //TODO Reduce number of arguments
+    #[allow(clippy::too_many_arguments)]
+    pub fn upload(
+        instance: &Instance,
+        physical_device: PhysicalDevice,
+        device: &Device,
+        debug_utils: &PotatoDebugUtils,
+        allocator: &mut Allocator,
+        command_pool: CommandPool,
+        submit_queue: Queue,
+        timeline: Option<&GpuTimeline>,
+        texture_image: &TextureImage,
+        enabled_features: &EnabledDeviceFeatures,
+        sampler_settings: &SamplerSettings,
+    ) -> Result<PotatoTexture, PotatoError> {
+        let extent = Extent2D {
+            width: texture_image.width,
+            height: texture_image.height,
+        };
+        if extent.width == 0
+            || extent.height == 0
+            || texture_image.pixels.len() as u64 != extent.width as u64 * extent.height as u64 * 4
+        {
+            return Err(PotatoError::InvalidTexture {
+                width: extent.width,
+                height: extent.height,
+                size: texture_image.pixels.len(),
+            });
+        }
+
+        let mip_levels = mip_level_count(extent.width, extent.height);
+        let blit_mipmaps = supports_linear_blit(instance, physical_device, TEXTURE_FORMAT);
+        let cpu_mip_chain = if blit_mipmaps {
+            debug!("Blitting {} mip levels on the device", mip_levels);
+            vec![]
+        } else {
+            debug!(
+                "Linear blits unsupported, generating {} mip levels on the CPU",
+                mip_levels
+            );
+            generate_mip_chain(texture_image, MipFilter::Kaiser, true)
+        };
+        let name = if texture_image.name.is_empty() {
+            "Texture"
+        } else {
+            texture_image.name.as_str()
+        };
+
+        let (image, allocation) = create_image(
+            device,
+            debug_utils,
+            allocator,
+            extent,
+            mip_levels,
+            SampleCountFlags::TYPE_1,
+            TEXTURE_FORMAT,
+            ImageUsageFlags::TRANSFER_SRC
+                | ImageUsageFlags::TRANSFER_DST
+                | ImageUsageFlags::SAMPLED,
+            MemoryPropertyFlags::DEVICE_LOCAL,
+            name,
+        )?;
+
+        let mut levels = vec![texture_image];
+        levels.extend(cpu_mip_chain.iter());
+        let result = upload_pixels(
+            device,
+            debug_utils,
+            allocator,
+            command_pool,
+            submit_queue,
+            timeline,
+            image,
+            mip_levels,
+            &levels,
+            name,
+        )
+        .and_then(|_| {
+            create_image_view(
+                device,
+                debug_utils,
+                TEXTURE_FORMAT,
+                image,
+                ImageAspectFlags::COLOR,
+                mip_levels,
+                &format!("{} view", name),
+            )
+        });
+        let image_view = match result {
+            Ok(image_view) => image_view,
+            Err(e) => {
+                destroy_image(device, allocator, image, allocation);
+                return Err(e);
+            }
+        };
+
+        //Supported is not enough, enabling anisotropy on a device created without it is invalid
+        let max_anisotropy = if enabled_features.has_feature(DeviceFeature::SamplerAnisotropy) {
+            let limits = unsafe { instance.get_physical_device_properties(physical_device) }.limits;
+            sampler_settings
+                .max_anisotropy
+                .min(limits.max_sampler_anisotropy)
+        } else {
+            1.0
+        };
+
+        let sampler = match create_sampler(
+            device,
+            debug_utils,
+            sampler_settings,
+            mip_levels,
+            max_anisotropy,
+            &format!("{} sampler", name),
+        ) {
+            Ok(sampler) => sampler,
+            Err(e) => {
+                unsafe { device.destroy_image_view(image_view, None) };
+                destroy_image(device, allocator, image, allocation);
+                return Err(e);
+            }
+        };
+
+        Ok(PotatoTexture {
+            image,
+            allocation,
+            image_view,
+            sampler,
+            format: TEXTURE_FORMAT,
+            extent,
+            mip_levels,
+        })
+    }
+
+    pub fn destroy(&self, device: &Device, allocator: &mut Allocator) {
+        unsafe {
+            device.destroy_sampler(self.sampler, None);
+            device.destroy_image_view(self.image_view, None);
+        }
+        destroy_image(device, allocator, self.image, self.allocation);
+    }
+}
+
+//Copies every given level through a staging buffer, blits the missing ones and leaves the image in SHADER_READ_ONLY_OPTIMAL
+//TODO Reduce number of arguments
+#[allow(clippy::too_many_arguments)]
+fn upload_pixels(
+    device: &Device,
+    debug_utils: &PotatoDebugUtils,
+    allocator: &mut Allocator,
+    command_pool: CommandPool,
+    submit_queue: Queue,
+    timeline: Option<&GpuTimeline>,
+    image: Image,
+    mip_levels: u32,
+    levels: &[&TextureImage],
+    name: &str,
+) -> Result<(), PotatoError> {
+    let mut pixels = vec![];
+    let mut level_regions = vec![];
+    for level in levels {
+        let extent = Extent2D {
+            width: level.width,
+            height: level.height,
+        };
+        level_regions.push((pixels.len() as DeviceSize, extent));
+        pixels.extend_from_slice(&level.pixels);
+    }
+
+    let (staging_buffer, staging_allocation) = create_buffer(
+        device,
+        debug_utils,
+        allocator,
+        pixels.len() as DeviceSize,
+        BufferUsageFlags::TRANSFER_SRC,
+        MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT,
+        &[],
+        &format!("{} staging", name),
+    )?;
+
+    let result = staging_allocation.write(&pixels).and_then(|_| {
+        let command_buffer = begin_single_time_commands(device, command_pool)?;
+        let upload_label =
+            debug_utils.begin_command_label(command_buffer, &format!("Upload {}", name));
+        transition_image_layout(
+            device,
+            command_buffer,
+            image,
+            mip_levels,
+            ImageLayout::UNDEFINED,
+            ImageLayout::TRANSFER_DST_OPTIMAL,
+        );
+        copy_buffer_to_image(
+            device,
+            command_buffer,
+            staging_buffer,
+            image,
+            &level_regions,
+        );
+        if levels.len() < mip_levels as usize {
+            record_mipmap_blits(
+                device,
+                command_buffer,
+                image,
+                level_regions[0].1,
+                mip_levels,
+            );
+        } else {
+            transition_image_layout(
+                device,
+                command_buffer,
+                image,
+                mip_levels,
+                ImageLayout::TRANSFER_DST_OPTIMAL,
+                ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            );
+        }
+        drop(upload_label);
+        end_single_time_commands(device, command_pool, submit_queue, timeline, command_buffer)
+    });
+
+    destroy_buffer(device, allocator, staging_buffer, staging_allocation);
+
+    result
+}
+
+pub fn create_sampler(
+    device: &Device,
+    debug_utils: &PotatoDebugUtils,
+    sampler_settings: &SamplerSettings,
+    mip_levels: u32,
+    max_anisotropy: f32,
+    name: &str,
+) -> Result<Sampler, PotatoError> {
+    let sampler_create_info = SamplerCreateInfo {
+        s_type: StructureType::SAMPLER_CREATE_INFO,
+        p_next: std::ptr::null(),
+        flags: SamplerCreateFlags::empty(),
+        mag_filter: sampler_settings.mag_filter,
+        min_filter: sampler_settings.min_filter,
+        mipmap_mode: sampler_settings.mipmap_mode,
+        address_mode_u: sampler_settings.address_mode_u,
+        address_mode_v: sampler_settings.address_mode_v,
+        address_mode_w: sampler_settings.address_mode_w,
+        mip_lod_bias: 0.0,
+        anisotropy_enable: if max_anisotropy > 1.0 { TRUE } else { FALSE },
+        max_anisotropy,
+        compare_enable: FALSE,
+        compare_op: CompareOp::ALWAYS,
+        min_lod: 0.0,
+        max_lod: mip_levels as f32,
+        border_color: BorderColor::INT_OPAQUE_BLACK,
+        unnormalized_coordinates: FALSE,
+    };
+
+    let sampler = unsafe { device.create_sampler(&sampler_create_info, None)? };
+    debug_utils.name_object(sampler, name);
+    Ok(sampler)
+}
@@ -0,0 +1,270 @@
+use super::debug_utils::PotatoDebugUtils;
+use super::device_features::{DeviceFeature, EnabledDeviceFeatures};
+use crate::error::PotatoError;
+use ash::vk::{
+    CommandBuffer, Fence, FenceCreateFlags, FenceCreateInfo, PipelineStageFlags, Queue,
+    Result as VkResult, Semaphore, SemaphoreCreateFlags, SemaphoreCreateInfo, SemaphoreType,
+    SemaphoreTypeCreateInfo, SemaphoreWaitFlags, SemaphoreWaitInfo, StructureType, SubmitInfo,
+    TimelineSemaphoreSubmitInfo,
+};
+use ash::Device;
+use std::ffi::c_void;
+use std::sync::Mutex;
+
+//Needs a Vulkan 1.2 device created with timelineSemaphore, otherwise the binary semaphore and fence path is used
+pub fn is_timeline_enabled(enabled_features: &EnabledDeviceFeatures) -> bool {
+    enabled_features.has_feature(DeviceFeature::TimelineSemaphore)
+}
+
+//One counter on the GPU that only increases, every submission signaling it gets the next value
+//Frames, uploads and retirements share it, so "has value N completed?" replaces waiting on queues and fences
+//Values have to be signaled in order, so a submission to another queue than the previous one waits on the GPU for the previous value
+pub struct GpuTimeline {
+    semaphore: Semaphore,
+    //Locked while submitting so values reach the queues in order
+    last_submission: Mutex<LastSubmission>,
+}
+
+struct LastSubmission {
+    //Highest value handed to a submission, 0 before the first one
+    value: u64,
+    queue: Queue,
+}
+
+impl GpuTimeline {
+    pub fn new(
+        device: &Device,
+        debug_utils: &PotatoDebugUtils,
+        name: &str,
+    ) -> Result<GpuTimeline, PotatoError> {
+        let semaphore_type_create_info = SemaphoreTypeCreateInfo {
+            s_type: StructureType::SEMAPHORE_TYPE_CREATE_INFO,
+            p_next: std::ptr::null(),
+            semaphore_type: SemaphoreType::TIMELINE,
+            initial_value: 0,
+        };
+
+        let semaphore_create_info = SemaphoreCreateInfo {
+            s_type: StructureType::SEMAPHORE_CREATE_INFO,
+            p_next: &semaphore_type_create_info as *const SemaphoreTypeCreateInfo as *const c_void,
+            flags: SemaphoreCreateFlags::empty(),
+        };
+
+        let semaphore = unsafe { device.create_semaphore(&semaphore_create_info, None)? };
+        debug_utils.name_object(semaphore, name);
+
+        Ok(GpuTimeline {
+            semaphore,
+            last_submission: Mutex::new(LastSubmission {
+                value: 0,
+                queue: Queue::null(),
+            }),
+        })
+    }
+
+    pub fn semaphore(&self) -> Semaphore {
+        self.semaphore
+    }
+
+    //Value of the latest submission, completing it means everything submitted before has completed too
+    pub fn last_value(&self) -> u64 {
+        self.last_submission
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .value
+    }
+
+    //Waits on binary wait_semaphores and signals the binary signal_semaphores along with the next value, which is returned
+    pub fn submit(
+        &self,
+        device: &Device,
+        queue: Queue,
+        command_buffers: &[CommandBuffer],
+        wait_semaphores: &[Semaphore],
+        wait_stages: &[PipelineStageFlags],
+        signal_semaphores: &[Semaphore],
+    ) -> Result<u64, PotatoError> {
+        let mut last_submission = self
+            .last_submission
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let value = last_submission.value + 1;
+
+        //Values of binary semaphores are ignored but the arrays have to match
+        let mut all_wait_semaphores = wait_semaphores.to_vec();
+        let mut all_wait_stages = wait_stages.to_vec();
+        let mut wait_values = vec![0; wait_semaphores.len()];
+        //Another queue could otherwise signal value before the previous value is signaled
+        if last_submission.value > 0 && last_submission.queue != queue {
+            all_wait_semaphores.push(self.semaphore);
+            all_wait_stages.push(PipelineStageFlags::ALL_COMMANDS);
+            wait_values.push(last_submission.value);
+        }
+        let mut all_signal_semaphores = signal_semaphores.to_vec();
+        all_signal_semaphores.push(self.semaphore);
+        let mut signal_values = vec![0; signal_semaphores.len()];
+        signal_values.push(value);
+
+        let timeline_submit_info = TimelineSemaphoreSubmitInfo {
+            s_type: StructureType::TIMELINE_SEMAPHORE_SUBMIT_INFO,
+            p_next: std::ptr::null(),
+            wait_semaphore_value_count: wait_values.len() as u32,
+            p_wait_semaphore_values: wait_values.as_ptr(),
+            signal_semaphore_value_count: signal_values.len() as u32,
+            p_signal_semaphore_values: signal_values.as_ptr(),
+        };
+
+        let submit_infos = [SubmitInfo {
+            s_type: StructureType::SUBMIT_INFO,
+            p_next: &timeline_submit_info as *const TimelineSemaphoreSubmitInfo as *const c_void,
+            wait_semaphore_count: all_wait_semaphores.len() as u32,
+            p_wait_semaphores: all_wait_semaphores.as_ptr(),
+            p_wait_dst_stage_mask: all_wait_stages.as_ptr(),
+            command_buffer_count: command_buffers.len() as u32,
+            p_command_buffers: command_buffers.as_ptr(),
+            signal_semaphore_count: all_signal_semaphores.len() as u32,
+            p_signal_semaphores: all_signal_semaphores.as_ptr(),
+        }];
+
+        unsafe { device.queue_submit(queue, &submit_infos, Fence::null())? };
+        *last_submission = LastSubmission { value, queue };
+        Ok(value)
+    }
+
+    //Value of the latest submission the GPU has finished
+    pub fn completed_value(&self, device: &Device) -> Result<u64, PotatoError> {
+        Ok(unsafe { device.get_semaphore_counter_value(self.semaphore)? })
+    }
+
+    pub fn is_complete(&self, device: &Device, value: u64) -> Result<bool, PotatoError> {
+        Ok(self.completed_value(device)? >= value)
+    }
+
+    pub fn wait(&self, device: &Device, value: u64) -> Result<(), PotatoError> {
+        self.wait_timeout(device, value, u64::MAX)?;
+        Ok(())
+    }
+
+    //Returns false when timeout nanoseconds pass before value completes
+    pub fn wait_timeout(
+        &self,
+        device: &Device,
+        value: u64,
+        timeout: u64,
+    ) -> Result<bool, PotatoError> {
+        let semaphores = [self.semaphore];
+        let values = [value];
+        let semaphore_wait_info = SemaphoreWaitInfo {
+            s_type: StructureType::SEMAPHORE_WAIT_INFO,
+            p_next: std::ptr::null(),
+            flags: SemaphoreWaitFlags::empty(),
+            semaphore_count: semaphores.len() as u32,
+            p_semaphores: semaphores.as_ptr(),
+            p_values: values.as_ptr(),
+        };
+
+        match unsafe { device.wait_semaphores(&semaphore_wait_info, timeout) } {
+            Ok(_) => Ok(true),
+            Err(VkResult::TIMEOUT) => Ok(false),
+            Err(vk_result) => Err(vk_result.into()),
+        }
+    }
+
+    pub fn destroy(&self, device: &Device) {
+        unsafe { device.destroy_semaphore(self.semaphore, None) };
+    }
+}
+
+//Submits command_buffer and blocks until it has executed, without idling the whole queue
+//Waits on the next timeline value when there is a timeline, otherwise on a fence of its own
+pub fn submit_and_wait(
+    device: &Device,
+    queue: Queue,
+    command_buffer: CommandBuffer,
+    timeline: Option<&GpuTimeline>,
+) -> Result<(), PotatoError> {
+    let command_buffers = [command_buffer];
+    if let Some(timeline) = timeline {
+        let value = timeline.submit(device, queue, &command_buffers, &[], &[], &[])?;
+        return timeline.wait(device, value);
+    }
+
+    let fence_create_info = FenceCreateInfo {
+        s_type: StructureType::FENCE_CREATE_INFO,
+        p_next: std::ptr::null(),
+        flags: FenceCreateFlags::empty(),
+    };
+    let submit_infos = [SubmitInfo {
+        s_type: StructureType::SUBMIT_INFO,
+        p_next: std::ptr::null(),
+        wait_semaphore_count: 0,
+        p_wait_semaphores: std::ptr::null(),
+        p_wait_dst_stage_mask: std::ptr::null(),
+        command_buffer_count: command_buffers.len() as u32,
+        p_command_buffers: command_buffers.as_ptr(),
+        signal_semaphore_count: 0,
+        p_signal_semaphores: std::ptr::null(),
+    }];
+
+    let fence = unsafe { device.create_fence(&fence_create_info, None)? };
+    let result = unsafe {
+        device
+            .queue_submit(queue, &submit_infos, fence)
+            .and_then(|_| device.wait_for_fences(&[fence], true, u64::MAX))
+    };
+    unsafe { device.destroy_fence(fence, None) };
+
+    Ok(result?)
+}
+
+//Resources still used by submissions, each kept until the value it was retired at has completed, on a timeline or a count of fenced submissions
+#[derive(Debug)]
+pub struct Retirements<T> {
+    pending: Vec<(u64, T)>,
+}
+
+impl<T> Default for Retirements<T> {
+    fn default() -> Self {
+        Retirements { pending: vec![] }
+    }
+}
+
+impl<T> Retirements<T> {
+    pub fn new() -> Retirements<T> {
+        Retirements::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    //resource is handed back once value has completed
+    pub fn retire(&mut self, value: u64, resource: T) {
+        self.pending.push((value, resource));
+    }
+
+    //Resources whose value is at most completed_value, in the order they were retired
+    pub fn take_completed(&mut self, completed_value: u64) -> Vec<T> {
+        let mut completed = vec![];
+        for (value, resource) in std::mem::take(&mut self.pending) {
+            if value <= completed_value {
+                completed.push(resource);
+            } else {
+                self.pending.push((value, resource));
+            }
+        }
+        completed
+    }
+
+    //Only valid once the device is idle
+    pub fn take_all(&mut self) -> Vec<T> {
+        std::mem::take(&mut self.pending)
+            .into_iter()
+            .map(|(_, resource)| resource)
+            .collect()
+    }
+}
@@ -7,10 +7,7 @@ pub fn vk_to_string(raw_string_array: &[c_char]) -> String {
         CStr::from_ptr(pointer)
     };
 
-    raw_string
-        .to_str()
-        .expect("Failed to convert vulkan raw string.")
-        .to_owned()
+    raw_string.to_string_lossy().into_owned()
 }
 
 pub fn conver_str_vec_to_c_str_ptr_vec(vec: Vec<&str>) -> (Vec<CString>, Vec<*const i8>) {
@@ -0,0 +1,312 @@
+use crate::error::PotatoError;
+use ash::vk::{
+    DebugUtilsMessageSeverityFlagsEXT, DebugUtilsMessageTypeFlagsEXT, ObjectType,
+    ValidationFeatureEnableEXT,
+};
+use log::{log, Level};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::sync::RwLock;
+
+//Whether the Khronos layer is loaded, only Required fails when it is not installed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValidationMode {
+    Off,
+    //Used when installed, skipped with a warning otherwise
+    Optional,
+    Required,
+}
+
+impl Default for ValidationMode {
+    //Debug builds validate whenever they can, release builds never need the layer
+    fn default() -> Self {
+        if cfg!(debug_assertions) {
+            ValidationMode::Optional
+        } else {
+            ValidationMode::Off
+        }
+    }
+}
+
+impl Display for ValidationMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ValidationMode::Off => "off",
+            ValidationMode::Optional => "on",
+            ValidationMode::Required => "required",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for ValidationMode {
+    type Err = PotatoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "off" | "false" | "0" => Ok(ValidationMode::Off),
+            "on" | "true" | "1" | "optional" => Ok(ValidationMode::Optional),
+            "required" | "require" => Ok(ValidationMode::Required),
+            _ => Err(PotatoError::InvalidSetting {
+                name: "validation".to_string(),
+                value: s.to_string(),
+            }),
+        }
+    }
+}
+
+//Extra checks enabled through VkValidationFeaturesEXT, all of them slow the application down
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ValidationFeatures {
+    pub gpu_assisted: bool,
+    pub best_practices: bool,
+    pub synchronization: bool,
+}
+
+impl ValidationFeatures {
+    pub fn is_empty(&self) -> bool {
+        !self.gpu_assisted && !self.best_practices && !self.synchronization
+    }
+
+    pub fn enables(&self) -> Vec<ValidationFeatureEnableEXT> {
+        let mut enables = vec![];
+        if self.gpu_assisted {
+            enables.push(ValidationFeatureEnableEXT::GPU_ASSISTED);
+            enables.push(ValidationFeatureEnableEXT::GPU_ASSISTED_RESERVE_BINDING_SLOT);
+        }
+        if self.best_practices {
+            enables.push(ValidationFeatureEnableEXT::BEST_PRACTICES);
+        }
+        if self.synchronization {
+            enables.push(ValidationFeatureEnableEXT::SYNCHRONIZATION_VALIDATION);
+        }
+        enables
+    }
+}
+
+//Comma separated list of gpu, best-practices and sync, empty or none turns them all off
+impl FromStr for ValidationFeatures {
+    type Err = PotatoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut features = ValidationFeatures::default();
+        for name in split_list(s) {
+            match name.as_str() {
+                "gpu" | "gpu-assisted" => features.gpu_assisted = true,
+                "best-practices" | "bestpractices" => features.best_practices = true,
+                "sync" | "synchronization" => features.synchronization = true,
+                "none" => {}
+                _ => return Err(invalid("validation features", s)),
+            }
+        }
+        Ok(features)
+    }
+}
+
+//Everything about the validation layer and the debug messenger
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidationConfig {
+    pub mode: ValidationMode,
+    pub severities: DebugUtilsMessageSeverityFlagsEXT,
+    pub message_types: DebugUtilsMessageTypeFlagsEXT,
+    pub features: ValidationFeatures,
+}
+
+impl Default for ValidationConfig {
+    fn default() -> Self {
+        ValidationConfig {
+            mode: ValidationMode::default(),
+            severities: DebugUtilsMessageSeverityFlagsEXT::WARNING
+                | DebugUtilsMessageSeverityFlagsEXT::ERROR,
+            message_types: DebugUtilsMessageTypeFlagsEXT::GENERAL
+                | DebugUtilsMessageTypeFlagsEXT::PERFORMANCE
+                | DebugUtilsMessageTypeFlagsEXT::VALIDATION,
+            features: ValidationFeatures::default(),
+        }
+    }
+}
+
+impl ValidationConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.mode != ValidationMode::Off
+    }
+}
+
+//Comma separated list of error, warning, info and verbose, or all
+pub fn parse_message_severities(s: &str) -> Result<DebugUtilsMessageSeverityFlagsEXT, PotatoError> {
+    let mut severities = DebugUtilsMessageSeverityFlagsEXT::empty();
+    for name in split_list(s) {
+        severities |= match name.as_str() {
+            "error" => DebugUtilsMessageSeverityFlagsEXT::ERROR,
+            "warning" | "warn" => DebugUtilsMessageSeverityFlagsEXT::WARNING,
+            "info" => DebugUtilsMessageSeverityFlagsEXT::INFO,
+            "verbose" => DebugUtilsMessageSeverityFlagsEXT::VERBOSE,
+            "all" => {
+                DebugUtilsMessageSeverityFlagsEXT::ERROR
+                    | DebugUtilsMessageSeverityFlagsEXT::WARNING
+                    | DebugUtilsMessageSeverityFlagsEXT::INFO
+                    | DebugUtilsMessageSeverityFlagsEXT::VERBOSE
+            }
+            _ => return Err(invalid("validation severity", s)),
+        };
+    }
+    Ok(severities)
+}
+
+//Comma separated list of general, validation and performance, or all
+pub fn parse_message_types(s: &str) -> Result<DebugUtilsMessageTypeFlagsEXT, PotatoError> {
+    let mut message_types = DebugUtilsMessageTypeFlagsEXT::empty();
+    for name in split_list(s) {
+        message_types |= match name.as_str() {
+            "general" => DebugUtilsMessageTypeFlagsEXT::GENERAL,
+            "validation" => DebugUtilsMessageTypeFlagsEXT::VALIDATION,
+            "performance" => DebugUtilsMessageTypeFlagsEXT::PERFORMANCE,
+            "all" => {
+                DebugUtilsMessageTypeFlagsEXT::GENERAL
+                    | DebugUtilsMessageTypeFlagsEXT::VALIDATION
+                    | DebugUtilsMessageTypeFlagsEXT::PERFORMANCE
+            }
+            _ => return Err(invalid("validation message type", s)),
+        };
+    }
+    Ok(message_types)
+}
+
+fn split_list(s: &str) -> Vec<String> {
+    s.split(',')
+        .map(|x| x.trim().to_lowercase())
+        .filter(|x| !x.is_empty())
+        .collect()
+}
+
+fn invalid(name: &str, value: &str) -> PotatoError {
+    PotatoError::InvalidSetting {
+        name: name.to_string(),
+        value: value.to_string(),
+    }
+}
+
+//Handle and debug name of an object a validation message is about
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidationObject {
+    pub object_type: ObjectType,
+    pub handle: u64,
+    pub name: Option<String>,
+}
+
+//One debug messenger callback with everything copied out of the callback data
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidationMessage {
+    pub severity: DebugUtilsMessageSeverityFlagsEXT,
+    pub message_types: DebugUtilsMessageTypeFlagsEXT,
+    pub message_id_name: Option<String>,
+    pub message_id_number: i32,
+    pub message: String,
+    pub objects: Vec<ValidationObject>,
+    //Innermost label last
+    pub command_buffer_labels: Vec<String>,
+    pub queue_labels: Vec<String>,
+}
+
+impl ValidationMessage {
+    pub fn level(&self) -> Level {
+        message_level(self.severity)
+    }
+}
+
+impl Display for ValidationMessage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "[{}] {} ({:#x}): {}",
+            message_type_names(self.message_types),
+            self.message_id_name.as_deref().unwrap_or("Unnamed"),
+            self.message_id_number,
+            self.message
+        )?;
+        if !self.objects.is_empty() {
+            let objects: Vec<String> = self
+                .objects
+                .iter()
+                .map(|x| match &x.name {
+                    Some(name) => format!("{:?} {:#x} {:?}", x.object_type, x.handle, name),
+                    None => format!("{:?} {:#x}", x.object_type, x.handle),
+                })
+                .collect();
+            write!(f, " | objects: {}", objects.join(", "))?;
+        }
+        if !self.command_buffer_labels.is_empty() {
+            write!(
+                f,
+                " | command buffer labels: {}",
+                self.command_buffer_labels.join(" > ")
+            )?;
+        }
+        if !self.queue_labels.is_empty() {
+            write!(f, " | queue labels: {}", self.queue_labels.join(" > "))?;
+        }
+        Ok(())
+    }
+}
+
+//The most severe bit decides, so combined flags never fall through
+pub fn message_level(severity: DebugUtilsMessageSeverityFlagsEXT) -> Level {
+    if severity.contains(DebugUtilsMessageSeverityFlagsEXT::ERROR) {
+        Level::Error
+    } else if severity.contains(DebugUtilsMessageSeverityFlagsEXT::WARNING) {
+        Level::Warn
+    } else if severity.contains(DebugUtilsMessageSeverityFlagsEXT::INFO) {
+        Level::Info
+    } else {
+        Level::Trace
+    }
+}
+
+pub fn message_type_names(message_types: DebugUtilsMessageTypeFlagsEXT) -> String {
+    let names: Vec<&str> = [
+        (DebugUtilsMessageTypeFlagsEXT::GENERAL, "General"),
+        (DebugUtilsMessageTypeFlagsEXT::VALIDATION, "Validation"),
+        (DebugUtilsMessageTypeFlagsEXT::PERFORMANCE, "Performance"),
+    ]
+    .iter()
+    .filter(|(flag, _)| message_types.contains(*flag))
+    .map(|(_, name)| *name)
+    .collect();
+
+    if names.is_empty() {
+        "Unknown".to_string()
+    } else {
+        names.join("|")
+    }
+}
+
+type ValidationSink = Box<dyn Fn(&ValidationMessage) + Send + Sync>;
+
+static VALIDATION_SINK: RwLock<Option<ValidationSink>> = RwLock::new(None);
+
+//Receives every message instead of the log, messages can arrive from any thread the driver calls back on
+pub fn set_validation_sink<F>(sink: F)
+where
+    F: Fn(&ValidationMessage) + Send + Sync + 'static,
+{
+    if let Ok(mut validation_sink) = VALIDATION_SINK.write() {
+        *validation_sink = Some(Box::new(sink));
+    }
+}
+
+//Sends messages back to the log
+pub fn clear_validation_sink() {
+    if let Ok(mut validation_sink) = VALIDATION_SINK.write() {
+        *validation_sink = None;
+    }
+}
+
+pub fn report_validation_message(message: &ValidationMessage) {
+    if let Ok(validation_sink) = VALIDATION_SINK.read() {
+        if let Some(sink) = validation_sink.as_ref() {
+            sink(message);
+            return;
+        }
+    }
+    log!(message.level(), "{}", message);
+}
@@ -1,21 +1,29 @@
-use super::buffer::{copy_buffer, create_buffer};
-use super::constants::{INDICES_DATA, VERTICES_DATA};
+use super::allocator::{Allocation, Allocator};
+use super::buffer::create_device_local_buffer;
+use super::debug_utils::PotatoDebugUtils;
+use super::timeline::GpuTimeline;
+use crate::error::PotatoError;
 use ash::vk::{
-    Buffer, BufferUsageFlags, CommandPool, DeviceMemory, DeviceSize, Format, MemoryMapFlags,
-    MemoryPropertyFlags, PhysicalDevice, Queue, VertexInputAttributeDescription,
+    Buffer, BufferUsageFlags, CommandPool, Format, Queue, VertexInputAttributeDescription,
     VertexInputBindingDescription, VertexInputRate,
 };
 use ash::Device;
-use ash::Instance;
+use bytemuck::{Pod, Zeroable};
 use memoffset::offset_of;
 
 #[repr(C)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy)]
 pub struct Vertex {
-    pub pos: [f32; 2],
+    pub pos: [f32; 3],
     pub color: [f32; 3],
+    pub normal: [f32; 3],
+    pub tex_coord: [f32; 2],
 }
 
+//Only f32 fields and no padding, so any bytes are a valid vertex
+unsafe impl Zeroable for Vertex {}
+unsafe impl Pod for Vertex {}
+
 impl Vertex {
     pub fn get_binding_descriptions() -> [VertexInputBindingDescription; 1] {
         [VertexInputBindingDescription {
@@ -25,12 +33,12 @@ impl Vertex {
         }]
     }
 
-    pub fn get_attribute_descriptions() -> [VertexInputAttributeDescription; 2] {
+    pub fn get_attribute_descriptions() -> [VertexInputAttributeDescription; 4] {
         [
             VertexInputAttributeDescription {
                 location: 0,
                 binding: 0,
-                format: Format::R32G32_SFLOAT,
+                format: Format::R32G32B32_SFLOAT,
                 offset: offset_of!(Self, pos) as u32,
             },
             VertexInputAttributeDescription {
@@ -39,124 +47,72 @@ impl Vertex {
                 format: Format::R32G32B32_SFLOAT,
                 offset: offset_of!(Self, color) as u32,
             },
+            VertexInputAttributeDescription {
+                location: 2,
+                binding: 0,
+                format: Format::R32G32B32_SFLOAT,
+                offset: offset_of!(Self, normal) as u32,
+            },
+            VertexInputAttributeDescription {
+                location: 3,
+                binding: 0,
+                format: Format::R32G32_SFLOAT,
+                offset: offset_of!(Self, tex_coord) as u32,
+            },
         ]
     }
 }
 
-//TODO undo duplicated logic
+//TODO Reduce number of arguments
+#[allow(clippy::too_many_arguments)]
 pub fn create_vertex_buffer(
-    instance: &Instance,
     device: &Device,
-    physical_device: PhysicalDevice,
+    debug_utils: &PotatoDebugUtils,
+    allocator: &mut Allocator,
     command_pool: CommandPool,
     submit_queue: Queue,
-    buffer_usage_flags: BufferUsageFlags,
-) -> (Buffer, DeviceMemory) {
-    let buffer_size = std::mem::size_of_val(&VERTICES_DATA) as DeviceSize;
-    let device_memory_properties =
-        unsafe { instance.get_physical_device_memory_properties(physical_device) };
-
-    let (staging_buffer, staging_buffer_memory) = create_buffer(
-        device,
-        buffer_size,
-        BufferUsageFlags::TRANSFER_SRC,
-        MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT,
-        &device_memory_properties,
-    );
-    unsafe {
-        let data_ptr = device
-            .map_memory(
-                staging_buffer_memory,
-                0,
-                buffer_size,
-                MemoryMapFlags::empty(),
-            )
-            .expect("Failed to map memory") as *mut Vertex;
-
-        data_ptr.copy_from_nonoverlapping(VERTICES_DATA.as_ptr(), VERTICES_DATA.len());
-
-        device.unmap_memory(staging_buffer_memory);
-    }
-
-    let (vertex_buffer, vertex_buffer_memory) = create_buffer(
-        device,
-        buffer_size,
-        buffer_usage_flags,
-        MemoryPropertyFlags::DEVICE_LOCAL,
-        &device_memory_properties,
-    );
-
-    copy_buffer(
+    timeline: Option<&GpuTimeline>,
+    vertices: &[Vertex],
+    queue_family_indices: &[u32],
+    name: &str,
+) -> Result<(Buffer, Allocation), PotatoError> {
+    create_device_local_buffer(
         device,
-        submit_queue,
+        debug_utils,
+        allocator,
         command_pool,
-        staging_buffer,
-        vertex_buffer,
-        buffer_size,
-    );
-
-    unsafe {
-        device.destroy_buffer(staging_buffer, None);
-        device.free_memory(staging_buffer_memory, None);
-    }
-
-    (vertex_buffer, vertex_buffer_memory)
+        submit_queue,
+        timeline,
+        vertices,
+        BufferUsageFlags::VERTEX_BUFFER,
+        queue_family_indices,
+        &format!("{} vertex buffer", name),
+    )
 }
 
+//TODO Reduce number of arguments
+#[allow(clippy::too_many_arguments)]
 pub fn create_index_buffer(
-    instance: &Instance,
     device: &Device,
-    physical_device: PhysicalDevice,
+    debug_utils: &PotatoDebugUtils,
+    allocator: &mut Allocator,
     command_pool: CommandPool,
     submit_queue: Queue,
-    buffer_usage_flags: BufferUsageFlags,
-) -> (Buffer, DeviceMemory) {
-    let buffer_size = std::mem::size_of_val(&INDICES_DATA) as DeviceSize;
-    let device_memory_properties =
-        unsafe { instance.get_physical_device_memory_properties(physical_device) };
-
-    let (staging_buffer, staging_buffer_memory) = create_buffer(
-        device,
-        buffer_size,
-        BufferUsageFlags::TRANSFER_SRC,
-        MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT,
-        &device_memory_properties,
-    );
-    unsafe {
-        let data_ptr = device
-            .map_memory(
-                staging_buffer_memory,
-                0,
-                buffer_size,
-                MemoryMapFlags::empty(),
-            )
-            .expect("Failed to map memory") as *mut u32;
-
-        data_ptr.copy_from_nonoverlapping(INDICES_DATA.as_ptr(), INDICES_DATA.len());
-
-        device.unmap_memory(staging_buffer_memory);
-    }
-
-    let (vertex_buffer, vertex_buffer_memory) = create_buffer(
-        device,
-        buffer_size,
-        buffer_usage_flags,
-        MemoryPropertyFlags::DEVICE_LOCAL,
-        &device_memory_properties,
-    );
-
-    copy_buffer(
+    timeline: Option<&GpuTimeline>,
+    indices: &[u32],
+    queue_family_indices: &[u32],
+    name: &str,
+) -> Result<(Buffer, Allocation), PotatoError> {
+    create_device_local_buffer(
         device,
-        submit_queue,
+        debug_utils,
+        allocator,
         command_pool,
-        staging_buffer,
-        vertex_buffer,
-        buffer_size,
-    );
-    unsafe {
-        device.destroy_buffer(staging_buffer, None);
-        device.free_memory(staging_buffer_memory, None);
-    }
-
-    (vertex_buffer, vertex_buffer_memory)
+        submit_queue,
+        timeline,
+        indices,
+        BufferUsageFlags::INDEX_BUFFER,
+        queue_family_indices,
+        &format!("{} index buffer", name),
+    )
 }
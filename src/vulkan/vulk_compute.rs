@@ -0,0 +1,338 @@
+use super::allocator::{Allocation, Allocator, HeapStats};
+use super::buffer::{create_buffer, destroy_buffer};
+use super::command_pool::{
+    begin_single_time_commands, create_command_pool, end_single_time_commands,
+};
+use super::compute_pipeline::{
+    create_compute_descriptor_pool, create_compute_descriptor_set,
+    create_compute_descriptor_set_layout, create_compute_pipeline,
+};
+use super::config::{device_selector_from_env, validation_config_from_env};
+use super::debug_utils::PotatoDebugUtils;
+use super::device::create_compute_logical_device;
+use super::device_features::{negotiate_device_features, DeviceRequirements};
+use super::instance::create_instance;
+use super::physical_device::{describe_device, select_compute_physical_device};
+use super::vulk_validation_layers::setup_debug_utils;
+use crate::error::PotatoError;
+use ash::extensions::ext::DebugUtils;
+use ash::vk::{
+    AccessFlags, Buffer, BufferUsageFlags, CommandPool, CommandPoolCreateFlags,
+    DebugUtilsMessengerEXT, DependencyFlags, DescriptorPool, DescriptorSetLayout, DeviceSize,
+    MemoryBarrier, MemoryPropertyFlags, Pipeline, PipelineBindPoint, PipelineLayout,
+    PipelineStageFlags, Queue, StructureType,
+};
+use ash::{Device, Entry, Instance};
+use bytemuck::Pod;
+use log::{debug, error};
+use std::collections::HashMap;
+
+pub struct StorageBuffer {
+    buffer: Buffer,
+    allocation: Allocation,
+    size: DeviceSize,
+}
+
+impl StorageBuffer {
+    pub fn size(&self) -> DeviceSize {
+        self.size
+    }
+}
+
+//Everything dispatching a shader needs apart from the descriptor set
+#[derive(Clone, Copy)]
+struct ComputePipeline {
+    set_layout: DescriptorSetLayout,
+    pipeline: Pipeline,
+    pipeline_layout: PipelineLayout,
+}
+
+pub struct ComputeApiObjects {
+    _entry: Entry,
+    instance: Instance,
+    debug_utils_loader: DebugUtils,
+    debug_messenger: DebugUtilsMessengerEXT,
+    device: Device,
+    debug_utils: PotatoDebugUtils,
+    allocator: Allocator,
+    compute_queue: Queue,
+    command_pool: CommandPool,
+    //Keyed by shader path and storage buffer count
+    compute_pipelines: HashMap<(String, usize), ComputePipeline>,
+    //Storage buffers not yet destroyed by the caller, the rest are destroyed on drop
+    storage_buffers: HashMap<Buffer, Allocation>,
+}
+
+impl ComputeApiObjects {
+    pub fn init() -> Result<ComputeApiObjects, PotatoError> {
+        debug!("Init entry");
+        let entry = Entry::linked();
+        debug!("Init instance");
+        let validation = validation_config_from_env();
+        let (instance, instance_capabilities) = create_instance(&entry, false, &validation)?;
+        debug!("Init debug utils");
+        let (debug_utils_loader, debug_messenger) =
+            setup_debug_utils(&entry, &instance, &validation, &instance_capabilities)?;
+        debug!("Init physical device");
+        let device_requirements = DeviceRequirements::new();
+        let physical_device = select_compute_physical_device(
+            &instance,
+            device_selector_from_env().as_ref(),
+            &device_requirements,
+        )?;
+        describe_device(&instance, physical_device);
+
+        debug!("Init device features");
+        let enabled_features =
+            negotiate_device_features(&instance, physical_device, &device_requirements)?;
+        debug!("Init logical device");
+        let (device, queue_family) =
+            create_compute_logical_device(&instance, physical_device, &enabled_features)?;
+        let debug_utils =
+            PotatoDebugUtils::new(&debug_utils_loader, &device, &instance_capabilities);
+        let compute_family = queue_family
+            .compute_family
+            .ok_or(PotatoError::NoSuitableQueueFamily)? as u32;
+        debug!("Init allocator");
+        let allocator = Allocator::new(&instance, physical_device, &device);
+        debug!("Init compute queue");
+        let compute_queue = unsafe { device.get_device_queue(compute_family, 0) };
+        debug_utils.name_object(compute_queue, "Compute queue");
+        debug!("Init command pool");
+        let command_pool = create_command_pool(
+            &device,
+            &debug_utils,
+            compute_family,
+            CommandPoolCreateFlags::empty(),
+            "Compute command pool",
+        )?;
+
+        Ok(ComputeApiObjects {
+            _entry: entry,
+            instance,
+            debug_utils_loader,
+            debug_messenger,
+            device,
+            debug_utils,
+            allocator,
+            compute_queue,
+            command_pool,
+            compute_pipelines: HashMap::new(),
+            storage_buffers: HashMap::new(),
+        })
+    }
+
+    //Storage buffers are host visible so results can be read back without a staging copy
+    pub fn create_storage_buffer(
+        &mut self,
+        size: DeviceSize,
+    ) -> Result<StorageBuffer, PotatoError> {
+        let (buffer, allocation) = create_buffer(
+            &self.device,
+            &self.debug_utils,
+            &mut self.allocator,
+            size,
+            BufferUsageFlags::STORAGE_BUFFER,
+            MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT,
+            &[],
+            "Storage buffer",
+        )?;
+        self.storage_buffers.insert(buffer, allocation);
+
+        Ok(StorageBuffer {
+            buffer,
+            allocation,
+            size,
+        })
+    }
+
+    pub fn write_storage_buffer<T: Pod>(
+        &self,
+        storage_buffer: &StorageBuffer,
+        data: &[T],
+    ) -> Result<(), PotatoError> {
+        storage_buffer.allocation.write(data)
+    }
+
+    pub fn read_storage_buffer<T: Pod>(
+        &self,
+        storage_buffer: &StorageBuffer,
+    ) -> Result<Vec<T>, PotatoError> {
+        let count = storage_buffer.size as usize / std::mem::size_of::<T>();
+        storage_buffer.allocation.read(count)
+    }
+
+    //Runs the shader once and blocks until its writes are visible to the host
+    //The pipeline is built on the first dispatch of a shader and reused afterwards
+    pub fn dispatch(
+        &mut self,
+        shader_path: &str,
+        storage_buffers: &[&StorageBuffer],
+        group_count: [u32; 3],
+    ) -> Result<(), PotatoError> {
+        let buffers: Vec<Buffer> = storage_buffers.iter().map(|x| x.buffer).collect();
+
+        let compute_pipeline = self.compute_pipeline(shader_path, buffers.len())?;
+        let descriptor_pool =
+            create_compute_descriptor_pool(&self.device, &self.debug_utils, buffers.len())?;
+        let result = self.submit_dispatch(
+            shader_path,
+            compute_pipeline,
+            descriptor_pool,
+            &buffers,
+            group_count,
+        );
+        unsafe {
+            self.device.destroy_descriptor_pool(descriptor_pool, None);
+        }
+
+        result
+    }
+
+    fn compute_pipeline(
+        &mut self,
+        shader_path: &str,
+        storage_buffer_count: usize,
+    ) -> Result<ComputePipeline, PotatoError> {
+        let key = (shader_path.to_string(), storage_buffer_count);
+        if let Some(compute_pipeline) = self.compute_pipelines.get(&key) {
+            return Ok(*compute_pipeline);
+        }
+
+        debug!("Init compute pipeline {}", shader_path);
+        let set_layout = create_compute_descriptor_set_layout(
+            &self.device,
+            &self.debug_utils,
+            storage_buffer_count,
+        )?;
+        let (pipeline, pipeline_layout) =
+            match create_compute_pipeline(&self.device, &self.debug_utils, shader_path, set_layout)
+            {
+                Ok(x) => x,
+                Err(e) => {
+                    unsafe { self.device.destroy_descriptor_set_layout(set_layout, None) };
+                    return Err(e);
+                }
+            };
+
+        let compute_pipeline = ComputePipeline {
+            set_layout,
+            pipeline,
+            pipeline_layout,
+        };
+        self.compute_pipelines.insert(key, compute_pipeline);
+        Ok(compute_pipeline)
+    }
+
+    fn submit_dispatch(
+        &self,
+        shader_path: &str,
+        compute_pipeline: ComputePipeline,
+        descriptor_pool: DescriptorPool,
+        buffers: &[Buffer],
+        group_count: [u32; 3],
+    ) -> Result<(), PotatoError> {
+        let descriptor_set = create_compute_descriptor_set(
+            &self.device,
+            &self.debug_utils,
+            descriptor_pool,
+            compute_pipeline.set_layout,
+            buffers,
+        )?;
+
+        let host_read_barriers = [MemoryBarrier {
+            s_type: StructureType::MEMORY_BARRIER,
+            p_next: std::ptr::null(),
+            src_access_mask: AccessFlags::SHADER_WRITE,
+            dst_access_mask: AccessFlags::HOST_READ,
+        }];
+
+        let command_buffer = begin_single_time_commands(&self.device, self.command_pool)?;
+        unsafe {
+            let _dispatch_label = self
+                .debug_utils
+                .begin_command_label(command_buffer, &format!("Dispatch {}", shader_path));
+            self.device.cmd_bind_pipeline(
+                command_buffer,
+                PipelineBindPoint::COMPUTE,
+                compute_pipeline.pipeline,
+            );
+            self.device.cmd_bind_descriptor_sets(
+                command_buffer,
+                PipelineBindPoint::COMPUTE,
+                compute_pipeline.pipeline_layout,
+                0,
+                &[descriptor_set],
+                &[],
+            );
+            self.device.cmd_dispatch(
+                command_buffer,
+                group_count[0],
+                group_count[1],
+                group_count[2],
+            );
+            self.device.cmd_pipeline_barrier(
+                command_buffer,
+                PipelineStageFlags::COMPUTE_SHADER,
+                PipelineStageFlags::HOST,
+                DependencyFlags::empty(),
+                &host_read_barriers,
+                &[],
+                &[],
+            );
+        }
+
+        end_single_time_commands(
+            &self.device,
+            self.command_pool,
+            self.compute_queue,
+            None,
+            command_buffer,
+        )
+    }
+
+    //Buffers of another ComputeApiObjects are left alone
+    pub fn destroy_storage_buffer(&mut self, storage_buffer: StorageBuffer) {
+        if let Some(allocation) = self.storage_buffers.remove(&storage_buffer.buffer) {
+            destroy_buffer(
+                &self.device,
+                &mut self.allocator,
+                storage_buffer.buffer,
+                allocation,
+            );
+        }
+    }
+
+    pub fn memory_stats(&self) -> Vec<HeapStats> {
+        self.allocator.stats()
+    }
+}
+
+impl Drop for ComputeApiObjects {
+    fn drop(&mut self) {
+        unsafe {
+            if let Err(e) = self.device.device_wait_idle() {
+                error!("Failed to wait device idle: {}", e);
+            }
+            for compute_pipeline in self.compute_pipelines.values() {
+                self.device
+                    .destroy_pipeline(compute_pipeline.pipeline, None);
+                self.device
+                    .destroy_pipeline_layout(compute_pipeline.pipeline_layout, None);
+                self.device
+                    .destroy_descriptor_set_layout(compute_pipeline.set_layout, None);
+            }
+            for (buffer, allocation) in std::mem::take(&mut self.storage_buffers) {
+                destroy_buffer(&self.device, &mut self.allocator, buffer, allocation);
+            }
+            self.allocator.destroy();
+            self.device.destroy_command_pool(self.command_pool, None);
+            self.device.destroy_device(None);
+            if self.debug_messenger != DebugUtilsMessengerEXT::null() {
+                self.debug_utils_loader
+                    .destroy_debug_utils_messenger(self.debug_messenger, None);
+            }
+            self.instance.destroy_instance(None);
+        }
+    }
+}
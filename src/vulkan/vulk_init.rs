@@ -1,30 +1,50 @@
-use super::command_pool::{create_command_buffers, create_command_pool};
-use super::constants::{INDICES_DATA, MAX_FRAMES_IN_FLIGHT, VALIDATION, VERTICES_DATA};
+use super::allocator::{Allocation, Allocator};
+use super::buffer::destroy_buffer;
+use super::command_pool::create_command_pool;
+use super::config::GraphicsConfig;
+use super::constants::{DEVICE_EXTENSTIONS, MSAA_SAMPLES};
+use super::debug_utils::PotatoDebugUtils;
+use super::depth::{create_depth_images, find_depth_format};
 use super::device::create_logical_device;
+use super::device_features::{negotiate_device_features, DeviceFeature, EnabledDeviceFeatures};
+use super::frame::{
+    create_frame_commands, record_default_frame, FrameCommands, FrameContext, RecordCallback,
+};
+use super::frame_pacing::{create_semaphore, create_semaphores, destroy_semaphores, FramePacer};
 use super::framebuffers::create_framebuffers;
 use super::graphics_pipeline::create_graphics_pipeline;
+use super::image::AttachmentImage;
 use super::instance::create_instance;
+use super::mesh::{Mesh, MeshBuffers};
+use super::msaa::{
+    clamp_sample_count, create_color_images, max_usable_sample_count, next_sample_count,
+};
 use super::physical_device::{describe_device, select_physical_device};
+use super::present_mode::PresentPolicy;
 use super::queue_family::QueueFamily;
+use super::recording_threads::RecordingThreads;
 use super::render_pass::create_render_pass;
 use super::surface::{create_surface, PotatoSurface};
-use super::swapchain::{create_swapchain, PotatoSwapChain};
-use super::sync_objects::create_sync_objects;
-use super::vertex::{create_index_buffer, create_vertex_buffer};
+use super::swapchain::{create_swapchain, destroy_swapchain, PotatoSwapChain};
+use super::texture::{PotatoTexture, SamplerSettings};
+use super::timeline::{is_timeline_enabled, Retirements};
 use super::vulk_validation_layers::setup_debug_utils;
 use super::UniformBufferObject::{
     create_descriptor_pool, create_descriptor_set_layout, create_descriptor_sets,
     create_uniform_buffers, update_uniform_buffer,
 };
+use crate::error::PotatoError;
+use crate::io::scene::TextureImage;
 use ash::extensions::ext::DebugUtils;
 use ash::vk::{
-    Buffer, BufferUsageFlags, CommandBuffer, CommandPool, DebugUtilsMessengerEXT, DescriptorPool,
-    DescriptorSet, DescriptorSetLayout, DeviceMemory, Fence, Framebuffer, PhysicalDevice, Pipeline,
-    PipelineLayout, PipelineStageFlags, PresentInfoKHR, Queue, RenderPass, Result, Semaphore,
-    StructureType, SubmitInfo,
+    Buffer, CommandPool, CommandPoolCreateFlags, DebugUtilsMessengerEXT, DescriptorPool,
+    DescriptorSet, DescriptorSetLayout, Fence, Format, Framebuffer, ImageLayout, ImageView,
+    PhysicalDevice, Pipeline, PipelineLayout, PipelineStageFlags, PolygonMode, PresentInfoKHR,
+    Queue, RenderPass, Result as VkResult, SampleCountFlags, Semaphore, StructureType,
+    SwapchainKHR,
 };
 use ash::{Device, Entry, Instance};
-use log::debug;
+use log::{debug, error, info, warn};
 use std::collections::HashMap;
 use winit::{
     dpi::LogicalSize,
@@ -34,33 +54,50 @@ use winit::{
 };
 
 pub struct VulkanApiObjects {
-    windows: Option<HashMap<WindowId, Window>>,
-    entry: Entry,
+    windows: HashMap<WindowId, Window>,
+    _entry: Entry,
     instance: Instance,
-    surface: Option<PotatoSurface>,
+    surface: PotatoSurface,
     queue_family: QueueFamily,
-    debug_utils_loader: Option<DebugUtils>,
-    debug_messenger: Option<DebugUtilsMessengerEXT>,
+    debug_utils_loader: DebugUtils,
+    debug_messenger: DebugUtilsMessengerEXT,
     physical_device: PhysicalDevice,
     device: Device,
+    debug_utils: PotatoDebugUtils,
+    enabled_features: EnabledDeviceFeatures,
+    allocator: Allocator,
     graphics_queue: Queue,
-    swapchain: Option<PotatoSwapChain>,
+    present_queue: Queue,
+    swapchain: PotatoSwapChain,
+    present_policy: PresentPolicy,
+    depth_format: Format,
+    depth_images: Vec<AttachmentImage>,
+    max_samples: SampleCountFlags,
+    samples: SampleCountFlags,
+    color_images: Vec<AttachmentImage>,
+    polygon_mode: PolygonMode,
     pipeline_layout: PipelineLayout,
     render_pass: RenderPass,
     graphics_pipeline: Pipeline,
-    swapchain_framebuffers: Option<Vec<Framebuffer>>,
+    swapchain_framebuffers: Vec<Framebuffer>,
     command_pool: CommandPool,
-    command_buffers: Vec<CommandBuffer>,
-    image_available_semaphores: Option<Vec<Semaphore>>,
-    render_finished_semaphores: Option<Vec<Semaphore>>,
-    in_flight_fences: Vec<Fence>,
-    current_frame: usize,
-    vertex_buffer: Buffer,
-    vertex_buffer_memory: DeviceMemory,
-    index_buffer: Buffer,
-    index_buffer_memory: DeviceMemory,
+    transfer_command_pool: CommandPool,
+    frame_commands: Vec<FrameCommands>,
+    recording_threads: RecordingThreads,
+    record_callback: RecordCallback,
+    frame_pacer: FramePacer,
+    //Replaced while frames in flight may still use them, destroyed once those frames have completed
+    retirements: Retirements<Retired>,
+    //One per frame in flight, signaled by the acquire of the image the frame renders to
+    image_available_semaphores: Vec<Semaphore>,
+    //One per swapchain image, presentation waits on the semaphore of the image it shows
+    render_finished_semaphores: Vec<Semaphore>,
+    is_resized: bool,
+    is_paused: bool,
+    mesh_buffers: MeshBuffers,
+    texture: PotatoTexture,
     uniform_buffers: Vec<Buffer>,
-    uniform_buffers_memory: Vec<DeviceMemory>,
+    uniform_buffers_allocations: Vec<Allocation>,
     ubo_layout: DescriptorSetLayout,
     descriptor_pool: DescriptorPool,
     descriptor_sets: Vec<DescriptorSet>,
@@ -68,246 +105,385 @@ pub struct VulkanApiObjects {
 
 impl VulkanApiObjects {
     //TODO Does not currently work in the lib and as referenced outside the lib
-    pub fn init(event_loop: &EventLoop<()>) -> VulkanApiObjects {
+    pub fn init(
+        event_loop: &EventLoop<()>,
+        mesh: &Mesh,
+        texture_image: Option<&TextureImage>,
+        config: &GraphicsConfig,
+    ) -> Result<VulkanApiObjects, PotatoError> {
         debug!("Init window");
-        let window = VulkanApiObjects::init_window(&event_loop, "origin");
+        let window = VulkanApiObjects::init_window(event_loop, "origin")?;
         debug!("Init entry");
         let entry = Entry::linked();
         debug!("Init instance");
-        let instance = create_instance(&entry);
+        let (instance, instance_capabilities) = create_instance(&entry, true, &config.validation)?;
         debug!("Init debug utils");
-        let (debug_utils_loader, debug_messenger) = setup_debug_utils(&entry, &instance);
+        let (debug_utils_loader, debug_messenger) = setup_debug_utils(
+            &entry,
+            &instance,
+            &config.validation,
+            &instance_capabilities,
+        )?;
         debug!("Init surface");
-        let potato_surface = create_surface(&entry, &instance, &window);
+        let potato_surface = create_surface(&entry, &instance, &window)?;
         debug!("Init physical device");
-        let physical_device = select_physical_device(&instance, &potato_surface);
-        let physical_device_memory_properties =
-            unsafe { instance.get_physical_device_memory_properties(physical_device) };
+        let mut device_requirements = config.device_requirements.clone();
+        for name in DEVICE_EXTENSTIONS.names {
+            device_requirements = device_requirements.require_extension(name);
+        }
+        let physical_device = select_physical_device(
+            &instance,
+            &potato_surface,
+            config.device.as_ref(),
+            &device_requirements,
+        )?;
         describe_device(&instance, physical_device);
 
+        debug!("Init device features");
+        let enabled_features =
+            negotiate_device_features(&instance, physical_device, &device_requirements)?;
         debug!("Init logical device");
-        let (logical_device, queue_family) =
-            create_logical_device(&instance, physical_device, &potato_surface);
+        let (logical_device, queue_family) = create_logical_device(
+            &instance,
+            physical_device,
+            &potato_surface,
+            &enabled_features,
+        )?;
+        let debug_utils =
+            PotatoDebugUtils::new(&debug_utils_loader, &logical_device, &instance_capabilities);
+        debug!("Init allocator");
+        let mut allocator = Allocator::new(&instance, physical_device, &logical_device);
         debug!("Init swapchain");
         let swapchain = create_swapchain(
             &instance,
             &logical_device,
+            &debug_utils,
             physical_device,
             &potato_surface,
             &queue_family,
-        );
+            config.present_policy,
+            SwapchainKHR::null(),
+        )?;
+        let graphics_family = queue_family
+            .graphics_family
+            .ok_or(PotatoError::NoSuitableQueueFamily)? as u32;
+        let present_family = queue_family
+            .present_family
+            .ok_or(PotatoError::NoSuitableQueueFamily)? as u32;
         debug!("Init graphics queue");
-        let graphics_queue = unsafe {
-            logical_device.get_device_queue(queue_family.graphics_family.unwrap() as u32, 0)
+        let graphics_queue = unsafe { logical_device.get_device_queue(graphics_family, 0) };
+        debug_utils.name_object(graphics_queue, "Graphics queue");
+        debug!("Init present queue");
+        let present_queue = unsafe { logical_device.get_device_queue(present_family, 0) };
+        if present_queue != graphics_queue {
+            debug_utils.name_object(present_queue, "Present queue");
+        }
+        let transfer_family = queue_family
+            .transfer_family
+            .ok_or(PotatoError::NoSuitableQueueFamily)? as u32;
+        debug!("Init transfer queue");
+        let transfer_queue = unsafe { logical_device.get_device_queue(transfer_family, 0) };
+        if transfer_queue != graphics_queue {
+            debug_utils.name_object(transfer_queue, "Transfer queue");
+        }
+        debug!("Init multisampling");
+        let max_samples = max_usable_sample_count(&instance, physical_device);
+        let samples = clamp_sample_count(MSAA_SAMPLES, max_samples);
+        debug!(
+            "Using {:?} samples, device supports up to {:?}",
+            samples, max_samples
+        );
+        let color_images = if samples == SampleCountFlags::TYPE_1 {
+            vec![]
+        } else {
+            create_color_images(
+                &logical_device,
+                &debug_utils,
+                &mut allocator,
+                swapchain.swapchain_format,
+                swapchain.swapchain_extent,
+                samples,
+                swapchain.swapchain_images.len(),
+            )?
         };
+        debug!("Init depth images");
+        let depth_format = find_depth_format(&instance, physical_device)?;
+        let depth_images = create_depth_images(
+            &logical_device,
+            &debug_utils,
+            &mut allocator,
+            depth_format,
+            swapchain.swapchain_extent,
+            samples,
+            swapchain.swapchain_images.len(),
+        )?;
         debug!("Init render pass");
-        let render_pass = create_render_pass(&logical_device, swapchain.swapchain_format);
+        let render_pass = create_render_pass(
+            &logical_device,
+            &debug_utils,
+            swapchain.swapchain_format,
+            depth_format,
+            samples,
+            ImageLayout::PRESENT_SRC_KHR,
+        )?;
         debug!("Init descriptor layout");
-        let ubo_layout = create_descriptor_set_layout(&logical_device);
+        let ubo_layout = create_descriptor_set_layout(&logical_device, &debug_utils)?;
         debug!("Init graphics pipeline");
         let (graphics_pipeline, pipeline_layout) = create_graphics_pipeline(
             &logical_device,
+            &debug_utils,
             render_pass,
-            swapchain.swapchain_extent,
             ubo_layout,
-        );
+            samples,
+            PolygonMode::FILL,
+        )?;
         debug!("Init framebuffers");
+        let depth_image_views: Vec<ImageView> = depth_images.iter().map(|x| x.image_view).collect();
+        let color_image_views: Vec<ImageView> = color_images.iter().map(|x| x.image_view).collect();
         let swapchain_framebuffers = create_framebuffers(
             &logical_device,
+            &debug_utils,
             render_pass,
             &swapchain.swapchain_image_views,
+            &depth_image_views,
+            &color_image_views,
             &swapchain.swapchain_extent,
-        );
+        )?;
         debug!("Init command pool");
-        let command_pool = create_command_pool(&logical_device, &queue_family);
-        debug!("Init vertex buffer");
-        let (vertex_buffer, vertex_buffer_memory) = create_vertex_buffer(
-            &instance,
+        let command_pool = create_command_pool(
             &logical_device,
-            physical_device,
-            command_pool,
-            graphics_queue,
-            BufferUsageFlags::TRANSFER_DST | BufferUsageFlags::VERTEX_BUFFER,
+            &debug_utils,
+            graphics_family,
+            CommandPoolCreateFlags::empty(),
+            "Graphics command pool",
+        )?;
+        debug!("Init transfer command pool");
+        let transfer_command_pool = create_command_pool(
+            &logical_device,
+            &debug_utils,
+            transfer_family,
+            CommandPoolCreateFlags::empty(),
+            "Transfer command pool",
+        )?;
+        let frames_in_flight = config.frames_in_flight.max(1);
+        let use_timeline = is_timeline_enabled(&enabled_features);
+        debug!(
+            "{} frames in flight for {} swapchain images, timeline semaphore {}",
+            frames_in_flight,
+            swapchain.swapchain_images.len(),
+            use_timeline
         );
-        debug!("Init index buffer");
-        let (index_buffer, index_buffer_memory) = create_index_buffer(
-            &instance,
+        debug!("Init frame pacing");
+        let frame_pacer = FramePacer::new(
             &logical_device,
+            &debug_utils,
+            frames_in_flight,
+            swapchain.swapchain_images.len(),
+            use_timeline,
+        )?;
+        debug!("Init mesh buffers");
+        //Copies run on the transfer queue, the buffers are shared with graphics when the families differ
+        let mut mesh_families = vec![graphics_family, transfer_family];
+        mesh_families.dedup();
+        let mesh_buffers = mesh.upload(
+            &logical_device,
+            &debug_utils,
+            &mut allocator,
+            transfer_command_pool,
+            transfer_queue,
+            frame_pacer.timeline(),
+            &mesh_families,
+            "Mesh",
+        )?;
+        debug!("Init texture");
+        //Mipmap blits need a graphics queue so textures stay on it
+        let texture = PotatoTexture::upload(
+            &instance,
             physical_device,
+            &logical_device,
+            &debug_utils,
+            &mut allocator,
             command_pool,
             graphics_queue,
-            BufferUsageFlags::TRANSFER_DST | BufferUsageFlags::INDEX_BUFFER,
-        );
+            frame_pacer.timeline(),
+            texture_image.unwrap_or(&TextureImage::solid([255, 255, 255, 255])),
+            &enabled_features,
+            &SamplerSettings::for_features(&enabled_features),
+        )?;
         debug!("Init ubo buffer");
-        let (uniform_buffers, uniform_buffers_memory) = create_uniform_buffers(
+        let (uniform_buffers, uniform_buffers_allocations) = create_uniform_buffers(
             &logical_device,
-            &physical_device_memory_properties,
-            swapchain.swapchain_images.len(),
-        );
+            &debug_utils,
+            &mut allocator,
+            frames_in_flight,
+        )?;
         debug!("Init descriptor pool");
         let descriptor_pool =
-            create_descriptor_pool(&logical_device, swapchain.swapchain_images.len());
+            create_descriptor_pool(&logical_device, &debug_utils, frames_in_flight)?;
         debug!("Init descriptor sets");
         let descriptor_sets = create_descriptor_sets(
             &logical_device,
+            &debug_utils,
             descriptor_pool,
             ubo_layout,
             &uniform_buffers,
-            swapchain.swapchain_images.len(),
-        );
-        debug!("Init command buffers");
-        let command_buffers = create_command_buffers(
+            &texture,
+            frames_in_flight,
+        )?;
+        debug!("Init frame command buffers");
+        let frame_commands = create_frame_commands(
             &logical_device,
-            command_pool,
-            graphics_pipeline,
-            &swapchain_framebuffers,
-            render_pass,
-            swapchain.swapchain_extent,
-            vertex_buffer,
-            index_buffer,
-            pipeline_layout,
-            &descriptor_sets,
-        );
-        debug!("Init sync objects");
-        let sync_objects = create_sync_objects(&logical_device);
+            &debug_utils,
+            graphics_family,
+            frames_in_flight,
+            config.recording_threads,
+        )?;
+        debug!("Init recording threads");
+        let recording_threads = RecordingThreads::new(config.recording_threads)?;
+        debug!("Init semaphores");
+        let image_available_semaphores = create_semaphores(
+            &logical_device,
+            &debug_utils,
+            frames_in_flight,
+            "Image available semaphore",
+        )?;
+        let render_finished_semaphores = create_semaphores(
+            &logical_device,
+            &debug_utils,
+            swapchain.swapchain_images.len(),
+            "Render finished semaphore",
+        )?;
+        allocator.log_stats();
 
         let mut windows = HashMap::new();
         windows.insert(window.id(), window);
 
-        VulkanApiObjects {
-            windows: Some(windows),
-            entry: entry,
+        Ok(VulkanApiObjects {
+            windows,
+            _entry: entry,
             instance,
-            surface: Some(potato_surface),
+            surface: potato_surface,
             queue_family,
-            debug_utils_loader: Some(debug_utils_loader),
-            debug_messenger: Some(debug_messenger),
+            debug_utils_loader,
+            debug_messenger,
             physical_device,
             device: logical_device,
+            debug_utils,
+            enabled_features,
+            allocator,
             graphics_queue,
-            swapchain: Some(swapchain),
+            present_queue,
+            swapchain,
+            present_policy: config.present_policy,
+            depth_format,
+            depth_images,
+            max_samples,
+            samples,
+            color_images,
+            polygon_mode: PolygonMode::FILL,
             pipeline_layout,
             render_pass,
             graphics_pipeline,
-            swapchain_framebuffers: Some(swapchain_framebuffers),
+            swapchain_framebuffers,
             command_pool,
-            command_buffers,
-            image_available_semaphores: Some(sync_objects.image_available_semaphores),
-            render_finished_semaphores: Some(sync_objects.render_finished_semaphores),
-            in_flight_fences: sync_objects.inflight_fences,
-            current_frame: 0,
-            vertex_buffer,
-            vertex_buffer_memory,
-            index_buffer,
-            index_buffer_memory,
+            transfer_command_pool,
+            frame_commands,
+            recording_threads,
+            record_callback: Box::new(record_default_frame),
+            frame_pacer,
+            retirements: Retirements::new(),
+            image_available_semaphores,
+            render_finished_semaphores,
+            is_resized: false,
+            is_paused: false,
+            mesh_buffers,
+            texture,
             uniform_buffers,
-            uniform_buffers_memory,
+            uniform_buffers_allocations,
             ubo_layout,
             descriptor_pool,
             descriptor_sets,
-        }
+        })
     }
 
-    pub fn init_compute() -> VulkanApiObjects {
-        debug!("Linking entry");
-        let entry = Entry::linked();
-        debug!("Creating instance");
-        let instance = create_instance(&entry);
-
-        VulkanApiObjects {
-            windows: None,
-            entry: entry,
-            instance: instance,
-            surface: None,
-            queue_family: None,
-            debug_utils_loader: None,
-            debug_messenger: None,
-            physical_device: None,
-            device: None,
-            graphics_queue: None,
-            swapchain: None,
-            pipeline_layout: None,
-            render_pass: None,
-            graphics_pipeline: None,
-            swapchain_framebuffers: None,
-            command_pool: None,
-            command_buffers: None,
-            image_available_semaphores: None,
-            render_finished_semaphores: None,
-            in_flight_fences: None,
-            current_frame: None,
-            vertex_buffer: None,
-            vertex_buffer_memory: None,
-            index_buffer: None,
-            index_buffer_memory: None,
-            uniform_buffers: None,
-            uniform_buffers_memory: None,
-            ubo_layout: None,
-            descriptor_pool: None,
-            descriptor_sets: None,
+    pub fn draw(&mut self, delta_time: f32) -> Result<(), PotatoError> {
+        if self.is_resized {
+            self.is_resized = false;
+            self.recreate_swapchain()?;
+        }
+        if self.is_paused {
+            return Ok(());
         }
-    }
 
-    pub fn draw(&mut self, delta_time: f32) {
-        let wait_fences = [self.in_flight_fences[self.current_frame]];
+        let current_frame = self.frame_pacer.current_frame();
+        self.frame_pacer.wait_for_current_frame(&self.device)?;
+        self.destroy_completed_retirements()?;
         let (image_index, _is_sub_optimal) = unsafe {
-            self.device
-                .wait_for_fences(&wait_fences, true, std::u64::MAX)
-                .expect("Failed to wait for Fence!");
-
             let result = self.swapchain.swapchain_loader.acquire_next_image(
                 self.swapchain.swapchain,
-                std::u64::MAX,
-                self.image_available_semaphores[self.current_frame],
+                u64::MAX,
+                self.image_available_semaphores[current_frame],
                 Fence::null(),
             );
             match result {
                 Ok(image_index) => image_index,
-                Err(vk_result) => match vk_result {
-                    Result::ERROR_OUT_OF_DATE_KHR => {
-                        self.recreate_swapchain();
-                        return;
-                    }
-                    _ => panic!("Failed to acquire swap chain image"),
-                },
+                Err(VkResult::ERROR_OUT_OF_DATE_KHR) => {
+                    return self.recreate_swapchain();
+                }
+                Err(vk_result) => return Err(vk_result.into()),
             }
         };
 
+        //Another frame may still render to the acquired image when there are more frames in flight than images
+        self.frame_pacer
+            .claim_image(&self.device, image_index as usize)?;
+
+        //The fence wait above means the previous submission of this frame has finished with its uniform buffer and commands
         update_uniform_buffer(
-            &self.swapchain,
-            &self.device,
-            image_index as usize,
+            self.swapchain.swapchain_extent,
+            current_frame,
             delta_time,
-            &self.uniform_buffers_memory,
-        );
+            &self.uniform_buffers_allocations,
+        )?;
 
-        let wait_semaphores = [self.image_available_semaphores[self.current_frame]];
-        let wait_stages = [PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT];
-        let signal_semaphores = [self.render_finished_semaphores[self.current_frame]];
+        let command_buffer = self.frame_commands[current_frame].begin(&self.device)?;
+        let frame = FrameContext {
+            device: &self.device,
+            debug_utils: &self.debug_utils,
+            command_buffer,
+            secondary_command_buffers: &self.frame_commands[current_frame]
+                .secondary_command_buffers,
+            recording_threads: &self.recording_threads,
+            frame_index: current_frame,
+            image_index: image_index as usize,
+            delta_time,
+            extent: self.swapchain.swapchain_extent,
+            render_pass: self.render_pass,
+            framebuffer: self.swapchain_framebuffers[image_index as usize],
+            graphics_pipeline: self.graphics_pipeline,
+            pipeline_layout: self.pipeline_layout,
+            descriptor_set: self.descriptor_sets[current_frame],
+            mesh: &self.mesh_buffers,
+        };
+        (self.record_callback)(&frame)?;
+        unsafe { self.device.end_command_buffer(command_buffer)? };
 
-        let submit_infos = [SubmitInfo {
-            s_type: StructureType::SUBMIT_INFO,
-            p_next: std::ptr::null(),
-            wait_semaphore_count: wait_semaphores.len() as u32,
-            p_wait_semaphores: wait_semaphores.as_ptr(),
-            p_wait_dst_stage_mask: wait_stages.as_ptr(),
-            command_buffer_count: 1,
-            p_command_buffers: &self.command_buffers[image_index as usize],
-            signal_semaphore_count: signal_semaphores.len() as u32,
-            p_signal_semaphores: signal_semaphores.as_ptr(),
-        }];
-        unsafe {
-            self.device
-                .reset_fences(&wait_fences)
-                .expect("Failed to reset Fence!");
+        let wait_semaphores = [self.image_available_semaphores[current_frame]];
+        let wait_stages = [PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT];
+        let signal_semaphores = [self.render_finished_semaphores[image_index as usize]];
 
-            self.device
-                .queue_submit(
-                    self.graphics_queue,
-                    &submit_infos,
-                    self.in_flight_fences[self.current_frame],
-                )
-                .expect("Failed to execute queue submit.");
+        {
+            let _submit_label = self
+                .debug_utils
+                .begin_queue_label(self.graphics_queue, &format!("Frame {}", image_index));
+            self.frame_pacer.submit(
+                &self.device,
+                self.graphics_queue,
+                command_buffer,
+                &wait_semaphores,
+                &wait_stages,
+                &signal_semaphores,
+            )?;
         }
 
         let swapchains = [self.swapchain.swapchain];
@@ -326,95 +502,275 @@ impl VulkanApiObjects {
         let result = unsafe {
             self.swapchain
                 .swapchain_loader
-                .queue_present(self.graphics_queue, &present_info)
+                .queue_present(self.present_queue, &present_info)
         };
 
         let is_resized = match result {
             Ok(_) => false,
-            Err(vk_result) => match vk_result {
-                Result::ERROR_OUT_OF_DATE_KHR | Result::SUBOPTIMAL_KHR => true,
-                _ => panic!("Failed to execute queue present"),
-            },
+            Err(VkResult::ERROR_OUT_OF_DATE_KHR | VkResult::SUBOPTIMAL_KHR) => true,
+            Err(vk_result) => return Err(vk_result.into()),
         };
 
         if is_resized {
-            self.recreate_swapchain();
+            self.recreate_swapchain()?;
         }
 
-        self.current_frame = (self.current_frame + 1) % MAX_FRAMES_IN_FLIGHT;
+        self.frame_pacer.advance();
+        Ok(())
     }
 
-    fn recreate_swapchain(&mut self) {
-        unsafe {
-            self.device
-                .device_wait_idle()
-                .expect("Failed to wait on device")
+    //Only what depends on the swapchain is rebuilt, the render pass and pipeline survive unless the format changed
+    fn recreate_swapchain(&mut self) -> Result<(), PotatoError> {
+        let capabilities = unsafe {
+            self.surface
+                .surface_loader
+                .get_physical_device_surface_capabilities(
+                    self.physical_device,
+                    self.surface.surface,
+                )?
         };
-        self.cleanup_swapchain();
+        if capabilities.current_extent.width == 0 || capabilities.current_extent.height == 0 {
+            debug!("Surface has no area, pausing rendering");
+            self.is_paused = true;
+            return Ok(());
+        }
+        self.is_paused = false;
+
+        let retire_value = self.frame_pacer.submitted_value();
+        self.retire_render_targets(retire_value);
 
-        self.swapchain = create_swapchain(
+        let swapchain = create_swapchain(
             &self.instance,
             &self.device,
+            &self.debug_utils,
             self.physical_device,
             &self.surface,
             &self.queue_family,
-        );
-        self.render_pass = create_render_pass(&self.device, self.swapchain.swapchain_format);
+            self.present_policy,
+            self.swapchain.swapchain,
+        )?;
+        let old_swapchain = std::mem::replace(&mut self.swapchain, swapchain);
+        let format_changed = self.swapchain.swapchain_format != old_swapchain.swapchain_format;
+        self.retirements
+            .retire(retire_value, Retired::Swapchain(old_swapchain));
+
+        if format_changed {
+            debug!(
+                "Swapchain format changed to {:?}, rebuilding pipeline",
+                self.swapchain.swapchain_format
+            );
+            self.retire_pipeline(retire_value);
+            self.create_pipeline()?;
+        }
+        self.create_render_targets()?;
+        self.create_image_sync()
+    }
+
+    //Presentation of the old swapchain may still wait on its semaphores, so they are kept and only added to
+    fn create_image_sync(&mut self) -> Result<(), PotatoError> {
+        let image_count = self.swapchain.swapchain_images.len();
+        for i in self.render_finished_semaphores.len()..image_count {
+            let semaphore = create_semaphore(
+                &self.device,
+                &self.debug_utils,
+                &format!("Render finished semaphore {}", i),
+            )?;
+            self.render_finished_semaphores.push(semaphore);
+        }
+        self.frame_pacer.reset_images(image_count);
+        Ok(())
+    }
+
+    //In flight frames are the only users of retired resources, no need to idle the whole device
+    fn destroy_completed_retirements(&mut self) -> Result<(), PotatoError> {
+        let completed_value = self.frame_pacer.completed_value(&self.device)?;
+        for retired in self.retirements.take_completed(completed_value) {
+            retired.destroy(&self.device, &mut self.allocator);
+        }
+        Ok(())
+    }
+
+    fn create_pipeline(&mut self) -> Result<(), PotatoError> {
+        self.render_pass = create_render_pass(
+            &self.device,
+            &self.debug_utils,
+            self.swapchain.swapchain_format,
+            self.depth_format,
+            self.samples,
+            ImageLayout::PRESENT_SRC_KHR,
+        )?;
         let (graphics_pipeline, pipeline_layout) = create_graphics_pipeline(
             &self.device,
+            &self.debug_utils,
             self.render_pass,
-            self.swapchain.swapchain_extent,
             self.ubo_layout,
-        );
+            self.samples,
+            self.polygon_mode,
+        )?;
         self.graphics_pipeline = graphics_pipeline;
         self.pipeline_layout = pipeline_layout;
+        Ok(())
+    }
+
+    //Handles are taken out so a failed rebuild never destroys them twice
+    fn take_pipeline(&mut self) -> Retired {
+        Retired::Pipeline {
+            graphics_pipeline: std::mem::replace(&mut self.graphics_pipeline, Pipeline::null()),
+            pipeline_layout: std::mem::replace(&mut self.pipeline_layout, PipelineLayout::null()),
+            render_pass: std::mem::replace(&mut self.render_pass, RenderPass::null()),
+        }
+    }
+
+    fn destroy_pipeline(&mut self) {
+        let pipeline = self.take_pipeline();
+        pipeline.destroy(&self.device, &mut self.allocator);
+    }
+
+    //Destroyed once everything submitted up to retire_value has completed
+    fn retire_pipeline(&mut self, retire_value: u64) {
+        let pipeline = self.take_pipeline();
+        self.retirements.retire(retire_value, pipeline);
+    }
+
+    //Attachments and framebuffers sized for the current swapchain, command buffers are recorded every frame
+    fn create_render_targets(&mut self) -> Result<(), PotatoError> {
+        self.color_images = if self.samples == SampleCountFlags::TYPE_1 {
+            vec![]
+        } else {
+            create_color_images(
+                &self.device,
+                &self.debug_utils,
+                &mut self.allocator,
+                self.swapchain.swapchain_format,
+                self.swapchain.swapchain_extent,
+                self.samples,
+                self.swapchain.swapchain_images.len(),
+            )?
+        };
+        self.depth_images = create_depth_images(
+            &self.device,
+            &self.debug_utils,
+            &mut self.allocator,
+            self.depth_format,
+            self.swapchain.swapchain_extent,
+            self.samples,
+            self.swapchain.swapchain_images.len(),
+        )?;
+        let depth_image_views: Vec<ImageView> =
+            self.depth_images.iter().map(|x| x.image_view).collect();
+        let color_image_views: Vec<ImageView> =
+            self.color_images.iter().map(|x| x.image_view).collect();
         self.swapchain_framebuffers = create_framebuffers(
             &self.device,
+            &self.debug_utils,
             self.render_pass,
             &self.swapchain.swapchain_image_views,
+            &depth_image_views,
+            &color_image_views,
             &self.swapchain.swapchain_extent,
-        );
-        self.command_buffers = create_command_buffers(
-            &self.device,
-            self.command_pool,
-            graphics_pipeline,
-            &self.swapchain_framebuffers,
-            self.render_pass,
-            self.swapchain.swapchain_extent,
-            self.vertex_buffer,
-            self.index_buffer,
-            self.pipeline_layout,
-            &self.descriptor_sets,
-        );
+        )?;
+        Ok(())
     }
 
-    fn cleanup_swapchain(&self) {
-        unsafe {
-            self.device
-                .free_command_buffers(self.command_pool, &self.command_buffers);
-            self.swapchain_framebuffers
-                .iter()
-                .for_each(|x| self.device.destroy_framebuffer(*x, None));
-            self.device.destroy_pipeline(self.graphics_pipeline, None);
-            self.device
-                .destroy_pipeline_layout(self.pipeline_layout, None);
-            self.device.destroy_render_pass(self.render_pass, None);
-            self.swapchain
-                .swapchain_image_views
-                .iter()
-                .for_each(|x| self.device.destroy_image_view(*x, None));
-            self.swapchain
-                .swapchain_loader
-                .destroy_swapchain(self.swapchain.swapchain, None);
+    //Handles are taken out so a failed rebuild never destroys them twice
+    fn take_render_targets(&mut self) -> Retired {
+        Retired::RenderTargets {
+            framebuffers: std::mem::take(&mut self.swapchain_framebuffers),
+            depth_images: std::mem::take(&mut self.depth_images),
+            color_images: std::mem::take(&mut self.color_images),
+        }
+    }
+
+    fn destroy_render_targets(&mut self) {
+        let render_targets = self.take_render_targets();
+        render_targets.destroy(&self.device, &mut self.allocator);
+    }
+
+    //Destroyed once everything submitted up to retire_value has completed
+    fn retire_render_targets(&mut self, retire_value: u64) {
+        let render_targets = self.take_render_targets();
+        self.retirements.retire(retire_value, render_targets);
+    }
+
+    //Frames in flight keep drawing with the old pipeline and render targets while the new ones are created
+    fn rebuild_pipeline(&mut self) -> Result<(), PotatoError> {
+        let retire_value = self.frame_pacer.submitted_value();
+        self.retire_render_targets(retire_value);
+        self.retire_pipeline(retire_value);
+        self.create_pipeline()?;
+        self.create_render_targets()
+    }
+
+    //Clamped to the device maximum, the pipeline and framebuffers are rebuilt for the new count
+    pub fn set_sample_count(&mut self, samples: SampleCountFlags) -> Result<(), PotatoError> {
+        let samples = clamp_sample_count(samples, self.max_samples);
+        if samples == self.samples {
+            return Ok(());
+        }
+        debug!("Switching to {:?} samples", samples);
+        self.samples = samples;
+        self.rebuild_pipeline()
+    }
+
+    //Wireframe needs fillModeNonSolid, without it the request is logged and ignored
+    pub fn set_wireframe(&mut self, wireframe: bool) -> Result<(), PotatoError> {
+        let polygon_mode = if wireframe {
+            PolygonMode::LINE
+        } else {
+            PolygonMode::FILL
+        };
+        if polygon_mode == self.polygon_mode {
+            return Ok(());
+        }
+        if !self
+            .enabled_features
+            .has_feature(DeviceFeature::FillModeNonSolid)
+        {
+            warn!(
+                "Wireframe needs {}, which the device was created without",
+                DeviceFeature::FillModeNonSolid
+            );
+            return Ok(());
         }
+        debug!("Switching to {:?} polygons", polygon_mode);
+        self.polygon_mode = polygon_mode;
+        self.rebuild_pipeline()
     }
 
-    fn init_window(event_loop: &EventLoopWindowTarget<()>, name: &str) -> Window {
-        WindowBuilder::new()
+    //Replaces what is recorded every frame, takes effect on the next frame
+    pub fn set_record_callback<F>(&mut self, record_callback: F)
+    where
+        F: FnMut(&FrameContext) -> Result<(), PotatoError> + 'static,
+    {
+        self.record_callback = Box::new(record_callback);
+    }
+
+    //The swapchain is recreated so the new mode takes effect on the next frame
+    pub fn set_present_policy(&mut self, present_policy: PresentPolicy) -> Result<(), PotatoError> {
+        if present_policy == self.present_policy {
+            return Ok(());
+        }
+        info!("Switching present policy to {}", present_policy);
+        self.present_policy = present_policy;
+        self.recreate_swapchain()?;
+        info!("Presenting with {:?}", self.swapchain.present_mode);
+        Ok(())
+    }
+
+    fn cleanup_swapchain(&mut self) {
+        self.destroy_render_targets();
+        self.destroy_pipeline();
+        destroy_swapchain(&self.device, &self.swapchain);
+    }
+
+    fn init_window(
+        event_loop: &EventLoopWindowTarget<()>,
+        name: &str,
+    ) -> Result<Window, PotatoError> {
+        Ok(WindowBuilder::new()
             .with_title(name)
             .with_inner_size(LogicalSize::new(800, 600))
-            .build(event_loop)
-            .expect("Failed to create window.")
+            .build(event_loop)?)
     }
 
     pub fn init_event_loop(mut self, event_loop: EventLoop<()>) {
@@ -425,6 +781,12 @@ impl VulkanApiObjects {
 
             match event {
                 Event::WindowEvent { event, window_id } => {
+                    //A minimized window has no area to present to, rendering resumes once it has one again
+                    if let WindowEvent::Resized(size) = event {
+                        self.is_paused = size.width == 0 || size.height == 0;
+                        self.is_resized = !self.is_paused;
+                    }
+
                     if let WindowEvent::CloseRequested = event {
                         println!("Window {:?} has received the signal to close", window_id);
                         self.windows.remove(&window_id);
@@ -449,28 +811,69 @@ impl VulkanApiObjects {
                             && virtual_keycode == Some(VirtualKeyCode::N)
                             && !is_synthetic
                         {
-                            let window = VulkanApiObjects::init_window(event_loop, "spawn");
-                            self.windows.insert(window.id(), window);
+                            match VulkanApiObjects::init_window(event_loop, "spawn") {
+                                Ok(window) => {
+                                    self.windows.insert(window.id(), window);
+                                }
+                                Err(e) => error!("{}", e),
+                            }
+                        }
+
+                        if state == ElementState::Released
+                            && virtual_keycode == Some(VirtualKeyCode::M)
+                            && !is_synthetic
+                        {
+                            let samples = next_sample_count(self.samples, self.max_samples);
+                            if let Err(e) = self.set_sample_count(samples) {
+                                error!("Failed to change sample count: {}", e);
+                                *control_flow = ControlFlow::ExitWithCode(1);
+                            }
+                        }
+
+                        if state == ElementState::Released
+                            && virtual_keycode == Some(VirtualKeyCode::P)
+                            && !is_synthetic
+                        {
+                            let present_policy = self.present_policy.next();
+                            if let Err(e) = self.set_present_policy(present_policy) {
+                                error!("Failed to change present policy: {}", e);
+                                *control_flow = ControlFlow::ExitWithCode(1);
+                            }
+                        }
+
+                        if state == ElementState::Released
+                            && virtual_keycode == Some(VirtualKeyCode::W)
+                            && !is_synthetic
+                        {
+                            let wireframe = self.polygon_mode == PolygonMode::FILL;
+                            if let Err(e) = self.set_wireframe(wireframe) {
+                                error!("Failed to change polygon mode: {}", e);
+                                *control_flow = ControlFlow::ExitWithCode(1);
+                            }
                         }
                     }
                 }
                 Event::MainEventsCleared => {
+                    if self.is_paused {
+                        return;
+                    }
                     for (.., window) in self.windows.iter() {
                         window.request_redraw();
                     }
                 }
                 Event::RedrawRequested(_window_id) => {
-                    let delta_time = delta_frame as f32 / 1_000_000.0 as f32;
-                    self.draw(delta_time);
+                    let delta_time = delta_frame as f32 / 1_000_000.0_f32;
+                    if let Err(e) = self.draw(delta_time) {
+                        error!("Failed to draw frame: {}", e);
+                        *control_flow = ControlFlow::ExitWithCode(1);
+                    }
 
                     delta_frame = time.elapsed().subsec_micros();
                 }
                 Event::LoopDestroyed => {
-                    unsafe {
-                        self.device
-                            .device_wait_idle()
-                            .expect("Failed to wait device idle!")
-                    };
+                    if let Err(e) = unsafe { self.device.device_wait_idle() } {
+                        error!("Failed to wait device idle: {}", e);
+                    }
                 }
                 _ => (),
             }
@@ -478,34 +881,87 @@ impl VulkanApiObjects {
     }
 }
 
+//What a rebuild replaces, kept alive until the frames that still use it have completed
+enum Retired {
+    RenderTargets {
+        framebuffers: Vec<Framebuffer>,
+        depth_images: Vec<AttachmentImage>,
+        color_images: Vec<AttachmentImage>,
+    },
+    Pipeline {
+        graphics_pipeline: Pipeline,
+        pipeline_layout: PipelineLayout,
+        render_pass: RenderPass,
+    },
+    Swapchain(PotatoSwapChain),
+}
+
+impl Retired {
+    fn destroy(self, device: &Device, allocator: &mut Allocator) {
+        match self {
+            Retired::RenderTargets {
+                framebuffers,
+                depth_images,
+                color_images,
+            } => {
+                framebuffers
+                    .iter()
+                    .for_each(|x| unsafe { device.destroy_framebuffer(*x, None) });
+                depth_images
+                    .iter()
+                    .chain(color_images.iter())
+                    .for_each(|x| x.destroy(device, allocator));
+            }
+            Retired::Pipeline {
+                graphics_pipeline,
+                pipeline_layout,
+                render_pass,
+            } => unsafe {
+                device.destroy_pipeline(graphics_pipeline, None);
+                device.destroy_pipeline_layout(pipeline_layout, None);
+                device.destroy_render_pass(render_pass, None);
+            },
+            Retired::Swapchain(swapchain) => destroy_swapchain(device, &swapchain),
+        }
+    }
+}
+
 impl Drop for VulkanApiObjects {
     fn drop(&mut self) {
         unsafe {
-            for i in 0..MAX_FRAMES_IN_FLIGHT {
-                self.device
-                    .destroy_semaphore(self.image_available_semaphores[i], None);
-                self.device
-                    .destroy_semaphore(self.render_finished_semaphores[i], None);
-                self.device.destroy_fence(self.in_flight_fences[i], None);
-            }
+            destroy_semaphores(&self.device, &self.image_available_semaphores);
+            destroy_semaphores(&self.device, &self.render_finished_semaphores);
+            self.frame_pacer.destroy(&self.device);
             self.cleanup_swapchain();
+            for retired in self.retirements.take_all() {
+                retired.destroy(&self.device, &mut self.allocator);
+            }
+            self.device
+                .destroy_descriptor_pool(self.descriptor_pool, None);
             self.device
                 .destroy_descriptor_set_layout(self.ubo_layout, None);
-            self.uniform_buffers.iter().enumerate().for_each(|(i, _)| {
-                self.device.destroy_buffer(self.uniform_buffers[i], None);
-                self.device
-                    .free_memory(self.uniform_buffers_memory[i], None);
-            });
-            self.device.destroy_buffer(self.index_buffer, None);
-            self.device.free_memory(self.index_buffer_memory, None);
-            self.device.destroy_buffer(self.vertex_buffer, None);
-            self.device.free_memory(self.vertex_buffer_memory, None);
+            for (i, x) in self.uniform_buffers.iter().enumerate() {
+                destroy_buffer(
+                    &self.device,
+                    &mut self.allocator,
+                    *x,
+                    self.uniform_buffers_allocations[i],
+                );
+            }
+            self.texture.destroy(&self.device, &mut self.allocator);
+            self.mesh_buffers.destroy(&self.device, &mut self.allocator);
+            self.allocator.destroy();
             self.device.destroy_command_pool(self.command_pool, None);
+            self.device
+                .destroy_command_pool(self.transfer_command_pool, None);
+            for frame_commands in &self.frame_commands {
+                frame_commands.destroy(&self.device);
+            }
             self.device.destroy_device(None);
             self.surface
                 .surface_loader
                 .destroy_surface(self.surface.surface, None);
-            if VALIDATION.is_enable {
+            if self.debug_messenger != DebugUtilsMessengerEXT::null() {
                 self.debug_utils_loader
                     .destroy_debug_utils_messenger(self.debug_messenger, None);
             }
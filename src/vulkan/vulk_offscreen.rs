@@ -0,0 +1,510 @@
+use super::allocator::{Allocation, Allocator, HeapStats};
+use super::buffer::{create_buffer, destroy_buffer};
+use super::command_pool::create_command_pool;
+use super::config::OffscreenConfig;
+use super::constants::OFFSCREEN_FORMAT;
+use super::debug_utils::PotatoDebugUtils;
+use super::depth::{create_depth_image, find_depth_format};
+use super::device::create_offscreen_logical_device;
+use super::device_features::{negotiate_device_features, DeviceFeature, DeviceRequirements};
+use super::frame::{
+    create_frame_commands, record_default_frame, FrameCommands, FrameContext, RecordCallback,
+};
+use super::frame_pacing::FramePacer;
+use super::framebuffers::create_framebuffers;
+use super::graphics_pipeline::create_graphics_pipeline;
+use super::image::{copy_image_to_buffer, create_image, AttachmentImage};
+use super::instance::create_instance;
+use super::mesh::{Mesh, MeshBuffers};
+use super::physical_device::{describe_device, select_offscreen_physical_device};
+use super::recording_threads::RecordingThreads;
+use super::render_pass::create_render_pass;
+use super::swapchain::create_image_view;
+use super::texture::{PotatoTexture, SamplerSettings};
+use super::timeline::is_timeline_enabled;
+use super::vulk_validation_layers::setup_debug_utils;
+use super::UniformBufferObject::{
+    create_descriptor_pool, create_descriptor_set_layout, create_descriptor_sets,
+    create_uniform_buffers, update_uniform_buffer,
+};
+use crate::error::PotatoError;
+use crate::io::file::write_image;
+use crate::io::scene::TextureImage;
+use ash::extensions::ext::DebugUtils;
+use ash::vk::{
+    Buffer, BufferUsageFlags, CommandPool, CommandPoolCreateFlags, DebugUtilsMessengerEXT,
+    DescriptorPool, DescriptorSet, DescriptorSetLayout, DeviceSize, Extent2D, Framebuffer,
+    ImageAspectFlags, ImageLayout, ImageUsageFlags, ImageView, MemoryPropertyFlags, Pipeline,
+    PipelineLayout, PolygonMode, Queue, RenderPass, SampleCountFlags,
+};
+use ash::{Device, Entry, Instance};
+use log::{debug, error};
+
+pub struct OffscreenApiObjects {
+    _entry: Entry,
+    instance: Instance,
+    debug_utils_loader: DebugUtils,
+    debug_messenger: DebugUtilsMessengerEXT,
+    device: Device,
+    allocator: Allocator,
+    graphics_queue: Queue,
+    extent: Extent2D,
+    color_images: Vec<AttachmentImage>,
+    //Frames submitted so far, picks the color image of the next one
+    frame_number: usize,
+    //Color image of the latest submitted frame, the one that is read back
+    last_target: usize,
+    depth_image: AttachmentImage,
+    readback_buffer: Buffer,
+    readback_buffer_allocation: Allocation,
+    pipeline_layout: PipelineLayout,
+    render_pass: RenderPass,
+    graphics_pipeline: Pipeline,
+    framebuffers: Vec<Framebuffer>,
+    command_pool: CommandPool,
+    frame_commands: Vec<FrameCommands>,
+    recording_threads: RecordingThreads,
+    frame_pacer: FramePacer,
+    mesh_buffers: MeshBuffers,
+    texture: PotatoTexture,
+    uniform_buffers: Vec<Buffer>,
+    uniform_buffers_allocations: Vec<Allocation>,
+    ubo_layout: DescriptorSetLayout,
+    descriptor_pool: DescriptorPool,
+    descriptor_sets: Vec<DescriptorSet>,
+    debug_utils: PotatoDebugUtils,
+    record_callback: RecordCallback,
+}
+
+impl OffscreenApiObjects {
+    //Configured by the POTATO_* environment variables
+    pub fn init(
+        width: u32,
+        height: u32,
+        mesh: &Mesh,
+        texture_image: Option<&TextureImage>,
+    ) -> Result<OffscreenApiObjects, PotatoError> {
+        OffscreenApiObjects::init_with_config(
+            width,
+            height,
+            mesh,
+            texture_image,
+            &OffscreenConfig::from_env(),
+        )
+    }
+
+    pub fn init_with_config(
+        width: u32,
+        height: u32,
+        mesh: &Mesh,
+        texture_image: Option<&TextureImage>,
+        config: &OffscreenConfig,
+    ) -> Result<OffscreenApiObjects, PotatoError> {
+        //Checked before anything is created, Vulkan does not allow images without area
+        if width == 0 || height == 0 {
+            return Err(PotatoError::InvalidSetting {
+                name: "extent".to_string(),
+                value: format!("{}x{}", width, height),
+            });
+        }
+        let extent = Extent2D { width, height };
+
+        debug!("Init entry");
+        let entry = Entry::linked();
+        debug!("Init instance");
+        let validation = &config.validation;
+        let (instance, instance_capabilities) = create_instance(&entry, false, validation)?;
+        debug!("Init debug utils");
+        let (debug_utils_loader, debug_messenger) =
+            setup_debug_utils(&entry, &instance, validation, &instance_capabilities)?;
+        debug!("Init physical device");
+        let mut device_requirements =
+            DeviceRequirements::new().request_feature(DeviceFeature::SamplerAnisotropy);
+        if config.timeline_semaphores {
+            device_requirements =
+                device_requirements.request_feature(DeviceFeature::TimelineSemaphore);
+        }
+        let physical_device = select_offscreen_physical_device(
+            &instance,
+            config.device.as_ref(),
+            &device_requirements,
+        )?;
+        describe_device(&instance, physical_device);
+
+        debug!("Init device features");
+        let enabled_features =
+            negotiate_device_features(&instance, physical_device, &device_requirements)?;
+        debug!("Init logical device");
+        let (logical_device, queue_family) =
+            create_offscreen_logical_device(&instance, physical_device, &enabled_features)?;
+        let debug_utils =
+            PotatoDebugUtils::new(&debug_utils_loader, &logical_device, &instance_capabilities);
+        let graphics_family = queue_family
+            .graphics_family
+            .ok_or(PotatoError::NoSuitableQueueFamily)? as u32;
+        debug!("Init allocator");
+        let mut allocator = Allocator::new(&instance, physical_device, &logical_device);
+        debug!("Init graphics queue");
+        let graphics_queue = unsafe { logical_device.get_device_queue(graphics_family, 0) };
+        debug_utils.name_object(graphics_queue, "Graphics queue");
+        debug!("Init color images");
+        let mut color_images = vec![];
+        for i in 0..config.target_count.max(1) {
+            let (image, allocation) = create_image(
+                &logical_device,
+                &debug_utils,
+                &mut allocator,
+                extent,
+                1,
+                SampleCountFlags::TYPE_1,
+                OFFSCREEN_FORMAT,
+                ImageUsageFlags::COLOR_ATTACHMENT | ImageUsageFlags::TRANSFER_SRC,
+                MemoryPropertyFlags::DEVICE_LOCAL,
+                &format!("Offscreen color image {}", i),
+            )?;
+            let image_view = create_image_view(
+                &logical_device,
+                &debug_utils,
+                OFFSCREEN_FORMAT,
+                image,
+                ImageAspectFlags::COLOR,
+                1,
+                &format!("Offscreen color image view {}", i),
+            )?;
+            color_images.push(AttachmentImage {
+                image,
+                allocation,
+                image_view,
+            });
+        }
+        debug!("Init depth image");
+        let depth_format = find_depth_format(&instance, physical_device)?;
+        let depth_image = create_depth_image(
+            &logical_device,
+            &debug_utils,
+            &mut allocator,
+            depth_format,
+            extent,
+            SampleCountFlags::TYPE_1,
+            "Offscreen depth image",
+        )?;
+        debug!("Init readback buffer");
+        let (readback_buffer, readback_buffer_allocation) = create_buffer(
+            &logical_device,
+            &debug_utils,
+            &mut allocator,
+            width as DeviceSize * height as DeviceSize * 4,
+            BufferUsageFlags::TRANSFER_DST,
+            MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT,
+            &[],
+            "Readback buffer",
+        )?;
+        debug!("Init render pass");
+        let render_pass = create_render_pass(
+            &logical_device,
+            &debug_utils,
+            OFFSCREEN_FORMAT,
+            depth_format,
+            SampleCountFlags::TYPE_1,
+            ImageLayout::TRANSFER_SRC_OPTIMAL,
+        )?;
+        debug!("Init descriptor layout");
+        let ubo_layout = create_descriptor_set_layout(&logical_device, &debug_utils)?;
+        debug!("Init graphics pipeline");
+        let (graphics_pipeline, pipeline_layout) = create_graphics_pipeline(
+            &logical_device,
+            &debug_utils,
+            render_pass,
+            ubo_layout,
+            SampleCountFlags::TYPE_1,
+            PolygonMode::FILL,
+        )?;
+        debug!("Init framebuffers");
+        let framebuffers = create_framebuffers(
+            &logical_device,
+            &debug_utils,
+            render_pass,
+            &color_images
+                .iter()
+                .map(|x| x.image_view)
+                .collect::<Vec<ImageView>>(),
+            &vec![depth_image.image_view; color_images.len()],
+            &[],
+            &extent,
+        )?;
+        debug!("Init command pool");
+        let command_pool = create_command_pool(
+            &logical_device,
+            &debug_utils,
+            graphics_family,
+            CommandPoolCreateFlags::empty(),
+            "Graphics command pool",
+        )?;
+        let frames_in_flight = config.frames_in_flight.max(1);
+        debug!("Init frame pacing");
+        let frame_pacer = FramePacer::new(
+            &logical_device,
+            &debug_utils,
+            frames_in_flight,
+            color_images.len(),
+            is_timeline_enabled(&enabled_features),
+        )?;
+        debug!("Init mesh buffers");
+        let mesh_buffers = mesh.upload(
+            &logical_device,
+            &debug_utils,
+            &mut allocator,
+            command_pool,
+            graphics_queue,
+            frame_pacer.timeline(),
+            &[],
+            "Mesh",
+        )?;
+        debug!("Init texture");
+        let texture = PotatoTexture::upload(
+            &instance,
+            physical_device,
+            &logical_device,
+            &debug_utils,
+            &mut allocator,
+            command_pool,
+            graphics_queue,
+            frame_pacer.timeline(),
+            texture_image.unwrap_or(&TextureImage::solid([255, 255, 255, 255])),
+            &enabled_features,
+            &SamplerSettings::for_features(&enabled_features),
+        )?;
+        debug!("Init ubo buffer");
+        let (uniform_buffers, uniform_buffers_allocations) = create_uniform_buffers(
+            &logical_device,
+            &debug_utils,
+            &mut allocator,
+            frames_in_flight,
+        )?;
+        debug!("Init descriptor pool");
+        let descriptor_pool =
+            create_descriptor_pool(&logical_device, &debug_utils, frames_in_flight)?;
+        debug!("Init descriptor sets");
+        let descriptor_sets = create_descriptor_sets(
+            &logical_device,
+            &debug_utils,
+            descriptor_pool,
+            ubo_layout,
+            &uniform_buffers,
+            &texture,
+            frames_in_flight,
+        )?;
+        debug!("Init frame commands");
+        let frame_commands = create_frame_commands(
+            &logical_device,
+            &debug_utils,
+            graphics_family,
+            frames_in_flight,
+            config.recording_threads,
+        )?;
+        debug!("Init recording threads");
+        let recording_threads = RecordingThreads::new(config.recording_threads)?;
+        allocator.log_stats();
+
+        Ok(OffscreenApiObjects {
+            _entry: entry,
+            instance,
+            debug_utils_loader,
+            debug_messenger,
+            device: logical_device,
+            allocator,
+            graphics_queue,
+            extent,
+            color_images,
+            frame_number: 0,
+            last_target: 0,
+            depth_image,
+            readback_buffer,
+            readback_buffer_allocation,
+            pipeline_layout,
+            render_pass,
+            graphics_pipeline,
+            framebuffers,
+            command_pool,
+            frame_commands,
+            recording_threads,
+            frame_pacer,
+            mesh_buffers,
+            texture,
+            uniform_buffers,
+            uniform_buffers_allocations,
+            ubo_layout,
+            descriptor_pool,
+            descriptor_sets,
+            debug_utils,
+            record_callback: Box::new(record_default_frame),
+        })
+    }
+
+    pub fn extent(&self) -> Extent2D {
+        self.extent
+    }
+
+    pub fn memory_stats(&self) -> Vec<HeapStats> {
+        self.allocator.stats()
+    }
+
+    //Returns the rendered frame as tightly packed RGBA rows
+    pub fn render_frame(&mut self, delta_time: f32) -> Result<Vec<u8>, PotatoError> {
+        self.render_frames(1, delta_time)
+    }
+
+    //Submits frame_count frames paced like the windowed renderer and returns the last one
+    pub fn render_frames(
+        &mut self,
+        frame_count: u32,
+        delta_time: f32,
+    ) -> Result<Vec<u8>, PotatoError> {
+        for _ in 0..frame_count {
+            self.submit_frame(delta_time)?;
+        }
+        self.frame_pacer.wait_for_all(&self.device)?;
+
+        copy_image_to_buffer(
+            &self.device,
+            self.graphics_queue,
+            self.command_pool,
+            self.frame_pacer.timeline(),
+            self.color_images[self.last_target].image,
+            self.readback_buffer,
+            self.extent,
+        )?;
+
+        let pixel_count = self.extent.width as usize * self.extent.height as usize * 4;
+        self.readback_buffer_allocation.read(pixel_count)
+    }
+
+    fn submit_frame(&mut self, delta_time: f32) -> Result<(), PotatoError> {
+        let current_frame = self.frame_pacer.current_frame();
+        self.frame_pacer.wait_for_current_frame(&self.device)?;
+        let target = target_order(self.frame_number, self.color_images.len());
+        self.frame_number += 1;
+        //Another frame in flight may still render to the target, like an acquired swapchain image
+        self.frame_pacer.claim_image(&self.device, target)?;
+
+        update_uniform_buffer(
+            self.extent,
+            current_frame,
+            delta_time,
+            &self.uniform_buffers_allocations,
+        )?;
+
+        let command_buffer = self.frame_commands[current_frame].begin(&self.device)?;
+        let frame = FrameContext {
+            device: &self.device,
+            debug_utils: &self.debug_utils,
+            command_buffer,
+            secondary_command_buffers: &self.frame_commands[current_frame]
+                .secondary_command_buffers,
+            recording_threads: &self.recording_threads,
+            frame_index: current_frame,
+            image_index: target,
+            delta_time,
+            extent: self.extent,
+            render_pass: self.render_pass,
+            framebuffer: self.framebuffers[target],
+            graphics_pipeline: self.graphics_pipeline,
+            pipeline_layout: self.pipeline_layout,
+            descriptor_set: self.descriptor_sets[current_frame],
+            mesh: &self.mesh_buffers,
+        };
+        (self.record_callback)(&frame)?;
+        unsafe { self.device.end_command_buffer(command_buffer)? };
+
+        self.frame_pacer.submit(
+            &self.device,
+            self.graphics_queue,
+            command_buffer,
+            &[],
+            &[],
+            &[],
+        )?;
+        self.frame_pacer.advance();
+        self.last_target = target;
+        Ok(())
+    }
+
+    //Replaces what is recorded every frame, takes effect on the next frame
+    pub fn set_record_callback<F>(&mut self, record_callback: F)
+    where
+        F: FnMut(&FrameContext) -> Result<(), PotatoError> + 'static,
+    {
+        self.record_callback = Box::new(record_callback);
+    }
+
+    //The file type is picked from the extension of path, e.g. .png or .ppm
+    pub fn save_frame(&mut self, delta_time: f32, path: &str) -> Result<(), PotatoError> {
+        let pixels = self.render_frame(delta_time)?;
+        write_image(path, self.extent.width, self.extent.height, &pixels)?;
+        Ok(())
+    }
+}
+
+impl Drop for OffscreenApiObjects {
+    fn drop(&mut self) {
+        unsafe {
+            if let Err(e) = self.device.device_wait_idle() {
+                error!("Failed to wait device idle: {}", e);
+            }
+            self.frame_pacer.destroy(&self.device);
+            self.frame_commands
+                .iter()
+                .for_each(|x| x.destroy(&self.device));
+            self.framebuffers
+                .iter()
+                .for_each(|x| self.device.destroy_framebuffer(*x, None));
+            self.device.destroy_pipeline(self.graphics_pipeline, None);
+            self.device
+                .destroy_pipeline_layout(self.pipeline_layout, None);
+            self.device.destroy_render_pass(self.render_pass, None);
+            self.device
+                .destroy_descriptor_pool(self.descriptor_pool, None);
+            self.device
+                .destroy_descriptor_set_layout(self.ubo_layout, None);
+            for (i, x) in self.uniform_buffers.iter().enumerate() {
+                destroy_buffer(
+                    &self.device,
+                    &mut self.allocator,
+                    *x,
+                    self.uniform_buffers_allocations[i],
+                );
+            }
+            self.texture.destroy(&self.device, &mut self.allocator);
+            self.mesh_buffers.destroy(&self.device, &mut self.allocator);
+            destroy_buffer(
+                &self.device,
+                &mut self.allocator,
+                self.readback_buffer,
+                self.readback_buffer_allocation,
+            );
+            self.depth_image.destroy(&self.device, &mut self.allocator);
+            for color_image in &self.color_images {
+                color_image.destroy(&self.device, &mut self.allocator);
+            }
+            self.allocator.destroy();
+            self.device.destroy_command_pool(self.command_pool, None);
+            self.device.destroy_device(None);
+            if self.debug_messenger != DebugUtilsMessengerEXT::null() {
+                self.debug_utils_loader
+                    .destroy_debug_utils_messenger(self.debug_messenger, None);
+            }
+            self.instance.destroy_instance(None);
+        }
+    }
+}
+
+//Walks the targets forwards and then backwards, so the last target of a pass is taken twice in a row
+//and frames get targets out of order the way a presentation engine hands out images
+fn target_order(frame_number: usize, target_count: usize) -> usize {
+    let position = frame_number % target_count;
+    if (frame_number / target_count).is_multiple_of(2) {
+        position
+    } else {
+        target_count - 1 - position
+    }
+}
@@ -1,14 +1,18 @@
+use super::capabilities::EnabledInstanceCapabilities;
+use super::instance::has_debug_messenger;
+use super::validation::{
+    report_validation_message, ValidationConfig, ValidationMessage, ValidationObject,
+};
+use crate::error::PotatoError;
 use ash::extensions::ext::DebugUtils;
 use ash::vk;
 use ash::vk::{
-    Bool32, DebugUtilsMessageSeverityFlagsEXT, DebugUtilsMessageTypeFlagsEXT,
+    Bool32, DebugUtilsLabelEXT, DebugUtilsMessageSeverityFlagsEXT, DebugUtilsMessageTypeFlagsEXT,
     DebugUtilsMessengerCallbackDataEXT, DebugUtilsMessengerEXT,
 };
 use ash::{Entry, Instance};
-use log::debug;
 use std::ffi::CStr;
-use std::os::raw::c_void;
-use super::constants::VALIDATION;
+use std::os::raw::{c_char, c_void};
 
 unsafe extern "system" fn vulkan_debug_utils_callback(
     message_severity: DebugUtilsMessageSeverityFlagsEXT,
@@ -16,58 +20,91 @@ unsafe extern "system" fn vulkan_debug_utils_callback(
     p_callback_data: *const DebugUtilsMessengerCallbackDataEXT,
     _p_user_data: *mut c_void,
 ) -> Bool32 {
-    let severity = match message_severity {
-        DebugUtilsMessageSeverityFlagsEXT::VERBOSE => "[Verbose]",
-        DebugUtilsMessageSeverityFlagsEXT::WARNING => "[Warning]",
-        DebugUtilsMessageSeverityFlagsEXT::ERROR => "[Error]",
-        DebugUtilsMessageSeverityFlagsEXT::INFO => "[Info]",
-        _ => "[Unknown]",
-    };
-    let types = match message_type {
-        DebugUtilsMessageTypeFlagsEXT::GENERAL => "[General]",
-        DebugUtilsMessageTypeFlagsEXT::PERFORMANCE => "[Performance]",
-        DebugUtilsMessageTypeFlagsEXT::VALIDATION => "[Validation]",
-        _ => "[Unknown]",
-    };
-    let message = CStr::from_ptr((*p_callback_data).p_message);
-    debug!("{}, {}, {:?}", severity, types, message);
+    if !p_callback_data.is_null() {
+        let callback_data = &*p_callback_data;
+        let objects = raw_slice(callback_data.p_objects, callback_data.object_count)
+            .iter()
+            .map(|x| ValidationObject {
+                object_type: x.object_type,
+                handle: x.object_handle,
+                name: optional_string(x.p_object_name),
+            })
+            .collect();
+
+        report_validation_message(&ValidationMessage {
+            severity: message_severity,
+            message_types: message_type,
+            message_id_name: optional_string(callback_data.p_message_id_name),
+            message_id_number: callback_data.message_id_number,
+            message: optional_string(callback_data.p_message).unwrap_or_default(),
+            objects,
+            command_buffer_labels: label_names(
+                callback_data.p_cmd_buf_labels,
+                callback_data.cmd_buf_label_count,
+            ),
+            queue_labels: label_names(
+                callback_data.p_queue_labels,
+                callback_data.queue_label_count,
+            ),
+        });
+    }
 
+    //Returning TRUE would abort the call that triggered the message
     ash::vk::FALSE
 }
 
+unsafe fn optional_string(p_string: *const c_char) -> Option<String> {
+    if p_string.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(p_string).to_string_lossy().into_owned())
+    }
+}
+
+unsafe fn raw_slice<'a, T>(p_items: *const T, count: u32) -> &'a [T] {
+    if p_items.is_null() || count == 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts(p_items, count as usize)
+    }
+}
+
+unsafe fn label_names(p_labels: *const DebugUtilsLabelEXT, count: u32) -> Vec<String> {
+    raw_slice(p_labels, count)
+        .iter()
+        .filter_map(|x| optional_string(x.p_label_name))
+        .collect()
+}
+
+//The messenger is null when validation is off or debug utils is unavailable
 pub fn setup_debug_utils(
     entry: &Entry,
     instance: &Instance,
-) -> (DebugUtils, DebugUtilsMessengerEXT) {
+    validation: &ValidationConfig,
+    instance_capabilities: &EnabledInstanceCapabilities,
+) -> Result<(DebugUtils, DebugUtilsMessengerEXT), PotatoError> {
     let debug_util_loader = DebugUtils::new(entry, instance);
 
-    if !VALIDATION.is_enable {
-        (debug_util_loader, DebugUtilsMessengerEXT::null())
+    if !has_debug_messenger(validation, instance_capabilities) {
+        Ok((debug_util_loader, DebugUtilsMessengerEXT::null()))
     } else {
-        let messenger_ci = populate_debug_messenger_create_info();
+        let messenger_ci = populate_debug_messenger_create_info(validation);
 
-        let utils_messenger = unsafe {
-            debug_util_loader
-                .create_debug_utils_messenger(&messenger_ci, None)
-                .expect("Debug Utils Callback")
-        };
-        (debug_util_loader, utils_messenger)
+        let utils_messenger =
+            unsafe { debug_util_loader.create_debug_utils_messenger(&messenger_ci, None)? };
+        Ok((debug_util_loader, utils_messenger))
     }
 }
 
-pub fn populate_debug_messenger_create_info() -> vk::DebugUtilsMessengerCreateInfoEXT {
+pub fn populate_debug_messenger_create_info(
+    validation: &ValidationConfig,
+) -> vk::DebugUtilsMessengerCreateInfoEXT {
     vk::DebugUtilsMessengerCreateInfoEXT {
         s_type: vk::StructureType::DEBUG_UTILS_MESSENGER_CREATE_INFO_EXT,
         p_next: std::ptr::null(),
         flags: vk::DebugUtilsMessengerCreateFlagsEXT::empty(),
-        message_severity: vk::DebugUtilsMessageSeverityFlagsEXT::WARNING
-            // Can uncomment for more verbosity form the validation layers
-            // | vk::DebugUtilsMessageSeverityFlagsEXT::VERBOSE
-            // | vk::DebugUtilsMessageSeverityFlagsEXT::INFO
-            | vk::DebugUtilsMessageSeverityFlagsEXT::ERROR,
-        message_type: vk::DebugUtilsMessageTypeFlagsEXT::GENERAL
-            | vk::DebugUtilsMessageTypeFlagsEXT::PERFORMANCE
-            | vk::DebugUtilsMessageTypeFlagsEXT::VALIDATION,
+        message_severity: validation.severities,
+        message_type: validation.message_types,
         pfn_user_callback: Some(vulkan_debug_utils_callback),
         p_user_data: std::ptr::null_mut(),
     }
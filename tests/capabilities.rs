@@ -0,0 +1,93 @@
+use potato::{
+    select_available, CapabilityReport, DeviceFeature, DeviceRequirements, InstanceRequirements,
+    PotatoError,
+};
+
+fn names(names: &[&str]) -> Vec<String> {
+    names.iter().map(|x| x.to_string()).collect()
+}
+
+#[test]
+fn every_required_name_must_be_available() {
+    let (enabled, missing) = select_available(
+        &["VK_KHR_swapchain", "VK_KHR_dynamic_rendering"],
+        &[],
+        &names(&["VK_KHR_swapchain"]),
+    );
+
+    assert_eq!(enabled, names(&["VK_KHR_swapchain"]));
+    assert_eq!(missing, names(&["VK_KHR_dynamic_rendering"]));
+}
+
+#[test]
+fn missing_optional_names_are_dropped() {
+    let (enabled, missing) = select_available(
+        &["VK_KHR_surface"],
+        &["VK_EXT_debug_utils", "VK_EXT_swapchain_colorspace"],
+        &names(&["VK_KHR_surface", "VK_EXT_debug_utils"]),
+    );
+
+    assert_eq!(enabled, names(&["VK_KHR_surface", "VK_EXT_debug_utils"]));
+    assert!(missing.is_empty());
+}
+
+#[test]
+fn instance_report_lists_layers_and_extensions() {
+    let requirements = InstanceRequirements::new()
+        .require_layer("VK_LAYER_KHRONOS_validation")
+        .request_layer("VK_LAYER_MESA_overlay")
+        .require_extension("VK_KHR_surface")
+        .require_extension("VK_KHR_wayland_surface");
+
+    match requirements.check(&[], &names(&["VK_KHR_surface"])) {
+        Err(PotatoError::MissingCapabilities(report)) => assert_eq!(
+            report,
+            CapabilityReport {
+                missing_layers: names(&["VK_LAYER_KHRONOS_validation"]),
+                missing_instance_extensions: names(&["VK_KHR_wayland_surface"]),
+                ..Default::default()
+            }
+        ),
+        x => panic!("Expected missing capabilities, got {:?}", x),
+    }
+}
+
+#[test]
+fn instance_check_enables_what_is_available() {
+    let requirements = InstanceRequirements::new()
+        .request_layer("VK_LAYER_KHRONOS_validation")
+        .require_extension("VK_KHR_surface")
+        .request_extension("VK_EXT_debug_utils");
+
+    let enabled = requirements
+        .check(&[], &names(&["VK_KHR_surface", "VK_EXT_debug_utils"]))
+        .unwrap();
+
+    assert!(!enabled.has_layer("VK_LAYER_KHRONOS_validation"));
+    assert!(enabled.has_extension("VK_KHR_surface"));
+    assert!(enabled.has_extension("VK_EXT_debug_utils"));
+}
+
+#[test]
+fn device_report_lists_extensions_and_features() {
+    let requirements = DeviceRequirements::new()
+        .require_feature(DeviceFeature::FillModeNonSolid)
+        .require_extension("VK_KHR_swapchain")
+        .require_extension("VK_EXT_mesh_shader");
+
+    match requirements.negotiate(&[], &names(&["VK_KHR_swapchain"])) {
+        Err(PotatoError::MissingCapabilities(report)) => {
+            assert_eq!(
+                report.missing_device_extensions,
+                names(&["VK_EXT_mesh_shader"])
+            );
+            assert_eq!(report.missing_device_features, names(&["fillModeNonSolid"]));
+            assert!(report.missing_layers.is_empty());
+            assert_eq!(
+                report.to_string(),
+                "device extensions VK_EXT_mesh_shader; device features fillModeNonSolid"
+            );
+        }
+        x => panic!("Expected missing capabilities, got {:?}", x),
+    }
+}
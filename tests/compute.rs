@@ -0,0 +1,83 @@
+use potato::ComputeApiObjects;
+
+//Writes every input value doubled to the output, 64 values per group
+const DOUBLE_SHADER: &str = "src/shaders/spv/double-comp.spv";
+const VALUE_COUNT: u32 = 256;
+
+fn group_count() -> [u32; 3] {
+    [VALUE_COUNT / 64, 1, 1]
+}
+
+#[test]
+fn dispatch_writes_results_to_storage_buffer() {
+    let mut compute_api_objects =
+        ComputeApiObjects::init().expect("Failed to init compute api objects");
+    let size = (VALUE_COUNT as usize * std::mem::size_of::<u32>()) as u64;
+    let input = compute_api_objects.create_storage_buffer(size).unwrap();
+    let output = compute_api_objects.create_storage_buffer(size).unwrap();
+
+    let values: Vec<u32> = (0..VALUE_COUNT).collect();
+    compute_api_objects
+        .write_storage_buffer(&input, &values)
+        .unwrap();
+    compute_api_objects
+        .write_storage_buffer(&output, &vec![0u32; VALUE_COUNT as usize])
+        .unwrap();
+
+    compute_api_objects
+        .dispatch(DOUBLE_SHADER, &[&input, &output], group_count())
+        .unwrap();
+
+    let expected: Vec<u32> = values.iter().map(|x| x * 2).collect();
+    let actual: Vec<u32> = compute_api_objects.read_storage_buffer(&output).unwrap();
+    assert_eq!(actual, expected);
+    let unchanged: Vec<u32> = compute_api_objects.read_storage_buffer(&input).unwrap();
+    assert_eq!(unchanged, values);
+
+    compute_api_objects.destroy_storage_buffer(input);
+    compute_api_objects.destroy_storage_buffer(output);
+}
+
+#[test]
+fn repeated_dispatches_reuse_the_pipeline() {
+    let mut compute_api_objects =
+        ComputeApiObjects::init().expect("Failed to init compute api objects");
+    let size = (VALUE_COUNT as usize * std::mem::size_of::<u32>()) as u64;
+    let first = compute_api_objects.create_storage_buffer(size).unwrap();
+    let second = compute_api_objects.create_storage_buffer(size).unwrap();
+
+    let values: Vec<u32> = (0..VALUE_COUNT).collect();
+    compute_api_objects
+        .write_storage_buffer(&first, &values)
+        .unwrap();
+
+    compute_api_objects
+        .dispatch(DOUBLE_SHADER, &[&first, &second], group_count())
+        .unwrap();
+    compute_api_objects
+        .dispatch(DOUBLE_SHADER, &[&second, &first], group_count())
+        .unwrap();
+
+    let expected: Vec<u32> = values.iter().map(|x| x * 4).collect();
+    let actual: Vec<u32> = compute_api_objects.read_storage_buffer(&first).unwrap();
+    assert_eq!(actual, expected);
+
+    compute_api_objects.destroy_storage_buffer(first);
+    compute_api_objects.destroy_storage_buffer(second);
+}
+
+#[test]
+fn missing_shader_is_an_error() {
+    let mut compute_api_objects =
+        ComputeApiObjects::init().expect("Failed to init compute api objects");
+    let storage_buffer = compute_api_objects.create_storage_buffer(64).unwrap();
+
+    let result = compute_api_objects.dispatch(
+        "src/shaders/spv/missing-comp.spv",
+        &[&storage_buffer],
+        [1, 1, 1],
+    );
+
+    assert!(result.is_err());
+    compute_api_objects.destroy_storage_buffer(storage_buffer);
+}
@@ -0,0 +1,32 @@
+use ash::vk::{Buffer, CommandBuffer, Handle, Queue, Semaphore};
+use potato::PotatoDebugUtils;
+
+#[test]
+fn disabled_debug_utils_never_call_the_driver() {
+    let debug_utils = PotatoDebugUtils::disabled();
+    assert!(!debug_utils.is_enabled());
+
+    //Any call reaching the loader would go through a null device and crash
+    debug_utils.name_object(Buffer::from_raw(1), "Vertex buffer");
+    debug_utils.name_objects(
+        &[Semaphore::from_raw(2), Semaphore::from_raw(3)],
+        "Image available semaphore",
+    );
+    {
+        let command_buffer = CommandBuffer::from_raw(4);
+        let _frame_label = debug_utils.begin_command_label(command_buffer, "Frame 0");
+        debug_utils.insert_command_label(command_buffer, "Draw mesh");
+    }
+    {
+        let queue = Queue::from_raw(5);
+        let _submit_label = debug_utils.begin_queue_label(queue, "Frame 0");
+        debug_utils.insert_queue_label(queue, "Present");
+    }
+}
+
+#[test]
+fn names_with_a_nul_are_skipped() {
+    let debug_utils = PotatoDebugUtils::disabled();
+    debug_utils.name_object(Buffer::from_raw(1), "Vertex\0buffer");
+    let _label = debug_utils.begin_command_label(CommandBuffer::from_raw(2), "Frame\0");
+}
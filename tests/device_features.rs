@@ -0,0 +1,93 @@
+use potato::{DeviceFeature, DeviceRequirements, PotatoError};
+
+fn extensions(names: &[&str]) -> Vec<String> {
+    names.iter().map(|x| x.to_string()).collect()
+}
+
+#[test]
+fn optional_items_are_dropped_when_unsupported() {
+    let requirements = DeviceRequirements::new()
+        .require_feature(DeviceFeature::SamplerAnisotropy)
+        .request_feature(DeviceFeature::FillModeNonSolid)
+        .request_feature(DeviceFeature::DescriptorIndexing)
+        .require_extension("VK_KHR_swapchain")
+        .request_extension("VK_EXT_memory_budget");
+
+    let enabled = requirements
+        .negotiate(
+            &[
+                DeviceFeature::SamplerAnisotropy,
+                DeviceFeature::DescriptorIndexing,
+            ],
+            &extensions(&["VK_KHR_swapchain"]),
+        )
+        .unwrap();
+
+    assert!(enabled.has_feature(DeviceFeature::SamplerAnisotropy));
+    assert!(enabled.has_feature(DeviceFeature::DescriptorIndexing));
+    assert!(!enabled.has_feature(DeviceFeature::FillModeNonSolid));
+    assert!(enabled.has_extension("VK_KHR_swapchain"));
+    assert!(!enabled.has_extension("VK_EXT_memory_budget"));
+}
+
+#[test]
+fn missing_required_feature_is_an_error() {
+    let requirements = DeviceRequirements::new()
+        .require_feature(DeviceFeature::TimelineSemaphore)
+        .require_feature(DeviceFeature::SamplerAnisotropy);
+
+    match requirements.negotiate(&[DeviceFeature::SamplerAnisotropy], &[]) {
+        Err(PotatoError::MissingCapabilities(report)) => {
+            assert_eq!(
+                report.missing_device_features,
+                vec!["timelineSemaphore".to_string()]
+            )
+        }
+        x => panic!("Expected missing features, got {:?}", x),
+    }
+}
+
+#[test]
+fn missing_required_extension_is_an_error() {
+    let requirements = DeviceRequirements::new().require_extension("VK_KHR_swapchain");
+
+    match requirements.negotiate(&[], &extensions(&["VK_EXT_memory_budget"])) {
+        Err(PotatoError::MissingCapabilities(report)) => {
+            assert_eq!(
+                report.missing_device_extensions,
+                extensions(&["VK_KHR_swapchain"])
+            )
+        }
+        x => panic!("Expected missing extensions, got {:?}", x),
+    }
+}
+
+#[test]
+fn requiring_an_optional_item_moves_it() {
+    let requirements = DeviceRequirements::new()
+        .request_feature(DeviceFeature::WideLines)
+        .require_feature(DeviceFeature::WideLines)
+        .request_feature(DeviceFeature::WideLines)
+        .request_extension("VK_EXT_memory_budget")
+        .require_extension("VK_EXT_memory_budget");
+
+    assert_eq!(
+        requirements.required_features,
+        vec![DeviceFeature::WideLines]
+    );
+    assert!(requirements.optional_features.is_empty());
+    assert_eq!(
+        requirements.required_extensions,
+        extensions(&["VK_EXT_memory_budget"])
+    );
+    assert!(requirements.optional_extensions.is_empty());
+}
+
+#[test]
+fn features_report_their_struct_version() {
+    assert_eq!(DeviceFeature::FillModeNonSolid.minor_version(), 0);
+    assert_eq!(DeviceFeature::ShaderDrawParameters.minor_version(), 1);
+    assert_eq!(DeviceFeature::DescriptorIndexing.minor_version(), 2);
+    assert_eq!(DeviceFeature::DynamicRendering.minor_version(), 3);
+    assert_eq!(DeviceFeature::ALL.len(), 21);
+}
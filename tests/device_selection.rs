@@ -0,0 +1,104 @@
+use ash::vk::{make_api_version, PhysicalDeviceType};
+use potato::{pick_device, DeviceCandidate, DeviceSelector};
+
+const GIB: u64 = 1 << 30;
+
+fn candidate(index: usize, name: &str, device_type: PhysicalDeviceType) -> DeviceCandidate {
+    DeviceCandidate {
+        index,
+        name: name.to_string(),
+        vendor_id: 0x8086,
+        device_id: 0x4680 + index as u32,
+        device_type,
+        api_version: make_api_version(0, 1, 3, 0),
+        device_local_memory: 2 * GIB,
+        optional_features: 1,
+        optional_extensions: 0,
+        is_suitable: true,
+    }
+}
+
+fn dual_gpu() -> Vec<DeviceCandidate> {
+    vec![
+        candidate(
+            0,
+            "Intel UHD Graphics 770",
+            PhysicalDeviceType::INTEGRATED_GPU,
+        ),
+        DeviceCandidate {
+            vendor_id: 0x10de,
+            device_id: 0x2684,
+            device_local_memory: 24 * GIB,
+            ..candidate(
+                1,
+                "NVIDIA GeForce RTX 4090",
+                PhysicalDeviceType::DISCRETE_GPU,
+            )
+        },
+    ]
+}
+
+#[test]
+fn discrete_gpu_outscores_integrated_gpu_enumerated_first() {
+    assert_eq!(pick_device(&dual_gpu(), None).unwrap(), 1);
+}
+
+#[test]
+fn unsuitable_devices_are_never_picked() {
+    let mut candidates = dual_gpu();
+    candidates[1].is_suitable = false;
+
+    assert_eq!(candidates[1].score(), None);
+    assert_eq!(pick_device(&candidates, None).unwrap(), 0);
+
+    candidates[0].is_suitable = false;
+    assert!(pick_device(&candidates, None).is_err());
+}
+
+#[test]
+fn memory_and_api_version_break_ties_between_same_type() {
+    let mut candidates = vec![
+        candidate(0, "Small", PhysicalDeviceType::DISCRETE_GPU),
+        candidate(1, "Large", PhysicalDeviceType::DISCRETE_GPU),
+    ];
+    candidates[1].device_local_memory = 8 * GIB;
+    assert_eq!(pick_device(&candidates, None).unwrap(), 1);
+
+    candidates[1].device_local_memory = 2 * GIB;
+    candidates[0].api_version = make_api_version(0, 1, 1, 0);
+    assert_eq!(pick_device(&candidates, None).unwrap(), 1);
+
+    //Identical devices keep enumeration order
+    candidates[0].api_version = candidates[1].api_version;
+    assert_eq!(pick_device(&candidates, None).unwrap(), 0);
+}
+
+#[test]
+fn selector_overrides_the_score() {
+    let candidates = dual_gpu();
+
+    let by_index: DeviceSelector = "0".parse().unwrap();
+    let by_name: DeviceSelector = "uhd".parse().unwrap();
+    let by_id: DeviceSelector = "8086:4680".parse().unwrap();
+
+    assert_eq!(by_index, DeviceSelector::Index(0));
+    assert_eq!(by_name, DeviceSelector::Name("uhd".to_string()));
+    assert_eq!(
+        by_id,
+        DeviceSelector::Id {
+            vendor_id: 0x8086,
+            device_id: 0x4680
+        }
+    );
+    for selector in [by_index, by_name, by_id] {
+        assert_eq!(pick_device(&candidates, Some(&selector)).unwrap(), 0);
+    }
+}
+
+#[test]
+fn selector_that_matches_nothing_is_an_error() {
+    let selector: DeviceSelector = "radeon".parse().unwrap();
+
+    assert!(pick_device(&dual_gpu(), Some(&selector)).is_err());
+    assert!("".parse::<DeviceSelector>().is_err());
+}
@@ -0,0 +1,119 @@
+use potato::{
+    available_recording_threads, split_draws, GraphicsConfig, Mesh, OffscreenApiObjects,
+    OffscreenConfig, PotatoError, RecordingThreads,
+};
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread::ThreadId;
+use std::time::Duration;
+
+#[test]
+fn draws_are_split_into_contiguous_ranges_covering_all_draws() {
+    let ranges = split_draws(10, 3);
+
+    assert_eq!(ranges, vec![0..3, 3..6, 6..10]);
+}
+
+#[test]
+fn no_range_is_empty_when_there_are_fewer_draws_than_threads() {
+    assert_eq!(split_draws(2, 8), vec![0..1, 1..2]);
+    assert!(split_draws(0, 8).is_empty());
+}
+
+#[test]
+fn zero_threads_records_on_one() {
+    assert_eq!(split_draws(5, 0), vec![0..5]);
+}
+
+#[test]
+fn at_least_one_recording_thread_is_available() {
+    assert!(available_recording_threads() >= 1);
+}
+
+#[test]
+fn frames_are_recorded_inline_by_default() {
+    assert_eq!(GraphicsConfig::default().recording_threads, 1);
+    assert_eq!(OffscreenConfig::default().recording_threads, 1);
+}
+
+#[test]
+fn recording_threads_are_reused_across_runs() {
+    let recording_threads = RecordingThreads::new(3).unwrap();
+    let thread_ids = || {
+        let jobs: Vec<Box<dyn FnOnce() -> ThreadId + Send>> = (0..3)
+            .map(|_| Box::new(|| std::thread::current().id()) as _)
+            .collect();
+        recording_threads.run(jobs)
+    };
+
+    let first = thread_ids();
+    let second = thread_ids();
+
+    assert_eq!(first, second);
+    assert!(!first.contains(&std::thread::current().id()));
+    assert!(first[0] != first[1] && first[1] != first[2] && first[0] != first[2]);
+}
+
+#[test]
+fn recording_jobs_borrow_from_the_caller() {
+    let recording_threads = RecordingThreads::new(2).unwrap();
+    let values = [1, 2, 3, 4];
+    let sums = AtomicUsize::new(0);
+
+    let jobs: Vec<Box<dyn FnOnce() -> usize + Send + '_>> = values
+        .chunks(2)
+        .map(|x| {
+            let sums = &sums;
+            Box::new(move || {
+                let sum = x.iter().sum();
+                sums.fetch_add(sum, Ordering::SeqCst);
+                sum
+            }) as _
+        })
+        .collect();
+
+    assert_eq!(recording_threads.run(jobs), vec![3, 7]);
+    assert_eq!(sums.load(Ordering::SeqCst), 10);
+}
+
+#[test]
+fn a_panicking_job_is_resumed_once_all_jobs_finished() {
+    let recording_threads = RecordingThreads::new(2).unwrap();
+    let finished = AtomicUsize::new(0);
+
+    let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+        let jobs: Vec<Box<dyn FnOnce() + Send + '_>> = vec![
+            Box::new(|| panic!("Recording failed")),
+            Box::new(|| {
+                std::thread::sleep(Duration::from_millis(50));
+                finished.fetch_add(1, Ordering::SeqCst);
+            }),
+        ];
+        recording_threads.run(jobs);
+    }));
+
+    assert!(result.is_err());
+    assert_eq!(finished.load(Ordering::SeqCst), 1);
+    let jobs: Vec<Box<dyn FnOnce() -> u32 + Send>> = vec![Box::new(|| 5)];
+    assert_eq!(recording_threads.run(jobs), vec![5]);
+}
+
+#[test]
+fn one_recording_thread_starts_none() {
+    assert_eq!(RecordingThreads::new(1).unwrap().thread_count(), 0);
+    assert_eq!(RecordingThreads::new(4).unwrap().thread_count(), 4);
+}
+
+#[test]
+fn offscreen_targets_without_area_are_rejected() {
+    for (width, height) in [(0, 64), (64, 0)] {
+        let result = OffscreenApiObjects::init_with_config(
+            width,
+            height,
+            &Mesh::quad(),
+            None,
+            &OffscreenConfig::default(),
+        );
+        assert!(matches!(result, Err(PotatoError::InvalidSetting { .. })));
+    }
+}
@@ -0,0 +1,58 @@
+use ash::vk::SurfaceCapabilitiesKHR;
+use potato::{choose_swapchain_image_count, ImagesInFlight};
+
+fn capabilities(min_image_count: u32, max_image_count: u32) -> SurfaceCapabilitiesKHR {
+    SurfaceCapabilitiesKHR {
+        min_image_count,
+        max_image_count,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn image_count_is_one_above_the_minimum() {
+    assert_eq!(choose_swapchain_image_count(&capabilities(2, 8)), 3);
+    assert_eq!(choose_swapchain_image_count(&capabilities(3, 0)), 4);
+}
+
+#[test]
+fn image_count_never_exceeds_the_maximum() {
+    assert_eq!(choose_swapchain_image_count(&capabilities(3, 3)), 3);
+}
+
+#[test]
+fn unused_image_needs_no_wait() {
+    let mut images_in_flight = ImagesInFlight::new(3);
+
+    assert_eq!(images_in_flight.claim(0, 0), None);
+    assert_eq!(images_in_flight.claim(1, 1), None);
+}
+
+#[test]
+fn image_still_used_by_another_frame_waits_for_it() {
+    let mut images_in_flight = ImagesInFlight::new(2);
+    images_in_flight.claim(1, 0);
+
+    assert_eq!(images_in_flight.claim(1, 1), Some(0));
+    assert_eq!(images_in_flight.claim(1, 0), Some(1));
+}
+
+#[test]
+fn frame_reusing_its_own_image_needs_no_extra_wait() {
+    let mut images_in_flight = ImagesInFlight::new(2);
+    images_in_flight.claim(0, 1);
+
+    assert_eq!(images_in_flight.claim(0, 1), None);
+}
+
+#[test]
+fn reset_forgets_the_old_images() {
+    let mut images_in_flight = ImagesInFlight::new(2);
+    images_in_flight.claim(0, 0);
+    images_in_flight.claim(1, 1);
+
+    images_in_flight.reset(4);
+
+    assert_eq!(images_in_flight.image_count(), 4);
+    assert_eq!(images_in_flight.claim(1, 0), None);
+}
@@ -0,0 +1,221 @@
+use ash::vk::{Filter, SamplerAddressMode};
+use potato::{load_gltf, Projection};
+use std::path::{Path, PathBuf};
+
+const POSITIONS: [[f32; 3]; 4] = [
+    [-1.0, -1.0, 0.0],
+    [1.0, -1.0, 0.0],
+    [1.0, 1.0, 0.0],
+    [-1.0, 1.0, 0.0],
+];
+const INDICES: [u16; 6] = [0, 1, 2, 2, 3, 0];
+
+//A parent node with a camera and a scaled child that draws one mesh with two primitives
+fn scene_json(buffer_uri: Option<&str>, with_texture: bool) -> String {
+    let buffer = match buffer_uri {
+        Some(uri) => format!(r#"{{"byteLength": 60, "uri": "{}"}}"#, uri),
+        None => r#"{"byteLength": 60}"#.to_string(),
+    };
+    let (textures, texture_refs) = if with_texture {
+        (
+            r#""images": [{"uri": "checker.png", "name": "checker"}],
+            "samplers": [{"magFilter": 9728, "wrapS": 33071, "wrapT": 33648}],
+            "textures": [{"source": 0, "sampler": 0}],"#,
+            r#""baseColorTexture": {"index": 0},"#,
+        )
+    } else {
+        ("", "")
+    };
+
+    format!(
+        r#"{{
+    "asset": {{"version": "2.0"}},
+    "scene": 0,
+    "scenes": [{{"nodes": [0]}}],
+    "nodes": [
+        {{"name": "root", "translation": [1.0, 0.0, 0.0], "children": [1, 2]}},
+        {{"name": "quad", "scale": [2.0, 2.0, 2.0], "mesh": 0}},
+        {{"name": "eye", "camera": 0}},
+        {{"name": "detached", "mesh": 0}}
+    ],
+    "cameras": [{{"type": "perspective", "perspective": {{"yfov": 0.8, "znear": 0.1}}}}],
+    "meshes": [{{"name": "quad", "primitives": [
+        {{"attributes": {{"POSITION": 0}}, "indices": 1, "material": 0}},
+        {{"attributes": {{"POSITION": 0}}, "indices": 2, "material": 1}}
+    ]}}],
+    "materials": [
+        {{"name": "red", "pbrMetallicRoughness": {{
+            {}
+            "baseColorFactor": [1.0, 0.0, 0.0, 1.0],
+            "metallicFactor": 0.25,
+            "roughnessFactor": 0.5
+        }}}},
+        {{"name": "plain"}}
+    ],
+    {}
+    "buffers": [{}],
+    "bufferViews": [
+        {{"buffer": 0, "byteOffset": 0, "byteLength": 48, "target": 34962}},
+        {{"buffer": 0, "byteOffset": 48, "byteLength": 12, "target": 34963}}
+    ],
+    "accessors": [
+        {{"bufferView": 0, "componentType": 5126, "count": 4, "type": "VEC3",
+          "min": [-1.0, -1.0, 0.0], "max": [1.0, 1.0, 0.0]}},
+        {{"bufferView": 1, "byteOffset": 0, "componentType": 5123, "count": 3, "type": "SCALAR"}},
+        {{"bufferView": 1, "byteOffset": 6, "componentType": 5123, "count": 3, "type": "SCALAR"}}
+    ]
+}}"#,
+        texture_refs, textures, buffer
+    )
+}
+
+fn buffer_bytes() -> Vec<u8> {
+    let mut bytes = vec![];
+    for position in POSITIONS.iter().flatten() {
+        bytes.extend_from_slice(&position.to_le_bytes());
+    }
+    for index in INDICES {
+        bytes.extend_from_slice(&index.to_le_bytes());
+    }
+    bytes
+}
+
+fn fixture_dir(name: &str) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    std::fs::create_dir_all(&dir).expect("Failed to create fixture directory");
+    dir
+}
+
+fn write_gltf_fixture(dir: &Path) -> PathBuf {
+    std::fs::write(dir.join("quad.bin"), buffer_bytes()).expect("Failed to write bin fixture");
+    let checker = image::RgbaImage::from_fn(2, 2, |x, y| {
+        if (x + y) % 2 == 0 {
+            image::Rgba([255, 255, 255, 255])
+        } else {
+            image::Rgba([0, 0, 0, 255])
+        }
+    });
+    checker
+        .save(dir.join("checker.png"))
+        .expect("Failed to write png fixture");
+    std::fs::write(dir.join("quad.gltf"), scene_json(Some("quad.bin"), true))
+        .expect("Failed to write gltf fixture");
+    dir.join("quad.gltf")
+}
+
+//Chunks are padded to 4 bytes, JSON with spaces and BIN with zeros
+fn write_glb_fixture(dir: &Path) -> PathBuf {
+    let mut json = scene_json(None, false).into_bytes();
+    json.resize(json.len().div_ceil(4) * 4, b' ');
+    let mut bin = buffer_bytes();
+    bin.resize(bin.len().div_ceil(4) * 4, 0);
+
+    let mut glb = vec![];
+    glb.extend_from_slice(b"glTF");
+    glb.extend_from_slice(&2u32.to_le_bytes());
+    glb.extend_from_slice(&((12 + 8 + json.len() + 8 + bin.len()) as u32).to_le_bytes());
+    glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+    glb.extend_from_slice(b"JSON");
+    glb.extend_from_slice(&json);
+    glb.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+    glb.extend_from_slice(b"BIN\0");
+    glb.extend_from_slice(&bin);
+
+    std::fs::write(dir.join("quad.glb"), glb).expect("Failed to write glb fixture");
+    dir.join("quad.glb")
+}
+
+#[test]
+fn primitives_keep_their_own_indices_and_materials() {
+    let scene = load_gltf(write_gltf_fixture(&fixture_dir("gltf_primitives"))).unwrap();
+
+    assert_eq!(scene.meshes.len(), 1);
+    let primitives = &scene.meshes[0].primitives;
+    assert_eq!(primitives.len(), 2);
+    assert_eq!(primitives[0].mesh.indices, [0, 1, 2]);
+    assert_eq!(primitives[1].mesh.indices, [2, 3, 0]);
+    assert_eq!(primitives[0].material, Some(0));
+    assert_eq!(primitives[1].material, Some(1));
+    //Without COLOR_0 vertices take the base color of their material
+    assert_eq!(primitives[0].mesh.vertices[0].color, [1.0, 0.0, 0.0]);
+    assert_eq!(primitives[1].mesh.vertices[0].color, [1.0, 1.0, 1.0]);
+}
+
+#[test]
+fn materials_and_textures_are_converted() {
+    let scene = load_gltf(write_gltf_fixture(&fixture_dir("gltf_materials"))).unwrap();
+
+    let red = &scene.materials[0];
+    assert_eq!(red.name, "red");
+    assert_eq!(red.base_color_factor, [1.0, 0.0, 0.0, 1.0]);
+    assert_eq!(red.metallic_factor, 0.25);
+    assert_eq!(red.roughness_factor, 0.5);
+    assert_eq!(red.base_color_texture, Some(0));
+    assert_eq!(scene.materials[1].metallic_factor, 1.0);
+
+    let texture = scene.textures[0];
+    assert_eq!(texture.image, 0);
+    assert_eq!(texture.mag_filter, Filter::NEAREST);
+    assert_eq!(texture.address_mode_u, SamplerAddressMode::CLAMP_TO_EDGE);
+    assert_eq!(texture.address_mode_v, SamplerAddressMode::MIRRORED_REPEAT);
+
+    let image = &scene.images[0];
+    assert_eq!((image.width, image.height), (2, 2));
+    assert_eq!(image.pixels.len(), 16);
+    assert_eq!(&image.pixels[4..8], [0, 0, 0, 255]);
+}
+
+#[test]
+fn hierarchy_transforms_reach_draws_and_cameras() {
+    let scene = load_gltf(write_gltf_fixture(&fixture_dir("gltf_hierarchy"))).unwrap();
+
+    assert_eq!(scene.roots, [0]);
+    assert_eq!(scene.nodes[0].children, [1, 2]);
+
+    //The detached node is not part of the scene and is not drawn
+    let draws = scene.draw_list();
+    assert_eq!(draws.len(), 2);
+    assert!(draws.iter().all(|x| x.node == 1));
+
+    let cameras = scene.camera_list();
+    assert_eq!(cameras.len(), 1);
+    assert_eq!(cameras[0].0, 0);
+    assert_eq!(cameras[0].1.w.x, 1.0);
+    assert_eq!(
+        scene.cameras[0].projection,
+        Projection::Perspective {
+            yfov: 0.8,
+            aspect_ratio: None,
+            znear: 0.1,
+            zfar: None,
+        }
+    );
+
+    let mesh = scene.flatten();
+    assert_eq!(mesh.vertices.len(), 8);
+    assert_eq!(mesh.indices, [0, 1, 2, 6, 7, 4]);
+    assert_eq!(mesh.vertices[0].pos, [-1.0, -2.0, 0.0]);
+    assert_eq!(mesh.vertices[2].pos, [3.0, 2.0, 0.0]);
+}
+
+#[test]
+fn binary_glb_matches_gltf() {
+    let dir = fixture_dir("gltf_binary");
+    let scene = load_gltf(write_glb_fixture(&dir)).unwrap();
+
+    assert_eq!(scene.meshes[0].primitives.len(), 2);
+    assert_eq!(scene.draw_list().len(), 2);
+    assert!(scene.images.is_empty());
+    let positions: Vec<[f32; 3]> = scene.meshes[0].primitives[0]
+        .mesh
+        .vertices
+        .iter()
+        .map(|x| x.pos)
+        .collect();
+    assert_eq!(positions, POSITIONS);
+}
+
+#[test]
+fn missing_file_is_an_error() {
+    assert!(load_gltf(fixture_dir("gltf_missing").join("missing.gltf")).is_err());
+}
@@ -0,0 +1,52 @@
+mod harness;
+
+use harness::{assert_golden, compare_images, GoldenScene};
+use image::{Rgb, RgbImage};
+
+#[test]
+fn quad_unrotated() {
+    assert_golden(&GoldenScene {
+        name: "quad_unrotated",
+        width: 320,
+        height: 240,
+        delta_time: 0.0,
+        tolerance: 2,
+    });
+}
+
+#[test]
+fn quad_rotated_45_degrees() {
+    assert_golden(&GoldenScene {
+        name: "quad_rotated_45_degrees",
+        width: 320,
+        height: 240,
+        delta_time: 0.5,
+        tolerance: 2,
+    });
+}
+
+#[test]
+fn quad_rotated_90_degrees() {
+    assert_golden(&GoldenScene {
+        name: "quad_rotated_90_degrees",
+        width: 320,
+        height: 240,
+        delta_time: 1.0,
+        tolerance: 2,
+    });
+}
+
+#[test]
+fn compare_images_respects_tolerance() {
+    let expected = RgbImage::from_pixel(4, 4, Rgb([100, 100, 100]));
+    let mut actual = expected.clone();
+    actual.put_pixel(1, 1, Rgb([102, 100, 100]));
+    actual.put_pixel(2, 3, Rgb([100, 90, 100]));
+
+    let comparison = compare_images(&expected, &actual, 2);
+
+    assert_eq!(comparison.mismatched_pixels, 1);
+    assert_eq!(comparison.max_difference, 10);
+    assert_eq!(*comparison.diff.get_pixel(2, 3), Rgb([255, 0, 0]));
+    assert_ne!(*comparison.diff.get_pixel(1, 1), Rgb([255, 0, 0]));
+}
@@ -0,0 +1,216 @@
+use ash::vk;
+use ash::{Device, Entry, Instance};
+use potato::{submit_and_wait, GpuTimeline, PotatoDebugUtils};
+use std::ffi::c_void;
+
+//Needs a Vulkan 1.2 driver with timelineSemaphore such as lavapipe
+//A bare device with one queue, so the timeline is tested without a renderer around it
+struct TimelineDevice {
+    _entry: Entry,
+    instance: Instance,
+    device: Device,
+    queue: vk::Queue,
+    command_pool: vk::CommandPool,
+}
+
+impl TimelineDevice {
+    fn new() -> TimelineDevice {
+        let entry = Entry::linked();
+        let application_info = vk::ApplicationInfo {
+            api_version: vk::API_VERSION_1_2,
+            ..Default::default()
+        };
+        let instance_create_info = vk::InstanceCreateInfo {
+            p_application_info: &application_info,
+            ..Default::default()
+        };
+        let instance = unsafe { entry.create_instance(&instance_create_info, None) }
+            .expect("Failed to create instance");
+
+        let physical_device = unsafe { instance.enumerate_physical_devices() }
+            .unwrap()
+            .into_iter()
+            .find(|x| supports_timeline(&instance, *x))
+            .expect("No device supports timelineSemaphore");
+
+        let queue_priorities = [1.0];
+        let queue_create_infos = [vk::DeviceQueueCreateInfo {
+            queue_family_index: 0,
+            queue_count: 1,
+            p_queue_priorities: queue_priorities.as_ptr(),
+            ..Default::default()
+        }];
+        let timeline_features = vk::PhysicalDeviceTimelineSemaphoreFeatures {
+            timeline_semaphore: vk::TRUE,
+            ..Default::default()
+        };
+        let device_create_info = vk::DeviceCreateInfo {
+            p_next: &timeline_features as *const vk::PhysicalDeviceTimelineSemaphoreFeatures
+                as *const c_void,
+            queue_create_info_count: queue_create_infos.len() as u32,
+            p_queue_create_infos: queue_create_infos.as_ptr(),
+            ..Default::default()
+        };
+        let device = unsafe { instance.create_device(physical_device, &device_create_info, None) }
+            .expect("Failed to create device");
+        let queue = unsafe { device.get_device_queue(0, 0) };
+
+        let command_pool_create_info = vk::CommandPoolCreateInfo {
+            queue_family_index: 0,
+            ..Default::default()
+        };
+        let command_pool = unsafe { device.create_command_pool(&command_pool_create_info, None) }
+            .expect("Failed to create command pool");
+
+        TimelineDevice {
+            _entry: entry,
+            instance,
+            device,
+            queue,
+            command_pool,
+        }
+    }
+
+    fn empty_command_buffer(&self) -> vk::CommandBuffer {
+        let allocate_info = vk::CommandBufferAllocateInfo {
+            command_pool: self.command_pool,
+            level: vk::CommandBufferLevel::PRIMARY,
+            command_buffer_count: 1,
+            ..Default::default()
+        };
+        let command_buffer =
+            unsafe { self.device.allocate_command_buffers(&allocate_info) }.unwrap()[0];
+        unsafe {
+            self.device
+                .begin_command_buffer(command_buffer, &vk::CommandBufferBeginInfo::default())
+                .unwrap();
+            self.device.end_command_buffer(command_buffer).unwrap();
+        }
+        command_buffer
+    }
+
+    fn timeline(&self) -> GpuTimeline {
+        GpuTimeline::new(&self.device, &PotatoDebugUtils::disabled(), "Test timeline")
+            .expect("Failed to create timeline")
+    }
+}
+
+impl Drop for TimelineDevice {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.device_wait_idle().unwrap();
+            self.device.destroy_command_pool(self.command_pool, None);
+            self.device.destroy_device(None);
+            self.instance.destroy_instance(None);
+        }
+    }
+}
+
+fn supports_timeline(instance: &Instance, physical_device: vk::PhysicalDevice) -> bool {
+    let properties = unsafe { instance.get_physical_device_properties(physical_device) };
+    if properties.api_version < vk::API_VERSION_1_2 {
+        return false;
+    }
+    let mut timeline_features = vk::PhysicalDeviceTimelineSemaphoreFeatures::default();
+    let mut features = vk::PhysicalDeviceFeatures2 {
+        p_next: &mut timeline_features as *mut vk::PhysicalDeviceTimelineSemaphoreFeatures
+            as *mut c_void,
+        ..Default::default()
+    };
+    unsafe { instance.get_physical_device_features2(physical_device, &mut features) };
+    timeline_features.timeline_semaphore == vk::TRUE
+}
+
+#[test]
+fn submitted_values_complete_in_order() {
+    let timeline_device = TimelineDevice::new();
+    let device = &timeline_device.device;
+    let timeline = timeline_device.timeline();
+    let command_buffers = [timeline_device.empty_command_buffer()];
+    assert_eq!(timeline.last_value(), 0);
+
+    let first = timeline
+        .submit(
+            device,
+            timeline_device.queue,
+            &command_buffers,
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+    assert_eq!(first, 1);
+    assert_eq!(timeline.last_value(), first);
+    timeline.wait(device, first).unwrap();
+    assert!(timeline.is_complete(device, first).unwrap());
+
+    let second = timeline
+        .submit(
+            device,
+            timeline_device.queue,
+            &command_buffers,
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+    assert_eq!(second, first + 1);
+    assert_eq!(timeline.last_value(), second);
+    assert!(timeline.wait_timeout(device, second, u64::MAX).unwrap());
+    assert!(timeline.completed_value(device).unwrap() >= second);
+
+    timeline.destroy(device);
+}
+
+#[test]
+fn waiting_for_an_unsubmitted_value_times_out() {
+    let timeline_device = TimelineDevice::new();
+    let device = &timeline_device.device;
+    let timeline = timeline_device.timeline();
+    let command_buffers = [timeline_device.empty_command_buffer()];
+
+    let value = timeline
+        .submit(
+            device,
+            timeline_device.queue,
+            &command_buffers,
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+    timeline.wait(device, value).unwrap();
+
+    assert!(!timeline.wait_timeout(device, value + 1, 0).unwrap());
+    assert!(!timeline.is_complete(device, value + 1).unwrap());
+
+    timeline.destroy(device);
+}
+
+#[test]
+fn submit_and_wait_signals_the_next_value() {
+    let timeline_device = TimelineDevice::new();
+    let device = &timeline_device.device;
+    let timeline = timeline_device.timeline();
+
+    for expected_value in 1..=3 {
+        let command_buffer = timeline_device.empty_command_buffer();
+        submit_and_wait(
+            device,
+            timeline_device.queue,
+            command_buffer,
+            Some(&timeline),
+        )
+        .unwrap();
+
+        assert_eq!(timeline.last_value(), expected_value);
+        assert!(timeline.is_complete(device, expected_value).unwrap());
+    }
+
+    //Without a timeline a fence of its own is waited on and no value is used up
+    let command_buffer = timeline_device.empty_command_buffer();
+    submit_and_wait(device, timeline_device.queue, command_buffer, None).unwrap();
+    assert_eq!(timeline.last_value(), 3);
+
+    timeline.destroy(device);
+}
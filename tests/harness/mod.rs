@@ -0,0 +1,144 @@
+use image::{Rgb, RgbImage};
+use potato::{Mesh, OffscreenApiObjects, OffscreenConfig};
+use std::path::{Path, PathBuf};
+
+pub struct GoldenScene {
+    pub name: &'static str,
+    pub width: u32,
+    pub height: u32,
+    //Rotation of the quad, it does not build up across frames
+    pub delta_time: f32,
+    //Largest difference allowed in any channel of a single pixel
+    pub tolerance: u8,
+}
+
+pub struct Comparison {
+    pub mismatched_pixels: usize,
+    pub max_difference: u8,
+    pub diff: RgbImage,
+}
+
+//Renders the scene offscreen and checks the frame against tests/golden/<name>.png.
+//Set POTATO_BLESS=1 to write the rendered frame as the new reference instead.
+pub fn assert_golden(scene: &GoldenScene) {
+    let actual = render_scene(scene);
+    let reference_path = reference_path(scene.name);
+
+    if std::env::var("POTATO_BLESS").is_ok() {
+        std::fs::create_dir_all(reference_path.parent().unwrap()).unwrap();
+        actual
+            .save(&reference_path)
+            .unwrap_or_else(|e| panic!("Failed to bless {:?}: {}", reference_path, e));
+        return;
+    }
+
+    let output_dir = output_dir();
+    let actual_path = output_dir.join(format!("{}-actual.png", scene.name));
+
+    let expected = match image::open(&reference_path) {
+        Ok(x) => x.to_rgb8(),
+        Err(_) => {
+            actual.save(&actual_path).unwrap();
+            panic!(
+                "No reference image at {:?}, rendered frame written to {:?}. Rerun with POTATO_BLESS=1 to accept it.",
+                reference_path, actual_path
+            );
+        }
+    };
+
+    if expected.dimensions() != actual.dimensions() {
+        actual.save(&actual_path).unwrap();
+        panic!(
+            "{}: reference is {:?} but rendered frame is {:?}, see {:?}",
+            scene.name,
+            expected.dimensions(),
+            actual.dimensions(),
+            actual_path
+        );
+    }
+
+    let comparison = compare_images(&expected, &actual, scene.tolerance);
+    if comparison.mismatched_pixels > 0 {
+        let diff_path = output_dir.join(format!("{}-diff.png", scene.name));
+        actual.save(&actual_path).unwrap();
+        comparison.diff.save(&diff_path).unwrap();
+        panic!(
+            "{}: {} pixel(s) differ by up to {} (tolerance {}), see {:?} and {:?}",
+            scene.name,
+            comparison.mismatched_pixels,
+            comparison.max_difference,
+            scene.tolerance,
+            actual_path,
+            diff_path
+        );
+    }
+}
+
+pub fn render_scene(scene: &GoldenScene) -> RgbImage {
+    //Default settings so POTATO_* environment variables cannot change the output
+    let mut offscreen_api_objects = OffscreenApiObjects::init_with_config(
+        scene.width,
+        scene.height,
+        &Mesh::quad(),
+        None,
+        &OffscreenConfig::default(),
+    )
+    .expect("Failed to init offscreen renderer");
+
+    let pixels = offscreen_api_objects
+        .render_frames(1, scene.delta_time)
+        .expect("Failed to render frames");
+
+    let rgb: Vec<u8> = pixels
+        .chunks_exact(4)
+        .flat_map(|x| x[..3].to_owned())
+        .collect();
+    RgbImage::from_raw(scene.width, scene.height, rgb).expect("Rendered frame has wrong size")
+}
+
+//Mismatched pixels are drawn red over a dimmed copy of the expected image
+pub fn compare_images(expected: &RgbImage, actual: &RgbImage, tolerance: u8) -> Comparison {
+    let mut mismatched_pixels = 0;
+    let mut max_difference = 0;
+    let mut diff = RgbImage::new(expected.width(), expected.height());
+
+    for (x, y, expected_pixel) in expected.enumerate_pixels() {
+        let actual_pixel = actual.get_pixel(x, y);
+        let difference = expected_pixel
+            .0
+            .iter()
+            .zip(actual_pixel.0.iter())
+            .map(|(a, b)| a.abs_diff(*b))
+            .max()
+            .unwrap_or(0);
+
+        max_difference = max_difference.max(difference);
+        if difference > tolerance {
+            mismatched_pixels += 1;
+            diff.put_pixel(x, y, Rgb([255, 0, 0]));
+        } else {
+            let luma = expected_pixel.0.iter().map(|x| *x as u32).sum::<u32>() / 3;
+            let dimmed = (luma / 4) as u8;
+            diff.put_pixel(x, y, Rgb([dimmed, dimmed, dimmed]));
+        }
+    }
+
+    Comparison {
+        mismatched_pixels,
+        max_difference,
+        diff,
+    }
+}
+
+fn reference_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+        .join(format!("{}.png", name))
+}
+
+fn output_dir() -> PathBuf {
+    let output_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden");
+    std::fs::create_dir_all(&output_dir).expect("Failed to create golden output directory");
+    output_dir
+}
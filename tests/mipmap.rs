@@ -0,0 +1,93 @@
+use potato::{generate_mip_chain, mip_level_count, MipFilter, TextureImage};
+
+fn checker(size: u32) -> TextureImage {
+    let pixels = (0..size * size)
+        .flat_map(|i| {
+            let value = if (i % size + i / size).is_multiple_of(2) {
+                255
+            } else {
+                0
+            };
+            [value, value, value, 255]
+        })
+        .collect();
+    TextureImage {
+        name: "checker".to_string(),
+        width: size,
+        height: size,
+        pixels,
+    }
+}
+
+#[test]
+fn level_count_reaches_one_texel() {
+    assert_eq!(mip_level_count(1, 1), 1);
+    assert_eq!(mip_level_count(2, 2), 2);
+    assert_eq!(mip_level_count(256, 256), 9);
+    assert_eq!(mip_level_count(300, 20), 9);
+    assert_eq!(mip_level_count(0, 0), 1);
+}
+
+#[test]
+fn chain_halves_every_level_down_to_one_texel() {
+    let source = TextureImage {
+        pixels: vec![255; 5 * 3 * 4],
+        width: 5,
+        height: 3,
+        ..Default::default()
+    };
+
+    let sizes: Vec<(u32, u32)> = generate_mip_chain(&source, MipFilter::Box, false)
+        .iter()
+        .map(|x| (x.width, x.height))
+        .collect();
+    assert_eq!(sizes, [(2, 1), (1, 1)]);
+}
+
+#[test]
+fn box_filter_averages_a_checker() {
+    let levels = generate_mip_chain(&checker(4), MipFilter::Box, false);
+
+    assert_eq!(levels.len(), 2);
+    assert!(levels
+        .iter()
+        .flat_map(|x| x.pixels.chunks_exact(4))
+        .all(|x| x == [128, 128, 128, 255]));
+}
+
+#[test]
+fn srgb_is_averaged_in_linear_space() {
+    let levels = generate_mip_chain(&checker(2), MipFilter::Box, true);
+
+    //Half way between black and white in linear light is 188 once encoded as sRGB
+    assert_eq!(levels[0].pixels, [188, 188, 188, 255]);
+}
+
+#[test]
+fn kaiser_filter_keeps_flat_images_flat() {
+    let source = TextureImage {
+        pixels: [40, 80, 120, 200].repeat(16 * 8),
+        width: 16,
+        height: 8,
+        ..Default::default()
+    };
+
+    let levels = generate_mip_chain(&source, MipFilter::Kaiser, true);
+
+    assert_eq!(levels.len(), 4);
+    assert!(levels
+        .iter()
+        .flat_map(|x| x.pixels.chunks_exact(4))
+        .all(|x| x == [40, 80, 120, 200]));
+}
+
+#[test]
+fn kaiser_filter_removes_checker_aliasing() {
+    let levels = generate_mip_chain(&checker(8), MipFilter::Kaiser, false);
+
+    //A checker is pure Nyquist frequency, a proper low pass leaves an even gray
+    assert!(levels[0]
+        .pixels
+        .chunks_exact(4)
+        .all(|x| x[0].abs_diff(128) <= 16));
+}
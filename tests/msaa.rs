@@ -0,0 +1,46 @@
+use ash::vk::SampleCountFlags;
+use potato::{clamp_sample_count, next_sample_count};
+
+#[test]
+fn clamp_picks_highest_supported_count_not_above_request() {
+    let supported = SampleCountFlags::TYPE_1 | SampleCountFlags::TYPE_2 | SampleCountFlags::TYPE_8;
+
+    assert_eq!(
+        clamp_sample_count(SampleCountFlags::TYPE_4, supported),
+        SampleCountFlags::TYPE_2
+    );
+    assert_eq!(
+        clamp_sample_count(SampleCountFlags::TYPE_64, supported),
+        SampleCountFlags::TYPE_8
+    );
+    assert_eq!(
+        clamp_sample_count(SampleCountFlags::TYPE_8, supported),
+        SampleCountFlags::TYPE_8
+    );
+}
+
+#[test]
+fn clamp_falls_back_to_single_sampling() {
+    assert_eq!(
+        clamp_sample_count(SampleCountFlags::TYPE_4, SampleCountFlags::empty()),
+        SampleCountFlags::TYPE_1
+    );
+}
+
+#[test]
+fn next_count_wraps_past_the_maximum() {
+    let max_samples = SampleCountFlags::TYPE_4;
+
+    assert_eq!(
+        next_sample_count(SampleCountFlags::TYPE_1, max_samples),
+        SampleCountFlags::TYPE_2
+    );
+    assert_eq!(
+        next_sample_count(SampleCountFlags::TYPE_2, max_samples),
+        SampleCountFlags::TYPE_4
+    );
+    assert_eq!(
+        next_sample_count(SampleCountFlags::TYPE_4, max_samples),
+        SampleCountFlags::TYPE_1
+    );
+}
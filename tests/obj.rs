@@ -0,0 +1,76 @@
+use potato::load_obj;
+use std::path::PathBuf;
+
+const QUAD_OBJ: &str = "mtllib quad.mtl
+v -1.0 -1.0 0.0
+v 1.0 -1.0 0.0
+v 1.0 1.0 0.0
+v -1.0 1.0 0.0
+v 0.0 0.0 1.0
+vt 0.0 0.0
+vt 1.0 0.0
+vt 1.0 1.0
+vt 0.0 1.0
+vn 0.0 0.0 1.0
+usemtl red
+f 1/1/1 2/2/1 3/3/1 4/4/1
+usemtl green
+f 1/1/1 2/2/1 5/3/1
+";
+
+const QUAD_MTL: &str = "newmtl red
+Kd 1.0 0.0 0.0
+newmtl green
+Kd 0.0 1.0 0.0
+";
+
+fn write_fixture(name: &str) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    std::fs::create_dir_all(&dir).expect("Failed to create fixture directory");
+    std::fs::write(dir.join("quad.obj"), QUAD_OBJ).expect("Failed to write obj fixture");
+    std::fs::write(dir.join("quad.mtl"), QUAD_MTL).expect("Failed to write mtl fixture");
+    dir.join("quad.obj")
+}
+
+#[test]
+fn polygons_are_triangulated_per_material() {
+    let model = load_obj(write_fixture("triangulate")).unwrap();
+
+    assert_eq!(model.mesh.indices.len(), 9);
+    assert_eq!(model.face_materials.len(), 3);
+    let names: Vec<&str> = model
+        .face_materials
+        .iter()
+        .map(|x| model.materials[x.unwrap()].name.as_str())
+        .collect();
+    assert_eq!(names, ["red", "red", "green"]);
+}
+
+#[test]
+fn shared_corners_are_deduplicated() {
+    let model = load_obj(write_fixture("dedupe")).unwrap();
+
+    //The quad shares two corners between its triangles, the second face is a separate mesh
+    assert_eq!(model.mesh.vertices.len(), 7);
+    assert!(model
+        .mesh
+        .indices
+        .iter()
+        .all(|x| (*x as usize) < model.mesh.vertices.len()));
+}
+
+#[test]
+fn vertices_carry_normals_uvs_and_material_color() {
+    let model = load_obj(write_fixture("attributes")).unwrap();
+    let first = model.mesh.vertices[model.mesh.indices[0] as usize];
+
+    assert_eq!(first.pos, [-1.0, -1.0, 0.0]);
+    assert_eq!(first.normal, [0.0, 0.0, 1.0]);
+    assert_eq!(first.tex_coord, [0.0, 1.0]);
+    assert_eq!(first.color, [1.0, 0.0, 0.0]);
+}
+
+#[test]
+fn missing_file_is_an_error() {
+    assert!(load_obj("does/not/exist.obj").is_err());
+}
@@ -0,0 +1,48 @@
+use potato::{FrameContext, Mesh, OffscreenApiObjects, OffscreenConfig};
+
+const CLEAR_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+
+//Draws the mesh draw_count times through record_parallel_render_pass and returns the last of a few frames
+fn render(recording_threads: usize, draw_count: usize) -> Vec<u8> {
+    let config = OffscreenConfig {
+        recording_threads,
+        ..Default::default()
+    };
+    let mut offscreen_api_objects =
+        OffscreenApiObjects::init_with_config(64, 64, &Mesh::quad(), None, &config)
+            .expect("Failed to init offscreen renderer");
+    offscreen_api_objects.set_record_callback(move |frame: &FrameContext| {
+        frame.record_parallel_render_pass(CLEAR_COLOR, draw_count, |secondary, draws| {
+            for _ in draws {
+                secondary.draw_mesh(secondary.mesh);
+            }
+            Ok(())
+        })
+    });
+
+    offscreen_api_objects
+        .render_frames(3, 0.25)
+        .expect("Failed to render frames")
+}
+
+fn has_drawn_pixels(pixels: &[u8]) -> bool {
+    pixels.chunks_exact(4).any(|x| x[..3] != [0, 0, 0])
+}
+
+#[test]
+fn secondaries_render_the_same_as_inline_recording() {
+    let inline = render(1, 8);
+    let parallel = render(4, 8);
+
+    assert!(has_drawn_pixels(&inline));
+    assert!(parallel == inline, "Parallel recording changed the frame");
+}
+
+#[test]
+fn threads_without_draws_are_left_out() {
+    let inline = render(1, 2);
+    let parallel = render(4, 2);
+
+    assert!(has_drawn_pixels(&parallel));
+    assert!(parallel == inline, "Parallel recording changed the frame");
+}
@@ -0,0 +1,71 @@
+use ash::vk::PresentModeKHR;
+use potato::{choose_present_mode, PresentPolicy};
+
+#[test]
+fn preferred_mode_is_used_when_available() {
+    let available = [
+        PresentModeKHR::FIFO,
+        PresentModeKHR::FIFO_RELAXED,
+        PresentModeKHR::MAILBOX,
+        PresentModeKHR::IMMEDIATE,
+    ];
+
+    assert_eq!(
+        choose_present_mode(&available, PresentPolicy::VsyncOn),
+        PresentModeKHR::FIFO
+    );
+    assert_eq!(
+        choose_present_mode(&available, PresentPolicy::VsyncOff),
+        PresentModeKHR::IMMEDIATE
+    );
+    assert_eq!(
+        choose_present_mode(&available, PresentPolicy::LowLatency),
+        PresentModeKHR::MAILBOX
+    );
+    assert_eq!(
+        choose_present_mode(&available, PresentPolicy::Relaxed),
+        PresentModeKHR::FIFO_RELAXED
+    );
+}
+
+#[test]
+fn unsupported_modes_fall_back_along_the_chain() {
+    let available = [PresentModeKHR::FIFO, PresentModeKHR::MAILBOX];
+
+    assert_eq!(
+        choose_present_mode(&available, PresentPolicy::VsyncOff),
+        PresentModeKHR::MAILBOX
+    );
+    assert_eq!(
+        choose_present_mode(&available, PresentPolicy::Relaxed),
+        PresentModeKHR::FIFO
+    );
+    //FIFO is always supported, even when a surface forgets to report it
+    assert_eq!(
+        choose_present_mode(&[], PresentPolicy::LowLatency),
+        PresentModeKHR::FIFO
+    );
+}
+
+#[test]
+fn policies_parse_from_their_names() {
+    for policy in PresentPolicy::ALL {
+        assert_eq!(policy.to_string().parse::<PresentPolicy>().unwrap(), policy);
+    }
+    assert_eq!(
+        "FIFO-Relaxed".parse::<PresentPolicy>().unwrap(),
+        PresentPolicy::Relaxed
+    );
+    assert!("tearing".parse::<PresentPolicy>().is_err());
+}
+
+#[test]
+fn next_cycles_through_every_policy() {
+    let mut policy = PresentPolicy::default();
+    for _ in 0..PresentPolicy::ALL.len() {
+        policy = policy.next();
+    }
+
+    assert_eq!(policy, PresentPolicy::default());
+    assert_ne!(PresentPolicy::VsyncOn.next(), PresentPolicy::VsyncOn);
+}
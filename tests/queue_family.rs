@@ -0,0 +1,89 @@
+use ash::vk::{QueueFamilyProperties, QueueFlags};
+use potato::{pick_queue_families, QueueFamily};
+
+fn family(queue_flags: QueueFlags) -> QueueFamilyProperties {
+    QueueFamilyProperties {
+        queue_flags,
+        queue_count: 1,
+        ..Default::default()
+    }
+}
+
+fn everything() -> QueueFlags {
+    QueueFlags::GRAPHICS | QueueFlags::COMPUTE | QueueFlags::TRANSFER
+}
+
+#[test]
+fn single_family_does_everything() {
+    let queue_family = pick_queue_families(&[family(everything())], &[true]);
+
+    assert_eq!(
+        queue_family,
+        QueueFamily {
+            graphics_family: Some(0),
+            present_family: Some(0),
+            compute_family: Some(0),
+            transfer_family: Some(0),
+        }
+    );
+    assert!(queue_family.is_complete());
+    assert_eq!(queue_family.unique_families(), vec![0]);
+}
+
+#[test]
+fn dedicated_families_are_preferred() {
+    let queue_families = [
+        family(everything()),
+        family(QueueFlags::COMPUTE | QueueFlags::TRANSFER),
+        family(QueueFlags::TRANSFER),
+    ];
+    let queue_family = pick_queue_families(&queue_families, &[true, false, false]);
+
+    assert_eq!(queue_family.graphics_family, Some(0));
+    assert_eq!(queue_family.present_family, Some(0));
+    assert_eq!(queue_family.compute_family, Some(1));
+    assert_eq!(queue_family.transfer_family, Some(2));
+    assert_eq!(queue_family.unique_families(), vec![0, 1, 2]);
+}
+
+#[test]
+fn transfer_falls_back_to_async_compute() {
+    let queue_families = [
+        family(everything()),
+        family(QueueFlags::COMPUTE | QueueFlags::TRANSFER),
+    ];
+    let queue_family = pick_queue_families(&queue_families, &[true, true]);
+
+    assert_eq!(queue_family.transfer_family, Some(1));
+    assert_eq!(queue_family.unique_families(), vec![0, 1]);
+}
+
+#[test]
+fn present_uses_another_family_when_graphics_cannot() {
+    let queue_families = [family(everything()), family(QueueFlags::TRANSFER)];
+    let queue_family = pick_queue_families(&queue_families, &[false, true]);
+
+    assert_eq!(queue_family.graphics_family, Some(0));
+    assert_eq!(queue_family.present_family, Some(1));
+    assert!(queue_family.is_complete());
+}
+
+#[test]
+fn missing_present_is_incomplete() {
+    let queue_family = pick_queue_families(&[family(everything())], &[false]);
+
+    assert_eq!(queue_family.present_family, None);
+    assert!(!queue_family.is_complete());
+}
+
+#[test]
+fn empty_families_are_skipped() {
+    let empty = QueueFamilyProperties {
+        queue_count: 0,
+        ..family(everything())
+    };
+    let queue_family = pick_queue_families(&[empty, family(everything())], &[true, true]);
+
+    assert_eq!(queue_family.graphics_family, Some(1));
+    assert_eq!(queue_family.present_family, Some(1));
+}
@@ -0,0 +1,67 @@
+use ash::vk::DebugUtilsMessageSeverityFlagsEXT;
+use potato::{
+    clear_validation_sink, set_validation_sink, Mesh, OffscreenApiObjects, OffscreenConfig,
+    ValidationConfig, ValidationMode,
+};
+use std::sync::{Arc, Mutex};
+
+//Needs a software driver such as lavapipe and the Khronos validation layer
+//Pipelines many frames with more frames in flight than targets, any synchronization hazard fails the test
+//The offscreen targets are claimed out of order like swapchain images, so frames wait on the fence or timeline value
+//of whichever frame last rendered to their target. Acquire and present, and with them the per image render finished
+//semaphores of the windowed renderer, need a surface and are not covered here.
+//The validation sink is global to the process, so this stays the only test in its binary and runs the configurations in turn.
+#[test]
+fn paced_frames_have_no_sync_hazards() {
+    let reported = Arc::new(Mutex::new(vec![]));
+    let sink_reported = Arc::clone(&reported);
+    set_validation_sink(move |message| {
+        let is_hazard = message
+            .message_id_name
+            .as_deref()
+            .is_some_and(|x| x.starts_with("SYNC-"));
+        if is_hazard
+            || message
+                .severity
+                .contains(DebugUtilsMessageSeverityFlagsEXT::ERROR)
+        {
+            sink_reported.lock().unwrap().push(message.to_string());
+        }
+    });
+
+    for timeline_semaphores in [false, true] {
+        for target_count in [1, 2, 3] {
+            render_paced_frames(&OffscreenConfig {
+                validation: ValidationConfig {
+                    mode: ValidationMode::Required,
+                    features: "sync".parse().unwrap(),
+                    ..Default::default()
+                },
+                frames_in_flight: 3,
+                target_count,
+                timeline_semaphores,
+                ..Default::default()
+            });
+        }
+    }
+    clear_validation_sink();
+
+    let reported = reported.lock().unwrap();
+    assert!(
+        reported.is_empty(),
+        "Validation reported {} message(s):\n{}",
+        reported.len(),
+        reported.join("\n")
+    );
+}
+
+fn render_paced_frames(config: &OffscreenConfig) {
+    let mut offscreen_api_objects =
+        OffscreenApiObjects::init_with_config(64, 64, &Mesh::quad(), None, config)
+            .expect("Failed to init offscreen renderer");
+    for i in 0..20 {
+        offscreen_api_objects
+            .render_frames(25, i as f32 * 0.05)
+            .expect("Failed to render frames");
+    }
+}
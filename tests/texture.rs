@@ -0,0 +1,58 @@
+use potato::{read_image, TextureImage};
+use std::path::PathBuf;
+
+fn fixture_path(name: &str) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("texture");
+    std::fs::create_dir_all(&dir).expect("Failed to create fixture directory");
+    dir.join(name)
+}
+
+#[test]
+fn png_is_decoded_to_rgba() {
+    let path = fixture_path("gradient.png");
+    image::RgbImage::from_fn(3, 2, |x, y| image::Rgb([x as u8 * 100, y as u8 * 200, 7]))
+        .save(&path)
+        .expect("Failed to write png fixture");
+
+    let texture = read_image(path.to_str().unwrap()).unwrap();
+
+    assert_eq!((texture.width, texture.height), (3, 2));
+    assert_eq!(texture.pixels.len(), 3 * 2 * 4);
+    //Row major from the top left, alpha is filled in for RGB sources
+    assert_eq!(&texture.pixels[0..4], [0, 0, 7, 255]);
+    assert_eq!(
+        &texture.pixels[(3 + 2) * 4..(3 + 2) * 4 + 4],
+        [200, 200, 7, 255]
+    );
+}
+
+#[test]
+fn jpeg_is_decoded_to_rgba() {
+    let path = fixture_path("flat.jpg");
+    image::RgbImage::from_pixel(8, 8, image::Rgb([128, 128, 128]))
+        .save(&path)
+        .expect("Failed to write jpeg fixture");
+
+    let texture = read_image(path.to_str().unwrap()).unwrap();
+
+    assert_eq!((texture.width, texture.height), (8, 8));
+    assert_eq!(texture.pixels.len(), 8 * 8 * 4);
+    //JPEG is lossy, a flat image should still come back close to its color
+    assert!(texture
+        .pixels
+        .chunks_exact(4)
+        .all(|x| x[..3].iter().all(|c| c.abs_diff(128) <= 2) && x[3] == 255));
+}
+
+#[test]
+fn missing_image_is_an_error() {
+    assert!(read_image(fixture_path("missing.png").to_str().unwrap()).is_err());
+}
+
+#[test]
+fn solid_texture_is_one_texel() {
+    let texture = TextureImage::solid([1, 2, 3, 4]);
+
+    assert_eq!((texture.width, texture.height), (1, 1));
+    assert_eq!(texture.pixels, [1, 2, 3, 4]);
+}
@@ -0,0 +1,52 @@
+use potato::{is_timeline_enabled, DeviceFeature, DeviceRequirements, Retirements};
+
+#[test]
+fn timeline_follows_the_negotiated_feature() {
+    let requirements = DeviceRequirements::new().request_feature(DeviceFeature::TimelineSemaphore);
+
+    let enabled = requirements
+        .negotiate(&[DeviceFeature::TimelineSemaphore], &[])
+        .unwrap();
+    assert!(is_timeline_enabled(&enabled));
+
+    let enabled = requirements.negotiate(&[], &[]).unwrap();
+    assert!(!is_timeline_enabled(&enabled));
+}
+
+#[test]
+fn completed_resources_are_taken_in_retire_order() {
+    let mut retirements = Retirements::new();
+    retirements.retire(2, "b");
+    retirements.retire(1, "a");
+    retirements.retire(4, "d");
+    retirements.retire(2, "c");
+
+    assert_eq!(retirements.take_completed(2), vec!["b", "a", "c"]);
+    assert_eq!(retirements.len(), 1);
+}
+
+#[test]
+fn pending_resources_stay_until_their_value_completes() {
+    let mut retirements = Retirements::new();
+    retirements.retire(3, 30);
+    retirements.retire(5, 50);
+
+    assert!(retirements.take_completed(0).is_empty());
+    assert!(retirements.take_completed(2).is_empty());
+    assert_eq!(retirements.len(), 2);
+
+    assert_eq!(retirements.take_completed(4), vec![30]);
+    assert_eq!(retirements.take_completed(5), vec![50]);
+    assert!(retirements.is_empty());
+}
+
+#[test]
+fn take_all_drains_everything() {
+    let mut retirements = Retirements::new();
+    retirements.retire(7, 'x');
+    retirements.retire(9, 'y');
+
+    assert_eq!(retirements.take_all(), vec!['x', 'y']);
+    assert!(retirements.is_empty());
+    assert!(retirements.take_completed(u64::MAX).is_empty());
+}
@@ -0,0 +1,156 @@
+use ash::vk::{
+    DebugUtilsMessageSeverityFlagsEXT, DebugUtilsMessageTypeFlagsEXT, ObjectType,
+    ValidationFeatureEnableEXT,
+};
+use log::Level;
+use potato::{
+    message_level, message_type_names, parse_message_severities, parse_message_types,
+    ValidationConfig, ValidationFeatures, ValidationMessage, ValidationMode, ValidationObject,
+};
+
+#[test]
+fn modes_parse_with_aliases() {
+    assert_eq!(
+        "off".parse::<ValidationMode>().unwrap(),
+        ValidationMode::Off
+    );
+    assert_eq!(
+        "On".parse::<ValidationMode>().unwrap(),
+        ValidationMode::Optional
+    );
+    assert_eq!(
+        " required ".parse::<ValidationMode>().unwrap(),
+        ValidationMode::Required
+    );
+    assert!("sometimes".parse::<ValidationMode>().is_err());
+
+    for mode in [
+        ValidationMode::Off,
+        ValidationMode::Optional,
+        ValidationMode::Required,
+    ] {
+        assert_eq!(mode.to_string().parse::<ValidationMode>().unwrap(), mode);
+    }
+}
+
+#[test]
+fn severities_combine() {
+    assert_eq!(
+        parse_message_severities("error, warning").unwrap(),
+        DebugUtilsMessageSeverityFlagsEXT::ERROR | DebugUtilsMessageSeverityFlagsEXT::WARNING
+    );
+    assert!(parse_message_severities("all")
+        .unwrap()
+        .contains(DebugUtilsMessageSeverityFlagsEXT::VERBOSE));
+    assert!(parse_message_severities("error,loud").is_err());
+}
+
+#[test]
+fn message_types_combine() {
+    assert_eq!(
+        parse_message_types("validation,PERFORMANCE").unwrap(),
+        DebugUtilsMessageTypeFlagsEXT::VALIDATION | DebugUtilsMessageTypeFlagsEXT::PERFORMANCE
+    );
+    assert!(parse_message_types("layout").is_err());
+}
+
+#[test]
+fn features_map_to_enables() {
+    let features: ValidationFeatures = "gpu,sync".parse().unwrap();
+
+    assert!(features.gpu_assisted);
+    assert!(!features.best_practices);
+    assert!(features.synchronization);
+    assert_eq!(
+        features.enables(),
+        vec![
+            ValidationFeatureEnableEXT::GPU_ASSISTED,
+            ValidationFeatureEnableEXT::GPU_ASSISTED_RESERVE_BINDING_SLOT,
+            ValidationFeatureEnableEXT::SYNCHRONIZATION_VALIDATION,
+        ]
+    );
+    assert!("none".parse::<ValidationFeatures>().unwrap().is_empty());
+    assert!("printf".parse::<ValidationFeatures>().is_err());
+}
+
+#[test]
+fn default_never_requires_the_layer() {
+    let validation = ValidationConfig::default();
+
+    assert_ne!(validation.mode, ValidationMode::Required);
+    assert!(validation.features.is_empty());
+    assert_eq!(
+        validation.severities,
+        DebugUtilsMessageSeverityFlagsEXT::ERROR | DebugUtilsMessageSeverityFlagsEXT::WARNING
+    );
+}
+
+#[test]
+fn combined_severities_log_at_the_most_severe_level() {
+    assert_eq!(
+        message_level(DebugUtilsMessageSeverityFlagsEXT::ERROR),
+        Level::Error
+    );
+    assert_eq!(
+        message_level(DebugUtilsMessageSeverityFlagsEXT::WARNING),
+        Level::Warn
+    );
+    assert_eq!(
+        message_level(DebugUtilsMessageSeverityFlagsEXT::INFO),
+        Level::Info
+    );
+    assert_eq!(
+        message_level(DebugUtilsMessageSeverityFlagsEXT::VERBOSE),
+        Level::Trace
+    );
+    assert_eq!(
+        message_level(
+            DebugUtilsMessageSeverityFlagsEXT::INFO | DebugUtilsMessageSeverityFlagsEXT::WARNING
+        ),
+        Level::Warn
+    );
+
+    assert_eq!(
+        message_type_names(
+            DebugUtilsMessageTypeFlagsEXT::VALIDATION | DebugUtilsMessageTypeFlagsEXT::PERFORMANCE
+        ),
+        "Validation|Performance"
+    );
+    assert_eq!(
+        message_type_names(DebugUtilsMessageTypeFlagsEXT::empty()),
+        "Unknown"
+    );
+}
+
+#[test]
+fn messages_include_ids_objects_and_labels() {
+    let message = ValidationMessage {
+        severity: DebugUtilsMessageSeverityFlagsEXT::ERROR,
+        message_types: DebugUtilsMessageTypeFlagsEXT::VALIDATION,
+        message_id_name: Some("VUID-vkCmdDraw-None-02859".to_string()),
+        message_id_number: 0x1b,
+        message: "Image layout mismatch".to_string(),
+        objects: vec![
+            ValidationObject {
+                object_type: ObjectType::IMAGE,
+                handle: 0xab,
+                name: Some("Depth image".to_string()),
+            },
+            ValidationObject {
+                object_type: ObjectType::COMMAND_BUFFER,
+                handle: 0xcd,
+                name: None,
+            },
+        ],
+        command_buffer_labels: vec!["Frame".to_string(), "Main pass".to_string()],
+        queue_labels: vec![],
+    };
+
+    assert_eq!(message.level(), Level::Error);
+    assert_eq!(
+        message.to_string(),
+        "[Validation] VUID-vkCmdDraw-None-02859 (0x1b): Image layout mismatch \
+         | objects: IMAGE 0xab \"Depth image\", COMMAND_BUFFER 0xcd \
+         | command buffer labels: Frame > Main pass"
+    );
+}